@@ -0,0 +1,40 @@
+// Particles drawn by particle_render.rs: a unit octahedron per instance, scaled and placed by the
+// particle's position and size, in the particle's colour
+#import bevy_pbr::{mesh_view_bindings::view, view_transformations::position_world_to_clip}
+#ifdef TONEMAP_IN_SHADER
+#import bevy_core_pipeline::tonemapping::tone_mapping
+#endif
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    // xyz is where the particle is, w its size
+    @location(3) particle: vec4<f32>,
+    @location(4) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = position_world_to_clip(vertex.particle.xyz + vertex.position * vertex.particle.w);
+    out.color = vertex.color;
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    var color = in.color;
+#ifdef TONEMAP_IN_SHADER
+    color = tone_mapping(color, view.color_grading);
+#endif
+#ifdef BLEND_PREMULTIPLIED_ALPHA
+    // Glowing particles add to what's behind them without covering any of it
+    return vec4(color.rgb * color.a, 0.0);
+#else
+    return color;
+#endif
+}
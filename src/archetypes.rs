@@ -0,0 +1,261 @@
+use bevy::prelude::*;
+use bevy::asset::{AssetLoader, LoadContext, LoadedFolder, RecursiveDependencyLoadState, io::Reader};
+use serde::Deserialize;
+use std::sync::Arc;
+use crate::enemies::PilotPersonality;
+use crate::game_state::GameState;
+use crate::models::AircraftModel;
+
+// Under the assets folder; every `*.enemy.ron` file in it is an enemy type
+pub const ENEMY_ARCHETYPE_FOLDER: &str = "enemies";
+
+// Everything that makes one kind of enemy different from another, read from a RON file so new
+// kinds can be added and tuned without rebuilding the game
+#[derive(Asset, TypePath, Debug, Clone, Deserialize)]
+pub struct EnemyArchetype {
+    pub name: String,
+    pub model: AircraftModel,
+    // Colour on the radar and in radio chatter, and of the fireball it goes up in
+    pub hud_color: (f32, f32, f32),
+    pub explosion_color: (f32, f32, f32),
+    pub spawn: SpawnWeights,
+    pub speed: f32,
+    pub health: f32,
+    pub preferred_distance: f32,
+    pub attack_range: f32,
+    pub pursuit_range: f32,
+    // Range the pilot's skill is drawn from, before their personality is taken into account
+    pub maneuver_skill: (f32, f32),
+    // How far ahead of the player it points while attacking, in seconds of the player's flight
+    pub aim_lead: f32,
+    // Relative chances of each personality
+    pub personalities: Vec<(PilotPersonality, f32)>,
+    pub weapon: EnemyWeapon,
+    // Points for shooting it down
+    pub score: u32,
+    pub ramming: Ramming,
+}
+
+// Relative chances of being picked, among all archetypes, for a lone enemy and for a formation.
+// A lone enemy's weight grows by `weight_per_level` for each difficulty level past the first.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpawnWeights {
+    pub weight: f32,
+    pub weight_per_level: f32,
+    pub formation_weight: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EnemyWeapon {
+    pub cooldown: f32,
+    // Largest error in each axis of the aim direction, before personality and skill
+    pub spread: f32,
+    pub bullet_speed: f32,
+    pub damage: f32,
+}
+
+// Flying into it: how close counts, the damage the player takes and the points they get
+#[derive(Debug, Clone, Deserialize)]
+pub struct Ramming {
+    pub radius: f32,
+    pub damage: f32,
+    pub points: u32,
+}
+
+impl EnemyArchetype {
+    pub fn hud_color(&self) -> Color {
+        let (red, green, blue) = self.hud_color;
+        Color::srgb(red, green, blue)
+    }
+
+    pub fn explosion_color(&self) -> Color {
+        let (red, green, blue) = self.explosion_color;
+        Color::srgb(red, green, blue)
+    }
+
+    pub fn spawn_weight(&self, difficulty_level: f32) -> f32 {
+        (self.spawn.weight + self.spawn.weight_per_level * (difficulty_level - 1.0)).max(0.0)
+    }
+}
+
+#[derive(Debug)]
+pub enum EnemyArchetypeError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+}
+
+impl std::fmt::Display for EnemyArchetypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EnemyArchetypeError::Io(err) => write!(f, "i/o error: {err}"),
+            EnemyArchetypeError::Parse(err) => write!(f, "invalid enemy archetype: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for EnemyArchetypeError {}
+
+impl From<std::io::Error> for EnemyArchetypeError {
+    fn from(err: std::io::Error) -> Self {
+        EnemyArchetypeError::Io(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for EnemyArchetypeError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        EnemyArchetypeError::Parse(err)
+    }
+}
+
+#[derive(Default)]
+pub struct EnemyArchetypeLoader;
+
+impl AssetLoader for EnemyArchetypeLoader {
+    type Asset = EnemyArchetype;
+    type Settings = ();
+    type Error = EnemyArchetypeError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<EnemyArchetype, EnemyArchetypeError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["enemy.ron"]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArchetypeStatus {
+    Loading,
+    Ready,
+    Failed(String),
+}
+
+// The enemy archetypes, sorted by name so picking one with the same random number always picks
+// the same one. A run can't start until they've all loaded.
+#[derive(Resource)]
+pub struct EnemyArchetypes {
+    folder: Handle<LoadedFolder>,
+    archetypes: Vec<Arc<EnemyArchetype>>,
+    pub status: ArchetypeStatus,
+    // A file changed during a run; the new values are picked up once it's over
+    reload_pending: bool,
+}
+
+impl EnemyArchetypes {
+    pub fn is_ready(&self) -> bool {
+        self.status == ArchetypeStatus::Ready
+    }
+
+    pub fn all(&self) -> &[Arc<EnemyArchetype>] {
+        &self.archetypes
+    }
+
+    // A lone enemy, with the chances at this difficulty level
+    pub fn pick_spawn(&self, rng: &mut fastrand::Rng, difficulty_level: f32) -> Option<Arc<EnemyArchetype>> {
+        pick_weighted(
+            rng,
+            self.archetypes.iter().map(|archetype| (archetype, archetype.spawn_weight(difficulty_level))),
+        ).cloned()
+    }
+
+    pub fn pick_formation(&self, rng: &mut fastrand::Rng) -> Option<Arc<EnemyArchetype>> {
+        pick_weighted(
+            rng,
+            self.archetypes.iter().map(|archetype| (archetype, archetype.spawn.formation_weight)),
+        ).cloned()
+    }
+
+    // Nothing further away than this can be rammed
+    pub fn largest_ramming_radius(&self) -> f32 {
+        self.archetypes.iter().map(|archetype| archetype.ramming.radius).fold(0.0, f32::max)
+    }
+
+    // Changes whenever any archetype's values do, so a replay can tell whether it was recorded
+    // against the same enemies. FNV-1a over each archetype's Debug text, in name order, since
+    // std's hasher isn't guaranteed to hash the same way from one build to the next.
+    pub fn fingerprint(&self) -> u64 {
+        self.archetypes
+            .iter()
+            .flat_map(|archetype| format!("{archetype:?}").into_bytes())
+            .fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+    }
+}
+
+// One of `items`, each as likely as its share of the total weight. Always takes one number from
+// `rng`, and picks nothing when no item has any weight.
+pub fn pick_weighted<T>(rng: &mut fastrand::Rng, items: impl Iterator<Item = (T, f32)> + Clone) -> Option<T> {
+    let total: f32 = items.clone().map(|(_, weight)| weight.max(0.0)).sum();
+    let mut roll = rng.f32() * total;
+    let mut picked = None;
+    for (item, weight) in items.filter(|(_, weight)| *weight > 0.0) {
+        if roll < weight {
+            return Some(item);
+        }
+        roll -= weight;
+        // Rounding can leave the roll just past the last item
+        picked = Some(item);
+    }
+    picked
+}
+
+pub fn load_enemy_archetypes(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(EnemyArchetypes {
+        folder: asset_server.load_folder(ENEMY_ARCHETYPE_FOLDER),
+        archetypes: Vec::new(),
+        status: ArchetypeStatus::Loading,
+        reload_pending: false,
+    });
+}
+
+// Marks the archetypes ready once every file has loaded, and picks up edits to them after that;
+// with the `dev` feature, the asset server reloads a file when it's saved. Edits made during a run
+// wait until it's over, so one run never mixes old and new values and its replay keeps matching
+// the fingerprint it was recorded with.
+pub fn update_enemy_archetypes(
+    mut archetypes: ResMut<EnemyArchetypes>,
+    mut events: EventReader<AssetEvent<EnemyArchetype>>,
+    assets: Res<Assets<EnemyArchetype>>,
+    asset_server: Res<AssetServer>,
+    game_state: Res<State<GameState>>,
+) {
+    if events.read().count() > 0 {
+        archetypes.reload_pending = true;
+    }
+    let in_run = matches!(game_state.get(), GameState::Playing | GameState::Paused);
+    let rebuild = match &archetypes.status {
+        ArchetypeStatus::Loading => match asset_server.recursive_dependency_load_state(&archetypes.folder) {
+            RecursiveDependencyLoadState::Loaded => true,
+            RecursiveDependencyLoadState::Failed(err) => {
+                archetypes.status = ArchetypeStatus::Failed(err.to_string());
+                false
+            }
+            _ => false,
+        },
+        ArchetypeStatus::Ready => archetypes.reload_pending && !in_run,
+        ArchetypeStatus::Failed(_) => false,
+    };
+    if !rebuild {
+        return;
+    }
+
+    let mut loaded: Vec<Arc<EnemyArchetype>> = assets.iter().map(|(_, archetype)| Arc::new(archetype.clone())).collect();
+    loaded.sort_by(|a, b| a.name.cmp(&b.name));
+    if archetypes.is_ready() {
+        info!("reloaded {} enemy archetypes", loaded.len());
+    }
+    archetypes.status = if loaded.is_empty() {
+        ArchetypeStatus::Failed(format!("no enemy archetypes in assets/{ENEMY_ARCHETYPE_FOLDER}"))
+    } else {
+        ArchetypeStatus::Ready
+    };
+    archetypes.archetypes = loaded;
+    archetypes.reload_pending = false;
+}
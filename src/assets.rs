@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+
+// Every colour bullets and missiles come in, in the same order as `Tint::ALL`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tint {
+    Player,
+    Triple,
+    Missile,
+    Rocket,
+    Enemy,
+}
+
+impl Tint {
+    const ALL: [Tint; 5] = [Tint::Player, Tint::Triple, Tint::Missile, Tint::Rocket, Tint::Enemy];
+
+    fn color(&self) -> Color {
+        match self {
+            Tint::Player => Color::srgb(0.0, 1.0, 1.0),
+            Tint::Triple => Color::srgb(1.0, 0.0, 1.0),
+            Tint::Missile => Color::srgb(1.0, 0.3, 0.3),
+            Tint::Rocket => Color::srgb(1.0, 0.6, 0.1),
+            Tint::Enemy => Color::srgb(1.0, 0.0, 0.0),
+        }
+    }
+}
+
+// Meshes and materials for everything spawned by the dozen every second. They're made once
+// at startup and shared, instead of each bullet adding its own to the asset stores. Bullet
+// sizes come from the entity's scale.
+#[derive(Resource)]
+pub struct SharedAssets {
+    pub sphere: Handle<Mesh>,
+    glows: Vec<Handle<StandardMaterial>>,
+}
+
+impl SharedAssets {
+    pub fn new(meshes: &mut Assets<Mesh>, materials: &mut Assets<StandardMaterial>) -> Self {
+        let glows = Tint::ALL
+            .iter()
+            .map(|tint| {
+                let color = tint.color();
+                materials.add(StandardMaterial {
+                    base_color: color,
+                    emissive: color.to_linear(),
+                    ..default()
+                })
+            })
+            .collect();
+
+        Self {
+            sphere: meshes.add(Sphere::new(1.0)),
+            glows,
+        }
+    }
+
+    pub fn glow(&self, tint: Tint) -> Handle<StandardMaterial> {
+        self.glows[tint as usize].clone()
+    }
+}
+
+pub fn setup_shared_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(SharedAssets::new(&mut meshes, &mut materials));
+}
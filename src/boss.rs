@@ -0,0 +1,635 @@
+use bevy::prelude::*;
+use crate::{Aircraft, GameEntity};
+use crate::archetypes::EnemyArchetypes;
+use crate::assets::{SharedAssets, Tint};
+use crate::enemies::{DestroyedEnemy, EnemyBullet, EnemyDestroyedEvent, Health, lead_position, spawn_formation_at};
+use crate::game_state::{CurrentGameMode, GameMode, GameRng, GameStats};
+use crate::models::{AircraftModels, BossModels};
+use crate::particles::{ParticleBurst, ParticlePreset};
+use crate::physics::{InterpolatedTransform, segment_hits_sphere};
+use crate::pool::EntityPool;
+use crate::terrain::TerrainHeightField;
+
+// Seconds of Survival before the first boss turns up, and between one going down and the next
+const BOSS_FIRST_WAVE: f32 = 45.0;
+const BOSS_WAVE_INTERVAL: f32 = 60.0;
+// Bosses arrive this far ahead of the player, somewhere within the second angle of their heading
+const BOSS_SPAWN_DISTANCE: f32 = 650.0;
+const BOSS_SPAWN_ARC: f32 = 1.0;
+// They keep at least the first height over the ground, and try to stay the second above the player
+const BOSS_MIN_ALTITUDE: f32 = 80.0;
+const BOSS_HEIGHT_OVER_PLAYER: f32 = 40.0;
+const BOSS_CLIMB_RATE: f32 = 12.0;
+// Radians a second, and how far they bank into a turn
+const BOSS_TURN_RATE: f32 = 0.35;
+const BOSS_BANK: f32 = 0.25;
+// Each wave has this much more health than the one before, as a share of the first
+const BOSS_HEALTH_PER_WAVE: f32 = 0.3;
+// Knocking out a turret or engine takes this share of the hull's health with it
+const PART_HULL_DAMAGE: f32 = 0.08;
+// With every engine out a boss still flies at this share of its speed
+const ENGINE_OUT_SPEED: f32 = 0.4;
+
+const TURRET_RANGE: f32 = 450.0;
+const TURRET_COOLDOWN: f32 = 0.7;
+const TURRET_BULLET_SPEED: f32 = 180.0;
+const TURRET_DAMAGE: f32 = 5.0;
+// Largest error in each axis of the aim direction
+const TURRET_SPREAD: f32 = 0.08;
+
+// Turrets and engines on each kind of boss, and where each sits on it. Bosses are built facing -Z.
+const GUNSHIP_PARTS: &[(BossPartKind, Vec3)] = &[
+    (BossPartKind::Turret, Vec3::new(0.0, 2.8, -9.0)),
+    (BossPartKind::Turret, Vec3::new(0.0, 2.8, 9.0)),
+    (BossPartKind::Turret, Vec3::new(0.0, -2.8, 0.0)),
+    (BossPartKind::Turret, Vec3::new(0.0, 0.0, 21.0)),
+    (BossPartKind::Engine, Vec3::new(-9.0, -0.4, -4.0)),
+    (BossPartKind::Engine, Vec3::new(-17.0, -0.4, -3.5)),
+    (BossPartKind::Engine, Vec3::new(9.0, -0.4, -4.0)),
+    (BossPartKind::Engine, Vec3::new(17.0, -0.4, -3.5)),
+];
+
+const AIRSHIP_PARTS: &[(BossPartKind, Vec3)] = &[
+    (BossPartKind::Turret, Vec3::new(0.0, 9.2, -10.0)),
+    (BossPartKind::Turret, Vec3::new(0.0, 9.2, 10.0)),
+    (BossPartKind::Turret, Vec3::new(0.0, -11.8, -4.0)),
+    (BossPartKind::Turret, Vec3::new(0.0, -11.8, 4.0)),
+    (BossPartKind::Engine, Vec3::new(-7.5, -7.0, 6.0)),
+    (BossPartKind::Engine, Vec3::new(7.5, -7.0, 6.0)),
+    (BossPartKind::Engine, Vec3::new(-9.5, 0.0, 18.0)),
+    (BossPartKind::Engine, Vec3::new(9.5, 0.0, 18.0)),
+];
+
+// Spheres covering each kind's hull and wings, as centres relative to the boss and radii
+const GUNSHIP_HULL: &[(Vec3, f32)] = &[
+    (Vec3::new(0.0, 0.0, -17.0), 3.0),
+    (Vec3::new(0.0, 0.0, -11.0), 3.5),
+    (Vec3::new(0.0, 0.0, -5.0), 3.5),
+    (Vec3::new(0.0, 0.0, 1.0), 3.5),
+    (Vec3::new(0.0, 0.0, 7.0), 3.5),
+    (Vec3::new(0.0, 0.0, 13.0), 3.0),
+    (Vec3::new(0.0, 1.5, 19.0), 3.5),
+    (Vec3::new(-13.0, 0.5, -2.0), 4.0),
+    (Vec3::new(-21.0, 0.5, -2.0), 3.5),
+    (Vec3::new(13.0, 0.5, -2.0), 4.0),
+    (Vec3::new(21.0, 0.5, -2.0), 3.5),
+];
+
+const AIRSHIP_HULL: &[(Vec3, f32)] = &[
+    (Vec3::new(0.0, 0.0, -25.0), 5.0),
+    (Vec3::new(0.0, 0.0, -16.0), 8.0),
+    (Vec3::new(0.0, 0.0, -6.0), 9.0),
+    (Vec3::new(0.0, 0.0, 5.0), 9.0),
+    (Vec3::new(0.0, 0.0, 15.0), 8.0),
+    (Vec3::new(0.0, 0.0, 25.0), 5.5),
+    (Vec3::new(0.0, -10.0, 0.0), 6.0),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BossKind {
+    Gunship,
+    Airship,
+}
+
+impl BossKind {
+    pub const ALL: [BossKind; 2] = [BossKind::Gunship, BossKind::Airship];
+
+    // Waves take turns
+    pub fn for_wave(wave: u32) -> Self {
+        Self::ALL[(wave.max(1) as usize - 1) % Self::ALL.len()]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BossKind::Gunship => "Heavy Gunship",
+            BossKind::Airship => "War Airship",
+        }
+    }
+
+    pub fn health(&self) -> f32 {
+        match self {
+            BossKind::Gunship => 1500.0,
+            BossKind::Airship => 2200.0,
+        }
+    }
+
+    pub fn speed(&self) -> f32 {
+        match self {
+            BossKind::Gunship => 38.0,
+            BossKind::Airship => 24.0,
+        }
+    }
+
+    // Points and coins for bringing it down in the first wave; later waves pay more
+    pub fn score(&self) -> u32 {
+        match self {
+            BossKind::Gunship => 5000,
+            BossKind::Airship => 6000,
+        }
+    }
+
+    pub fn coins(&self) -> u32 {
+        match self {
+            BossKind::Gunship => 50,
+            BossKind::Airship => 60,
+        }
+    }
+
+    pub fn parts(&self) -> &'static [(BossPartKind, Vec3)] {
+        match self {
+            BossKind::Gunship => GUNSHIP_PARTS,
+            BossKind::Airship => AIRSHIP_PARTS,
+        }
+    }
+
+    pub fn hull_spheres(&self) -> &'static [(Vec3, f32)] {
+        match self {
+            BossKind::Gunship => GUNSHIP_HULL,
+            BossKind::Airship => AIRSHIP_HULL,
+        }
+    }
+
+    // Nothing on it is further than this from its centre, hit spheres included
+    pub fn bounding_radius(&self) -> f32 {
+        match self {
+            BossKind::Gunship => 28.0,
+            BossKind::Airship => 35.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BossPartKind {
+    Turret,
+    Engine,
+}
+
+impl BossPartKind {
+    pub const ALL: [BossPartKind; 2] = [BossPartKind::Turret, BossPartKind::Engine];
+
+    pub fn health(&self) -> f32 {
+        match self {
+            BossPartKind::Turret => 120.0,
+            BossPartKind::Engine => 180.0,
+        }
+    }
+
+    pub fn score(&self) -> u32 {
+        match self {
+            BossPartKind::Turret => 300,
+            BossPartKind::Engine => 400,
+        }
+    }
+
+    pub fn hit_radius(&self) -> f32 {
+        match self {
+            BossPartKind::Turret => 2.0,
+            BossPartKind::Engine => 2.5,
+        }
+    }
+}
+
+// How a boss fights, from how much health it has left. Every new phase brings in an escort.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BossPhase {
+    #[default]
+    Approach,
+    Reinforced,
+    Desperate,
+}
+
+impl BossPhase {
+    pub fn of(health: &Health) -> Self {
+        let health_ratio = health.current / health.max;
+        if health_ratio < 1.0 / 3.0 {
+            BossPhase::Desperate
+        } else if health_ratio < 2.0 / 3.0 {
+            BossPhase::Reinforced
+        } else {
+            BossPhase::Approach
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BossPhase::Approach => "Approach",
+            BossPhase::Reinforced => "Reinforced",
+            BossPhase::Desperate => "Desperate",
+        }
+    }
+
+    // Distance it circles the player at; closer the more desperate it gets
+    pub fn orbit_radius(&self) -> f32 {
+        match self {
+            BossPhase::Approach => 320.0,
+            BossPhase::Reinforced => 260.0,
+            BossPhase::Desperate => 180.0,
+        }
+    }
+
+    // How much faster its turrets fire, and it flies, than at the start
+    pub fn fire_rate(&self) -> f32 {
+        match self {
+            BossPhase::Approach => 1.0,
+            BossPhase::Reinforced => 1.4,
+            BossPhase::Desperate => 2.0,
+        }
+    }
+
+    pub fn speed_factor(&self) -> f32 {
+        match self {
+            BossPhase::Desperate => 1.25,
+            _ => 1.0,
+        }
+    }
+}
+
+// A boss's hull. Its turrets and engines are child entities, each with its own health; one that's
+// knocked out loses its `Health` and stops working.
+#[derive(Component, Clone)]
+pub struct Boss {
+    pub kind: BossKind,
+    pub wave: u32,
+    pub phase: BossPhase,
+    pub parts: Vec<Entity>,
+    pub turrets_left: u32,
+    pub engines_left: u32,
+    // Which way it circles the player: 1 anticlockwise seen from above, -1 clockwise
+    pub orbit_direction: f32,
+}
+
+impl Boss {
+    pub fn score(&self) -> u32 {
+        self.kind.score() * self.wave
+    }
+
+    pub fn coins(&self) -> u32 {
+        self.kind.coins() * self.wave
+    }
+
+    // Engines it's lost slow it down
+    pub fn speed(&self) -> f32 {
+        let engines = self.kind.parts().iter().filter(|(kind, _)| *kind == BossPartKind::Engine).count() as f32;
+        let working = self.engines_left as f32 / engines.max(1.0);
+        self.kind.speed() * (ENGINE_OUT_SPEED + (1.0 - ENGINE_OUT_SPEED) * working) * self.phase.speed_factor()
+    }
+}
+
+#[derive(Component)]
+pub struct BossPart {
+    pub boss: Entity,
+    pub kind: BossPartKind,
+    // Where it sits on the boss, which is also its Transform's translation
+    pub offset: Vec3,
+    pub cooldown: f32,
+}
+
+// Survival's boss waves. The countdown to the next one only runs while there's no boss.
+#[derive(Resource)]
+pub struct BossWaves {
+    pub countdown: f32,
+    pub wave: u32,
+}
+
+impl Default for BossWaves {
+    fn default() -> Self {
+        Self {
+            countdown: BOSS_FIRST_WAVE,
+            wave: 0,
+        }
+    }
+}
+
+pub fn reset_boss_waves(mut boss_waves: ResMut<BossWaves>) {
+    *boss_waves = BossWaves::default();
+}
+
+pub enum BossHit {
+    Part(Entity),
+    Hull,
+}
+
+// What a shot along `start`..`end` hits on a boss when it passes within `reach` of it: one of the
+// turrets or engines `parts` that's still working, otherwise the hull. Also returns the centre of
+// the piece it hit.
+pub fn boss_hit<'a>(
+    boss_transform: &Transform,
+    kind: BossKind,
+    mut parts: impl Iterator<Item = (Entity, &'a BossPart)>,
+    start: Vec3,
+    end: Vec3,
+    reach: f32,
+) -> Option<(BossHit, Vec3)> {
+    if !segment_hits_sphere(start, end, boss_transform.translation, kind.bounding_radius() + reach) {
+        return None;
+    }
+    parts
+        .find_map(|(entity, part)| {
+            let center = boss_transform.transform_point(part.offset);
+            segment_hits_sphere(start, end, center, part.kind.hit_radius() + reach).then_some((BossHit::Part(entity), center))
+        })
+        .or_else(|| {
+            kind.hull_spheres()
+                .iter()
+                .map(|(offset, radius)| (boss_transform.transform_point(*offset), *radius))
+                .find(|(center, radius)| segment_hits_sphere(start, end, *center, radius + reach))
+                .map(|(center, _)| (BossHit::Hull, center))
+        })
+}
+
+// Starts the next wave in Survival once the countdown runs out: the boss arrives ahead of the
+// player with an escort formation
+pub fn boss_waves_system(
+    mut commands: Commands,
+    mut boss_waves: ResMut<BossWaves>,
+    game_mode: Res<CurrentGameMode>,
+    boss_models: Res<BossModels>,
+    models: Res<AircraftModels>,
+    archetypes: Res<EnemyArchetypes>,
+    player_query: Query<&Transform, With<Aircraft>>,
+    boss_query: Query<(), With<Boss>>,
+    height_field: Res<TerrainHeightField>,
+    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+) {
+    if game_mode.mode != GameMode::Survival || !boss_query.is_empty() {
+        return;
+    }
+    boss_waves.countdown -= time.delta_secs();
+    if boss_waves.countdown > 0.0 {
+        return;
+    }
+    let Ok(player_transform) = player_query.single() else {
+        return;
+    };
+    boss_waves.countdown = BOSS_WAVE_INTERVAL;
+    boss_waves.wave += 1;
+    let wave = boss_waves.wave;
+    let kind = BossKind::for_wave(wave);
+
+    let heading = player_transform.forward().with_y(0.0).try_normalize().unwrap_or(Vec3::NEG_Z);
+    let bearing = Quat::from_rotation_y((rng.spawns.f32() - 0.5) * 2.0 * BOSS_SPAWN_ARC) * heading;
+    let position = player_transform.translation + bearing * BOSS_SPAWN_DISTANCE;
+    let ground = height_field.height_at(position.x, position.z);
+    let position = position.with_y((player_transform.translation.y + BOSS_HEIGHT_OVER_PLAYER).max(ground + BOSS_MIN_ALTITUDE));
+    let orbit_direction = if rng.spawns.bool() { 1.0 } else { -1.0 };
+    // Flying across the player's path, the way it's going to circle them
+    let transform = Transform::from_translation(position).looking_to(Vec3::Y.cross(bearing) * orbit_direction, Vec3::Y);
+
+    let health = kind.health() * (1.0 + BOSS_HEALTH_PER_WAVE * (wave - 1) as f32);
+    let boss_entity = commands.spawn((
+        transform,
+        Visibility::default(),
+        Health {
+            current: health,
+            max: health,
+        },
+        InterpolatedTransform::new(transform),
+        GameEntity,
+    )).id();
+    boss_models.spawn_hull(&mut commands, boss_entity, kind);
+
+    let mut parts = Vec::new();
+    for (index, &(part_kind, offset)) in kind.parts().iter().enumerate() {
+        let part = commands.spawn((
+            Transform::from_translation(offset),
+            Visibility::default(),
+            BossPart {
+                boss: boss_entity,
+                kind: part_kind,
+                offset,
+                // Staggered so the turrets don't all fire together
+                cooldown: TURRET_COOLDOWN * index as f32 / kind.parts().len() as f32,
+            },
+            Health {
+                current: part_kind.health(),
+                max: part_kind.health(),
+            },
+        )).id();
+        boss_models.spawn_part(&mut commands, part, part_kind);
+        commands.entity(boss_entity).add_child(part);
+        parts.push(part);
+    }
+    let count = |part_kind| kind.parts().iter().filter(|(kind, _)| *kind == part_kind).count() as u32;
+    commands.entity(boss_entity).insert(Boss {
+        kind,
+        wave,
+        phase: BossPhase::Approach,
+        parts,
+        turrets_left: count(BossPartKind::Turret),
+        engines_left: count(BossPartKind::Engine),
+        orbit_direction,
+    });
+    info!("Boss wave {wave}: {}", kind.name());
+
+    spawn_escort(&mut commands, &models, &archetypes, &mut rng.spawns, &transform, player_transform);
+}
+
+// A formation flying off the boss's wing, heading for the player
+fn spawn_escort(
+    commands: &mut Commands,
+    models: &AircraftModels,
+    archetypes: &EnemyArchetypes,
+    rng: &mut fastrand::Rng,
+    boss_transform: &Transform,
+    player_transform: &Transform,
+) {
+    let Some(archetype) = archetypes.pick_formation(rng) else {
+        return;
+    };
+    let side = if rng.bool() { 1.0 } else { -1.0 };
+    let center = boss_transform.translation + boss_transform.right() * 50.0 * side + boss_transform.back() * 30.0;
+    spawn_formation_at(commands, models, rng, archetype, center, player_transform.translation);
+}
+
+// Bosses circle the player, closing in as they lose health, and slow down as their engines are
+// knocked out. Crossing into a new phase calls in another escort.
+pub fn boss_flight_system(
+    mut commands: Commands,
+    mut boss_query: Query<(&mut Transform, &mut Boss, &Health)>,
+    player_query: Query<&Transform, (With<Aircraft>, Without<Boss>)>,
+    models: Res<AircraftModels>,
+    archetypes: Res<EnemyArchetypes>,
+    mut bursts: EventWriter<ParticleBurst>,
+    height_field: Res<TerrainHeightField>,
+    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+) {
+    let Ok(player_transform) = player_query.single() else {
+        return;
+    };
+    let delta = time.delta_secs();
+    let player = player_transform.translation;
+
+    for (mut transform, mut boss, health) in boss_query.iter_mut() {
+        let phase = BossPhase::of(health);
+        if phase != boss.phase {
+            boss.phase = phase;
+            bursts.write(ParticleBurst {
+                preset: ParticlePreset::Explosion,
+                position: transform.translation,
+                velocity: Vec3::ZERO,
+                count: 30,
+                color: None,
+            });
+            spawn_escort(&mut commands, &models, &archetypes, &mut rng.spawns, &transform, player_transform);
+        }
+
+        // Steers for the circle around the player: along it when it's on it, back towards it otherwise
+        let offset = (transform.translation - player).with_y(0.0);
+        let outward = offset.try_normalize().unwrap_or(Vec3::X);
+        let along = Vec3::Y.cross(outward) * boss.orbit_direction;
+        let radius = phase.orbit_radius();
+        let correction = ((radius - offset.length()) / radius).clamp(-1.0, 1.0);
+        let wanted = (along + outward * correction).normalize_or_zero();
+
+        let heading = transform.forward().with_y(0.0).try_normalize().unwrap_or(wanted);
+        let turn = heading.cross(wanted).y.signum() * heading.angle_between(wanted).min(BOSS_TURN_RATE * delta);
+        let heading = Quat::from_rotation_y(turn) * heading;
+
+        let ground = height_field.height_at(transform.translation.x, transform.translation.z);
+        let cruise = (player.y + BOSS_HEIGHT_OVER_PLAYER).max(ground + BOSS_MIN_ALTITUDE);
+        let climb = (cruise - transform.translation.y).clamp(-BOSS_CLIMB_RATE, BOSS_CLIMB_RATE);
+        transform.translation += heading * boss.speed() * delta + Vec3::Y * climb * delta;
+        let bank = if turn.abs() > f32::EPSILON { -turn.signum() * BOSS_BANK } else { 0.0 };
+        transform.rotation = Transform::IDENTITY.looking_to(heading, Vec3::Y).rotation * Quat::from_rotation_z(bank);
+    }
+}
+
+// Working turrets swing round to the player and fire at them when they're in range, faster as the
+// boss gets more desperate
+pub fn boss_guns_system(
+    mut commands: Commands,
+    shared_assets: Res<SharedAssets>,
+    mut bullet_pool: ResMut<EntityPool<EnemyBullet>>,
+    mut bursts: EventWriter<ParticleBurst>,
+    boss_query: Query<(&Transform, &Boss)>,
+    mut part_query: Query<(&mut Transform, &mut BossPart), (With<Health>, Without<Boss>)>,
+    player_query: Query<(&Transform, &InterpolatedTransform), (With<Aircraft>, Without<Boss>, Without<BossPart>)>,
+    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+) {
+    let Ok((player_transform, player_interpolated)) = player_query.single() else {
+        return;
+    };
+    let delta = time.delta_secs();
+    let player = player_transform.translation;
+    let player_velocity = (player_interpolated.current.translation - player_interpolated.previous.translation) / delta;
+
+    for (mut transform, mut part) in part_query.iter_mut() {
+        if part.kind != BossPartKind::Turret {
+            continue;
+        }
+        let Ok((boss_transform, boss)) = boss_query.get(part.boss) else {
+            continue;
+        };
+        part.cooldown -= delta;
+        let muzzle = boss_transform.transform_point(part.offset);
+        if muzzle.distance(player) > TURRET_RANGE {
+            continue;
+        }
+        let aim_point = lead_position(muzzle, player, player_velocity, TURRET_BULLET_SPEED, 0.7);
+        let aim = (aim_point - muzzle).normalize_or_zero();
+        if let Ok(direction) = Dir3::new(boss_transform.rotation.inverse() * aim) {
+            transform.look_to(direction, Vec3::Y);
+        }
+        if part.cooldown > 0.0 {
+            continue;
+        }
+        part.cooldown = TURRET_COOLDOWN / boss.phase.fire_rate();
+
+        let spread = Vec3::new(rng.ai.f32() - 0.5, rng.ai.f32() - 0.5, rng.ai.f32() - 0.5) * TURRET_SPREAD;
+        let direction = (aim + spread).normalize_or_zero();
+        let bullet_spawn = muzzle + direction * 2.5;
+        let bullet_transform = Transform::from_translation(bullet_spawn).with_scale(Vec3::splat(0.35));
+        bullet_pool.spawn(&mut commands, (
+            Mesh3d(shared_assets.sphere.clone()),
+            MeshMaterial3d(shared_assets.glow(Tint::Enemy)),
+            bullet_transform,
+            InterpolatedTransform::new(bullet_transform),
+            EnemyBullet {
+                velocity: direction * TURRET_BULLET_SPEED,
+                damage: TURRET_DAMAGE,
+                lifetime: 3.0,
+            },
+        ));
+        bursts.write(ParticleBurst {
+            preset: ParticlePreset::MuzzleFlash,
+            position: bullet_spawn,
+            velocity: direction * 20.0,
+            count: 3,
+            color: Some(Color::srgb(1.0, 0.4, 0.2)),
+        });
+    }
+}
+
+// Takes `damage` off a boss's hull. Bringing it down pays its score and coins, blows it up with
+// everything on it, and returns the event to send for it. Bosses already destroyed earlier in the
+// step take no more hits.
+pub fn damage_boss(
+    commands: &mut Commands,
+    game_stats: &mut GameStats,
+    boss_entity: Entity,
+    transform: &Transform,
+    health: &mut Health,
+    boss: &Boss,
+    damage: f32,
+) -> Option<EnemyDestroyedEvent> {
+    if health.current <= 0.0 {
+        return None;
+    }
+    health.current -= damage;
+    if health.current > 0.0 {
+        return None;
+    }
+
+    game_stats.score += boss.score();
+    game_stats.bonus_coins += boss.coins();
+    game_stats.enemies_destroyed += 1;
+    commands.entity(boss_entity).despawn();
+    Some(EnemyDestroyedEvent {
+        position: transform.translation,
+        enemy: DestroyedEnemy::Boss(boss.kind),
+    })
+}
+
+// Takes `damage` off one of a boss's turrets or engines. Knocking it out scores it, takes a share
+// of the hull's health with it and slows the boss down if it's an engine; the event returned is
+// for the part, or for the boss if that finished it off.
+pub fn damage_boss_part(
+    commands: &mut Commands,
+    game_stats: &mut GameStats,
+    part_entity: Entity,
+    part: &BossPart,
+    part_health: &mut Health,
+    boss_transform: &Transform,
+    boss_health: &mut Health,
+    boss: &mut Boss,
+    damage: f32,
+) -> Option<EnemyDestroyedEvent> {
+    if part_health.current <= 0.0 || boss_health.current <= 0.0 {
+        return None;
+    }
+    part_health.current -= damage;
+    if part_health.current > 0.0 {
+        return None;
+    }
+
+    game_stats.score += part.kind.score();
+    let mut part_commands = commands.entity(part_entity);
+    part_commands.remove::<Health>();
+    match part.kind {
+        // Shot clean off; knocked-out engines stay on, burning
+        BossPartKind::Turret => {
+            boss.turrets_left = boss.turrets_left.saturating_sub(1);
+            part_commands.insert(Visibility::Hidden);
+        }
+        BossPartKind::Engine => boss.engines_left = boss.engines_left.saturating_sub(1),
+    }
+    let position = boss_transform.transform_point(part.offset);
+    let hull_damage = boss_health.max * PART_HULL_DAMAGE;
+    damage_boss(commands, game_stats, part.boss, boss_transform, boss_health, boss, hull_damage).or(Some(EnemyDestroyedEvent {
+        position,
+        enemy: DestroyedEnemy::BossPart(part.kind),
+    }))
+}
@@ -1,14 +1,34 @@
 use bevy::prelude::*;
 use std::collections::HashMap;
-use crate::{Aircraft, GameEntity, game_state::GameStats};
+use std::sync::Arc;
+use serde::Deserialize;
+use crate::{Aircraft, GameEntity, game_state::{GameStats, GameRng, RunClock, RunTimers}};
+use crate::physics::{InterpolatedTransform, segment_hits_sphere};
+use crate::spatial::SpatialIndex;
+use crate::terrain::TerrainHeightField;
+use crate::ground::{GROUND_UNIT_HIT_RADIUS, GroundUnit, GroundUnitKind, damage_ground_unit};
+use crate::boss::{Boss, BossHit, BossKind, BossPart, BossPartKind, boss_hit, damage_boss, damage_boss_part};
+use crate::assets::{SharedAssets, Tint};
+use crate::models::AircraftModels;
+use crate::archetypes::{EnemyArchetype, EnemyArchetypes, pick_weighted};
+use crate::particles::{ParticleBurst, ParticlePreset};
+use crate::pool::{EntityPool, Overflow, PoolConfig, Poolable};
+
+// Critically damaged enemies roll and sink at these rates, in radians and metres a second
+const CRITICAL_ROLL_RATE: f32 = 1.2;
+const CRITICAL_SINK_RATE: f32 = 4.0;
+
+const CRASH_GRAVITY: f32 = 20.0;
+const CRASH_SPIRAL_RATE: f32 = 1.5;
+const CRASH_ROLL_RATE: f32 = 4.0;
+const CRASH_TIMEOUT: f32 = 15.0;
 
 #[derive(Component)]
 pub struct Enemy {
+    pub archetype: Arc<EnemyArchetype>,
     pub speed: f32,
-    pub damage: f32,
     pub attack_range: f32,
     pub pursuit_range: f32,
-    pub enemy_type: EnemyType,
     pub behavior_state: EnemyBehaviorState,
     pub state_timer: f32,
     pub evasion_angle: f32,
@@ -19,6 +39,13 @@ pub struct Enemy {
     pub reaction_time: f32,
     pub morale: f32,
     pub last_taunt_time: f32,
+    // Slot relative to the formation leader (zero for leaders and lone enemies)
+    pub formation_offset: Vec3,
+    pub shoot_cooldown: f32,
+    pub damage_state: DamageState,
+    // Set by radar stations that can see the player; while it lasts the enemy hunts them from
+    // anywhere
+    pub alert_timer: f32,
 }
 
 #[derive(Component)]
@@ -28,6 +55,10 @@ pub struct EnemyBullet {
     pub lifetime: f32,
 }
 
+impl Poolable for EnemyBullet {
+    const POOL: PoolConfig = PoolConfig { size: 64, overflow: Overflow::Grow };
+}
+
 #[derive(Component)]
 pub struct PlayerBullet {
     pub velocity: Vec3,
@@ -35,33 +66,116 @@ pub struct PlayerBullet {
     pub lifetime: f32,
 }
 
+impl Poolable for PlayerBullet {
+    // Rapid triple shot keeps at most 90 in the air; past the limit a shot just isn't fired
+    const POOL: PoolConfig = PoolConfig { size: 96, overflow: Overflow::Skip };
+}
+
 #[derive(Component)]
 pub struct Health {
     pub current: f32,
     pub max: f32,
 }
 
+// How badly hurt an aircraft is: it shows in the smoke and fire it trails, and a damaged enemy
+// flies slower and worse
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DamageState {
+    #[default]
+    Intact,
+    Light,
+    Heavy,
+    Critical,
+}
+
+impl DamageState {
+    pub fn of(health: &Health) -> Self {
+        let health_ratio = health.current / health.max;
+        if health_ratio < 0.25 {
+            DamageState::Critical
+        } else if health_ratio < 0.5 {
+            DamageState::Heavy
+        } else if health_ratio < 0.75 {
+            DamageState::Light
+        } else {
+            DamageState::Intact
+        }
+    }
+
+    // Share of its archetype's speed, and of its pilot's skill, an enemy keeps
+    pub fn speed_factor(&self) -> f32 {
+        match self {
+            DamageState::Intact => 1.0,
+            DamageState::Light => 0.9,
+            DamageState::Heavy => 0.75,
+            DamageState::Critical => 0.6,
+        }
+    }
+
+    pub fn skill_factor(&self) -> f32 {
+        match self {
+            DamageState::Intact => 1.0,
+            DamageState::Light => 0.85,
+            DamageState::Heavy => 0.65,
+            DamageState::Critical => 0.4,
+        }
+    }
+
+    // Particles a second trailed behind the aircraft
+    pub fn smoke_rate(&self) -> f32 {
+        match self {
+            DamageState::Intact => 0.0,
+            DamageState::Light => 8.0,
+            DamageState::Heavy => 20.0,
+            DamageState::Critical => 36.0,
+        }
+    }
+
+    pub fn fire_rate(&self) -> f32 {
+        match self {
+            DamageState::Critical => 30.0,
+            _ => 0.0,
+        }
+    }
+}
+
+// A shot-down enemy on its way into the ground. It stops being an `Enemy` when it's hit, so
+// nothing targets, hits or rams it while it falls.
+#[derive(Component)]
+pub struct Crashing {
+    pub archetype: Arc<EnemyArchetype>,
+    pub velocity: Vec3,
+    // The spiral turns its flight path about the vertical and rolls it about its nose, in
+    // radians a second; both turn the same way
+    pub spiral_rate: f32,
+    pub roll_rate: f32,
+    pub roll: f32,
+    // Wrecks that somehow never reach the ground blow up anyway when this runs out
+    pub time_left: f32,
+}
+
 #[derive(Event)]
 pub struct EnemyDestroyedEvent {
     pub position: Vec3,
-    pub enemy_type: EnemyType,
+    pub enemy: DestroyedEnemy,
+}
+
+pub enum DestroyedEnemy {
+    Aircraft(Arc<EnemyArchetype>),
+    Ground(GroundUnitKind),
+    Boss(BossKind),
+    // A boss's turret or engine, knocked out while the boss flies on
+    BossPart(BossPartKind),
 }
 
 #[derive(Event)]
 pub struct RadioChatterEvent {
     pub message: String,
-    pub sender_type: EnemyType,
+    pub sender: Arc<EnemyArchetype>,
     pub personality: PilotPersonality,
 }
 
-#[derive(Debug, Clone, Copy)]
-pub enum EnemyType {
-    Fighter,
-    Bomber,
-    Ace,
-}
-
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Deserialize)]
 pub enum PilotPersonality {
     Aggressive,     // Rushes in, takes risks
     Defensive,      // Cautious, uses cover
@@ -104,36 +218,37 @@ pub enum ManeuverType {
 
 pub fn spawn_enemies_system(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    models: Res<AircraftModels>,
+    archetypes: Res<EnemyArchetypes>,
     player_query: Query<&Transform, With<Aircraft>>,
     enemies: Query<Entity, With<Enemy>>,
     time: Res<Time>,
-    mut spawn_timer: Local<f32>,
+    mut run_timers: ResMut<RunTimers>,
     game_stats: Res<crate::game_state::GameStats>,
+    mut rng: ResMut<GameRng>,
 ) {
     let enemy_count = enemies.iter().count();
     let max_enemies = (5.0 * game_stats.difficulty_level).min(10.0) as usize;
     
-    *spawn_timer += time.delta_secs();
+    run_timers.enemy_spawn += time.delta_secs();
     
     let spawn_interval = 3.0 / game_stats.difficulty_level.sqrt(); // Faster spawning at higher difficulty
     
-    if enemy_count < max_enemies && *spawn_timer > spawn_interval {
-        *spawn_timer = 0.0;
+    if enemy_count < max_enemies && run_timers.enemy_spawn > spawn_interval {
+        run_timers.enemy_spawn = 0.0;
         
         if let Ok(player_transform) = player_query.single() {
             // Decide whether to spawn a formation or single enemy
-            let spawn_formation = fastrand::f32() < 0.3 + (game_stats.difficulty_level - 1.0) * 0.1;
+            let spawn_formation = rng.spawns.f32() < 0.3 + (game_stats.difficulty_level - 1.0) * 0.1;
             
             if spawn_formation && enemy_count + 3 <= max_enemies {
                 // Spawn a formation of 3 enemies
-                spawn_enemy_formation(&mut commands, &mut meshes, &mut materials, player_transform, game_stats.difficulty_level);
+                spawn_enemy_formation(&mut commands, &models, &archetypes, &mut rng.spawns, player_transform);
             } else {
             // Spawn enemies at a distance from the player
-            let spawn_distance = 150.0 + fastrand::f32() * 100.0;
-            let angle = fastrand::f32() * std::f32::consts::TAU;
-            let height = player_transform.translation.y + (-20.0 + fastrand::f32() * 40.0);
+            let spawn_distance = 150.0 + rng.spawns.f32() * 100.0;
+            let angle = rng.spawns.f32() * std::f32::consts::TAU;
+            let height = player_transform.translation.y + (-20.0 + rng.spawns.f32() * 40.0);
             
             let position = Vec3::new(
                 player_transform.translation.x + angle.cos() * spawn_distance,
@@ -141,141 +256,126 @@ pub fn spawn_enemies_system(
                 player_transform.translation.z + angle.sin() * spawn_distance,
             );
             
-            // Determine enemy type based on difficulty
-            let ace_chance = 0.05 + (game_stats.difficulty_level - 1.0) * 0.1;
-            let bomber_chance = 0.2 + (game_stats.difficulty_level - 1.0) * 0.1;
-            
-            let enemy_type = if fastrand::f32() < ace_chance {
-                EnemyType::Ace
-            } else if fastrand::f32() < bomber_chance {
-                EnemyType::Bomber
-            } else {
-                EnemyType::Fighter
-            };
-            
-            let (color, scale, speed, health, damage, preferred_distance) = match enemy_type {
-                EnemyType::Fighter => (Color::srgb(0.8, 0.2, 0.2), 2.0, 60.0, 50.0, 10.0, 40.0),
-                EnemyType::Bomber => (Color::srgb(0.4, 0.4, 0.4), 3.0, 40.0, 100.0, 20.0, 60.0),
-                EnemyType::Ace => (Color::srgb(0.2, 0.2, 0.8), 1.8, 80.0, 75.0, 15.0, 30.0),
-            };
-            
-            // Assign personality based on enemy type and randomness
-            let personality = match enemy_type {
-                EnemyType::Ace => {
-                    if fastrand::f32() < 0.5 { PilotPersonality::Veteran } 
-                    else { PilotPersonality::ShowOff }
-                },
-                EnemyType::Bomber => {
-                    if fastrand::f32() < 0.7 { PilotPersonality::Defensive } 
-                    else { PilotPersonality::Tactical }
-                },
-                EnemyType::Fighter => {
-                    match fastrand::u32(0..4) {
-                        0 => PilotPersonality::Aggressive,
-                        1 => PilotPersonality::Defensive,
-                        2 => PilotPersonality::Tactical,
-                        _ => PilotPersonality::ShowOff,
-                    }
-                },
+            let Some(archetype) = archetypes.pick_spawn(&mut rng.spawns, game_stats.difficulty_level) else {
+                return;
             };
-            
-            // Skill varies by type and personality
-            let maneuver_skill = match (enemy_type, personality) {
-                (EnemyType::Ace, _) => 0.8 + fastrand::f32() * 0.2,
-                (_, PilotPersonality::Veteran) => 0.7 + fastrand::f32() * 0.2,
-                (_, PilotPersonality::ShowOff) => 0.6 + fastrand::f32() * 0.3,
-                (EnemyType::Fighter, _) => 0.4 + fastrand::f32() * 0.3,
-                (EnemyType::Bomber, _) => 0.2 + fastrand::f32() * 0.2,
-            };
-            
-            let reaction_time = match personality {
-                PilotPersonality::Veteran => 0.2 + fastrand::f32() * 0.1,
-                PilotPersonality::Aggressive => 0.3 + fastrand::f32() * 0.2,
-                PilotPersonality::Defensive => 0.4 + fastrand::f32() * 0.2,
-                _ => 0.5 + fastrand::f32() * 0.3,
-            };
-            
-            // Spawn enemy aircraft
-            let enemy_entity = commands.spawn((
-                Transform::from_translation(position)
-                    .looking_at(player_transform.translation, Vec3::Y),
-                Visibility::default(),
-                Enemy {
-                    speed,
-                    damage,
-                    attack_range: 50.0,
-                    pursuit_range: 200.0,
-                    enemy_type,
-                    behavior_state: EnemyBehaviorState::Patrol,
-                    state_timer: 0.0,
-                    evasion_angle: 0.0,
-                    preferred_distance,
-                    personality,
-                    formation_role: None,
-                    maneuver_skill,
-                    reaction_time,
-                    morale: 1.0,
-                    last_taunt_time: 0.0,
-                },
-                Health {
-                    current: health,
-                    max: health,
-                },
-                GameEntity,
-            )).id();
-            
-            // Enemy body
-            let body = commands.spawn((
-                Mesh3d(meshes.add(Cuboid::new(1.2 * scale, 0.8 * scale, 3.0 * scale))),
-                MeshMaterial3d(materials.add(StandardMaterial {
-                    base_color: color,
-                    metallic: 0.7,
-                    perceptual_roughness: 0.3,
-                    ..default()
-                })),
-                Transform::default(),
-            )).id();
-            
-            // Enemy wings
-            let left_wing = commands.spawn((
-                Mesh3d(meshes.add(Cuboid::new(5.0 * scale, 0.2 * scale, 1.5 * scale))),
-                MeshMaterial3d(materials.add(StandardMaterial {
-                    base_color: color.darker(0.2),
-                    metallic: 0.6,
-                    ..default()
-                })),
-                Transform::from_xyz(-3.0 * scale, 0.0, 0.0),
-            )).id();
-            
-            let right_wing = commands.spawn((
-                Mesh3d(meshes.add(Cuboid::new(5.0 * scale, 0.2 * scale, 1.5 * scale))),
-                MeshMaterial3d(materials.add(StandardMaterial {
-                    base_color: color.darker(0.2),
-                    metallic: 0.6,
-                    ..default()
-                })),
-                Transform::from_xyz(3.0 * scale, 0.0, 0.0),
-            )).id();
-            
-            // Build hierarchy
-            commands.entity(enemy_entity).add_children(&[body]);
-            commands.entity(body).add_children(&[left_wing, right_wing]);
+            let pilot = EnemyPilot::draw(&mut rng.spawns, &archetype);
+            let livery = models.livery(archetype.model);
+            spawn_enemy(
+                &mut commands,
+                &models,
+                archetype,
+                Transform::from_translation(position).looking_at(player_transform.translation, Vec3::Y),
+                pilot,
+                livery,
+            );
             }
         }
     }
 }
 
+// Who's flying an enemy, and where in a formation if it's in one
+struct EnemyPilot {
+    personality: PilotPersonality,
+    maneuver_skill: f32,
+    reaction_time: f32,
+    morale: f32,
+    formation_role: Option<FormationRole>,
+    formation_offset: Vec3,
+}
+
+impl EnemyPilot {
+    // A lone pilot for `archetype`, with a personality picked by its weights and skill drawn from its range
+    fn draw(rng: &mut fastrand::Rng, archetype: &EnemyArchetype) -> Self {
+        let personality = pick_weighted(rng, archetype.personalities.iter().copied())
+            .unwrap_or(PilotPersonality::Tactical);
+        
+        // Skill comes from the archetype, but veterans and show-offs are always good pilots
+        let (low, high) = archetype.maneuver_skill;
+        let (low, high) = match personality {
+            PilotPersonality::Veteran => (low.max(0.7), high.max(0.9)),
+            PilotPersonality::ShowOff => (low.max(0.6), high.max(0.9)),
+            _ => (low, high),
+        };
+        let maneuver_skill = low + rng.f32() * (high - low);
+        
+        let reaction_time = match personality {
+            PilotPersonality::Veteran => 0.2 + rng.f32() * 0.1,
+            PilotPersonality::Aggressive => 0.3 + rng.f32() * 0.2,
+            PilotPersonality::Defensive => 0.4 + rng.f32() * 0.2,
+            _ => 0.5 + rng.f32() * 0.3,
+        };
+        
+        Self {
+            personality,
+            maneuver_skill,
+            reaction_time,
+            morale: 1.0,
+            formation_role: None,
+            formation_offset: Vec3::ZERO,
+        }
+    }
+}
+
+fn spawn_enemy(
+    commands: &mut Commands,
+    models: &AircraftModels,
+    archetype: Arc<EnemyArchetype>,
+    transform: Transform,
+    pilot: EnemyPilot,
+    livery: crate::models::Livery,
+) -> Entity {
+    let behavior_state = if pilot.formation_role.is_some() {
+        EnemyBehaviorState::Formation
+    } else {
+        EnemyBehaviorState::Patrol
+    };
+    let model = archetype.model;
+    let enemy_entity = commands.spawn((
+        transform,
+        Visibility::default(),
+        Enemy {
+            speed: archetype.speed,
+            attack_range: archetype.attack_range,
+            pursuit_range: archetype.pursuit_range,
+            behavior_state,
+            state_timer: 0.0,
+            evasion_angle: 0.0,
+            preferred_distance: archetype.preferred_distance,
+            personality: pilot.personality,
+            formation_role: pilot.formation_role,
+            maneuver_skill: pilot.maneuver_skill,
+            reaction_time: pilot.reaction_time,
+            morale: pilot.morale,
+            last_taunt_time: 0.0,
+            formation_offset: pilot.formation_offset,
+            shoot_cooldown: 0.0,
+            damage_state: DamageState::Intact,
+            alert_timer: 0.0,
+            archetype: archetype.clone(),
+        },
+        Health {
+            current: archetype.health,
+            max: archetype.health,
+        },
+        InterpolatedTransform::new(transform),
+        GameEntity,
+    )).id();
+    models.spawn(commands, enemy_entity, model, livery);
+    enemy_entity
+}
+
 fn spawn_enemy_formation(
     commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
+    models: &AircraftModels,
+    archetypes: &EnemyArchetypes,
+    rng: &mut fastrand::Rng,
     player_transform: &Transform,
-    difficulty_level: f32,
 ) {
     // Formation spawn position
-    let spawn_distance = 200.0 + fastrand::f32() * 100.0;
-    let angle = fastrand::f32() * std::f32::consts::TAU;
-    let height = player_transform.translation.y + (-10.0 + fastrand::f32() * 20.0);
+    let spawn_distance = 200.0 + rng.f32() * 100.0;
+    let angle = rng.f32() * std::f32::consts::TAU;
+    let height = player_transform.translation.y + (-10.0 + rng.f32() * 20.0);
     
     let formation_center = Vec3::new(
         player_transform.translation.x + angle.cos() * spawn_distance,
@@ -283,19 +383,30 @@ fn spawn_enemy_formation(
         player_transform.translation.z + angle.sin() * spawn_distance,
     );
     
-    // Formation type and enemy type
-    let use_fighters = fastrand::f32() < 0.7;
-    let enemy_type = if use_fighters { EnemyType::Fighter } else { EnemyType::Bomber };
-    
+    let Some(archetype) = archetypes.pick_formation(rng) else {
+        return;
+    };
+    spawn_formation_at(commands, models, rng, archetype, formation_center, player_transform.translation);
+}
+
+// A leader and two wingmen of `archetype` around `formation_center`, facing `player_position`
+pub fn spawn_formation_at(
+    commands: &mut Commands,
+    models: &AircraftModels,
+    rng: &mut fastrand::Rng,
+    archetype: Arc<EnemyArchetype>,
+    formation_center: Vec3,
+    player_position: Vec3,
+) {
     // Spawn leader
     let leader_offset = Vec3::ZERO;
     let leader_entity = spawn_formation_enemy(
-        commands, meshes, materials,
+        commands, models, rng,
         formation_center + leader_offset,
-        player_transform.translation,
-        enemy_type,
+        player_position,
+        archetype.clone(),
         Some(FormationRole::Leader),
-        difficulty_level,
+        leader_offset,
     );
     
     // Spawn wingmen
@@ -306,118 +417,47 @@ fn spawn_enemy_formation(
     
     for offset in &wingman_offsets {
         spawn_formation_enemy(
-            commands, meshes, materials,
+            commands, models, rng,
             formation_center + *offset,
-            player_transform.translation,
-            enemy_type,
+            player_position,
+            archetype.clone(),
             Some(FormationRole::Wingman(leader_entity)),
-            difficulty_level,
+            *offset,
         );
     }
 }
 
 fn spawn_formation_enemy(
     commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
+    models: &AircraftModels,
+    rng: &mut fastrand::Rng,
     position: Vec3,
     player_position: Vec3,
-    enemy_type: EnemyType,
+    archetype: Arc<EnemyArchetype>,
     formation_role: Option<FormationRole>,
-    _difficulty_level: f32,
+    formation_offset: Vec3,
 ) -> Entity {
-    let (color, scale, speed, health, damage, preferred_distance) = match enemy_type {
-        EnemyType::Fighter => (Color::srgb(0.8, 0.2, 0.2), 2.0, 60.0, 50.0, 10.0, 40.0),
-        EnemyType::Bomber => (Color::srgb(0.4, 0.4, 0.4), 3.0, 40.0, 100.0, 20.0, 60.0),
-        EnemyType::Ace => (Color::srgb(0.2, 0.2, 0.8), 1.8, 80.0, 75.0, 15.0, 30.0),
-    };
+    let pilot = EnemyPilot::draw(rng, &archetype);
     
-    // Formation members are more tactical
-    let personality = match formation_role {
-        Some(FormationRole::Leader) => PilotPersonality::Tactical,
-        Some(FormationRole::Wingman(_)) => {
-            if fastrand::f32() < 0.5 { PilotPersonality::Tactical } 
-            else { PilotPersonality::Defensive }
-        },
-        _ => PilotPersonality::Tactical,
+    // Formation members carry lighter markings
+    let livery = if formation_role.is_some() {
+        models.formation_livery(archetype.model)
+    } else {
+        models.livery(archetype.model)
     };
-    
-    let maneuver_skill = 0.5 + fastrand::f32() * 0.3;
-    let reaction_time = 0.3 + fastrand::f32() * 0.2;
-    
-    // Spawn enemy
-    let enemy_entity = commands.spawn((
-        Transform::from_translation(position)
-            .looking_at(player_position, Vec3::Y),
-        Visibility::default(),
-        Enemy {
-            speed,
-            damage,
-            attack_range: 50.0,
-            pursuit_range: 200.0,
-            enemy_type,
-            behavior_state: EnemyBehaviorState::Formation,
-            state_timer: 0.0,
-            evasion_angle: 0.0,
-            preferred_distance,
-            personality,
-            formation_role,
-            maneuver_skill,
-            reaction_time,
+    spawn_enemy(
+        commands,
+        models,
+        archetype,
+        Transform::from_translation(position).looking_at(player_position, Vec3::Y),
+        EnemyPilot {
             morale: 1.2, // Formation bonus
-            last_taunt_time: 0.0,
-        },
-        Health {
-            current: health,
-            max: health,
+            formation_role,
+            formation_offset,
+            ..pilot
         },
-        GameEntity,
-    )).id();
-    
-    // Enemy body
-    let body = commands.spawn((
-        Mesh3d(meshes.add(Cuboid::new(1.2 * scale, 0.8 * scale, 3.0 * scale))),
-        MeshMaterial3d(materials.add(StandardMaterial {
-            base_color: color,
-            metallic: 0.7,
-            perceptual_roughness: 0.3,
-            ..default()
-        })),
-        Transform::default(),
-    )).id();
-    
-    // Enemy wings with formation markings
-    let wing_color = if formation_role.is_some() {
-        color.lighter(0.1) // Slightly different color for formation members
-    } else {
-        color.darker(0.2)
-    };
-    
-    let left_wing = commands.spawn((
-        Mesh3d(meshes.add(Cuboid::new(5.0 * scale, 0.2 * scale, 1.5 * scale))),
-        MeshMaterial3d(materials.add(StandardMaterial {
-            base_color: wing_color,
-            metallic: 0.6,
-            ..default()
-        })),
-        Transform::from_xyz(-3.0 * scale, 0.0, 0.0),
-    )).id();
-    
-    let right_wing = commands.spawn((
-        Mesh3d(meshes.add(Cuboid::new(5.0 * scale, 0.2 * scale, 1.5 * scale))),
-        MeshMaterial3d(materials.add(StandardMaterial {
-            base_color: wing_color,
-            metallic: 0.6,
-            ..default()
-        })),
-        Transform::from_xyz(3.0 * scale, 0.0, 0.0),
-    )).id();
-    
-    // Build hierarchy
-    commands.entity(enemy_entity).add_children(&[body]);
-    commands.entity(body).add_children(&[left_wing, right_wing]);
-    
-    enemy_entity
+        livery,
+    )
 }
 
 pub fn enemy_ai_system(
@@ -427,7 +467,9 @@ pub fn enemy_ai_system(
     )>,
     player_query: Query<(&Transform, &crate::Aircraft), With<Aircraft>>,
     time: Res<Time>,
+    run_clock: Res<RunClock>,
     mut chatter_events: EventWriter<RadioChatterEvent>,
+    mut rng: ResMut<GameRng>,
 ) {
     if let Ok((player_transform, player_aircraft)) = player_query.single() {
         let player_velocity = player_transform.forward() * player_aircraft.speed;
@@ -441,12 +483,14 @@ pub fn enemy_ai_system(
         }
         
         // Now update enemies
-        for (entity, mut enemy_transform, mut enemy, health) in set.p0().iter_mut() {
+        for (_, mut enemy_transform, mut enemy, health) in set.p0().iter_mut() {
             let to_player = player_transform.translation - enemy_transform.translation;
             let distance = to_player.length();
             
             // Update state timer and reaction
             enemy.state_timer -= time.delta_secs();
+            enemy.alert_timer -= time.delta_secs();
+            let alerted = enemy.alert_timer > 0.0;
             
             // Update morale based on health
             enemy.morale = (health.current / health.max) * 1.2;
@@ -464,26 +508,26 @@ pub fn enemy_ai_system(
             };
             
             // Radio chatter system
-            if time.elapsed_secs() - enemy.last_taunt_time > 5.0 {
-                if let Some(message) = generate_radio_chatter(&enemy, distance, health.current / health.max) {
+            if run_clock.elapsed - enemy.last_taunt_time > 5.0 {
+                if let Some(message) = generate_radio_chatter(&enemy, distance, health.current / health.max, &mut rng.ai) {
                     chatter_events.write(RadioChatterEvent {
                         message,
-                        sender_type: enemy.enemy_type,
+                        sender: enemy.archetype.clone(),
                         personality: enemy.personality,
                     });
-                    enemy.last_taunt_time = time.elapsed_secs();
+                    enemy.last_taunt_time = run_clock.elapsed;
                 }
             }
             
             // State transitions
             match enemy.behavior_state {
                 EnemyBehaviorState::Patrol => {
-                    if distance < enemy.pursuit_range {
+                    if distance < enemy.pursuit_range || alerted {
                         enemy.behavior_state = EnemyBehaviorState::Pursuing;
                         enemy.state_timer = 2.0;
                     } else {
                         // Patrol behavior - circle around spawn point
-                        let patrol_angle = time.elapsed_secs() * 0.5;
+                        let patrol_angle = run_clock.elapsed * 0.5;
                         let patrol_offset = Vec3::new(patrol_angle.cos() * 50.0, 0.0, patrol_angle.sin() * 50.0);
                         let patrol_target = enemy_transform.translation + patrol_offset;
                         
@@ -500,7 +544,7 @@ pub fn enemy_ai_system(
                     if distance < enemy.attack_range {
                         enemy.behavior_state = EnemyBehaviorState::Attacking;
                         enemy.state_timer = 1.5;
-                    } else if distance > enemy.pursuit_range * 1.5 {
+                    } else if distance > enemy.pursuit_range * 1.5 && !alerted {
                         enemy.behavior_state = EnemyBehaviorState::Patrol;
                     } else {
                         // Advanced pursuit with prediction
@@ -523,14 +567,14 @@ pub fn enemy_ai_system(
                         // Personality-based attack pattern selection
                         let next_behavior = match enemy.personality {
                             PilotPersonality::Aggressive => {
-                                if fastrand::f32() < 0.3 && enemy.maneuver_skill > 0.6 {
+                                if rng.ai.f32() < 0.3 && enemy.maneuver_skill > 0.6 {
                                     EnemyBehaviorState::Maneuvering(ManeuverType::BarrelRoll)
                                 } else {
                                     EnemyBehaviorState::Strafing
                                 }
                             }
                             PilotPersonality::Defensive => {
-                                if fastrand::f32() < 0.7 {
+                                if rng.ai.f32() < 0.7 {
                                     EnemyBehaviorState::Evading
                                 } else {
                                     EnemyBehaviorState::Strafing
@@ -543,14 +587,14 @@ pub fn enemy_ai_system(
                                         ManeuverType::Immelmann,
                                         ManeuverType::ChandelleTurn,
                                     ];
-                                    EnemyBehaviorState::Maneuvering(maneuvers[fastrand::usize(0..maneuvers.len())])
+                                    EnemyBehaviorState::Maneuvering(maneuvers[rng.ai.usize(0..maneuvers.len())])
                                 } else {
                                     EnemyBehaviorState::Strafing
                                 }
                             }
                             PilotPersonality::Veteran => {
                                 // Veterans make unpredictable choices
-                                match fastrand::u32(0..4) {
+                                match rng.ai.u32(0..4) {
                                     0 if enemy.maneuver_skill > 0.7 => {
                                         EnemyBehaviorState::Maneuvering(ManeuverType::HighYoYo)
                                     }
@@ -567,7 +611,7 @@ pub fn enemy_ai_system(
                         };
                         
                         enemy.behavior_state = next_behavior;
-                        enemy.evasion_angle = if fastrand::f32() > 0.5 { 1.0 } else { -1.0 };
+                        enemy.evasion_angle = if rng.ai.f32() > 0.5 { 1.0 } else { -1.0 };
                         enemy.state_timer = match next_behavior {
                             EnemyBehaviorState::Maneuvering(_) => 1.5,
                             _ => 2.0,
@@ -583,12 +627,10 @@ pub fn enemy_ai_system(
                         }
                         
                         // Keep facing player with personality-adjusted lead
-                        let lead_factor = match (enemy.enemy_type, enemy.personality) {
-                            (EnemyType::Ace, _) => 0.3 * enemy.maneuver_skill,
-                            (_, PilotPersonality::Veteran) => 0.25 * enemy.maneuver_skill,
-                            (_, PilotPersonality::Aggressive) => 0.15,
-                            (EnemyType::Fighter, _) => 0.1,
-                            (EnemyType::Bomber, _) => 0.0,
+                        let lead_factor = match enemy.personality {
+                            PilotPersonality::Veteran => enemy.archetype.aim_lead.max(0.25 * enemy.maneuver_skill),
+                            PilotPersonality::Aggressive => enemy.archetype.aim_lead.max(0.15),
+                            _ => enemy.archetype.aim_lead,
                         };
                         
                         let aim_point = player_transform.translation + player_velocity * lead_factor;
@@ -629,9 +671,9 @@ pub fn enemy_ai_system(
                         enemy.behavior_state = EnemyBehaviorState::Pursuing;
                     } else {
                         // Evasive maneuvers
-                        let evasion_pattern = (time.elapsed_secs() * 3.0 + enemy.evasion_angle).sin();
+                        let evasion_pattern = (run_clock.elapsed * 3.0 + enemy.evasion_angle).sin();
                         let roll = evasion_pattern * 0.5;
-                        let pitch = (time.elapsed_secs() * 2.0).cos() * 0.3;
+                        let pitch = (run_clock.elapsed * 2.0).cos() * 0.3;
                         
                         enemy_transform.rotate_local_x(pitch * time.delta_secs());
                         enemy_transform.rotate_local_z(roll * time.delta_secs());
@@ -681,14 +723,8 @@ pub fn enemy_ai_system(
                                 // Find leader and maintain formation
                                 if let Some((leader_position, leader_rotation)) = formation_data.get(leader_entity) {
                                     // Calculate desired position relative to leader
-                                    let offset = if entity.index() % 2 == 0 {
-                                        Vec3::new(-15.0, 0.0, -10.0)
-                                    } else {
-                                        Vec3::new(15.0, 0.0, -10.0)
-                                    };
-                                    
                                     let desired_position = *leader_position + 
-                                        *leader_rotation * offset;
+                                        *leader_rotation * enemy.formation_offset;
                                     
                                     let to_desired = desired_position - enemy_transform.translation;
                                     let formation_distance = to_desired.length();
@@ -724,7 +760,7 @@ pub fn enemy_ai_system(
                 
                 EnemyBehaviorState::Maneuvering(maneuver_type) => {
                     // Execute advanced maneuvers
-                    perform_maneuver(&mut enemy_transform, &mut enemy, maneuver_type, &time);
+                    perform_maneuver(&mut enemy_transform, &mut enemy, maneuver_type, &time, &run_clock);
                     
                     if enemy.state_timer <= 0.0 {
                         // Return to appropriate state after maneuver
@@ -764,7 +800,7 @@ pub fn enemy_ai_system(
                     if distance < enemy.pursuit_range {
                         // Circle at medium range
                         let support_radius = enemy.preferred_distance * 1.5;
-                        let angle = time.elapsed_secs() * 0.3;
+                        let angle = run_clock.elapsed * 0.3;
                         
                         let target_position = player_transform.translation + Vec3::new(
                             angle.cos() * support_radius,
@@ -784,11 +820,18 @@ pub fn enemy_ai_system(
                 }
             }
             
+            // A critically damaged aircraft can't be held level: it keeps rolling one way and sinks
+            if enemy.damage_state == DamageState::Critical {
+                let roll_direction = if enemy.evasion_angle < 0.0 { -1.0 } else { 1.0 };
+                enemy_transform.rotate_local_z(roll_direction * CRITICAL_ROLL_RATE * time.delta_secs());
+                enemy_transform.translation.y -= CRITICAL_SINK_RATE * time.delta_secs();
+            }
+            
             // Keep enemy within reasonable bounds
             enemy_transform.translation.y = enemy_transform.translation.y.clamp(10.0, 300.0);
             
             // Add slight wobble for more organic movement
-            let wobble = (time.elapsed_secs() * 2.0 + enemy.evasion_angle).sin() * 0.02;
+            let wobble = (run_clock.elapsed * 2.0 + enemy.evasion_angle).sin() * 0.02;
             enemy_transform.rotate_local_z(wobble);
         }
     }
@@ -799,6 +842,7 @@ fn perform_maneuver(
     enemy: &mut Enemy,
     maneuver_type: ManeuverType,
     time: &Time,
+    run_clock: &RunClock,
 ) {
     let skill_factor = enemy.maneuver_skill;
     let delta = time.delta_secs();
@@ -879,7 +923,7 @@ fn perform_maneuver(
         }
         ManeuverType::Scissors => {
             // Weaving pattern
-            let oscillation = (time.elapsed_secs() * 4.0).sin();
+            let oscillation = (run_clock.elapsed * 4.0).sin();
             transform.rotate_local_z(oscillation * 2.0 * skill_factor * delta);
             transform.rotate_local_x(oscillation * 0.5 * skill_factor * delta);
             
@@ -897,9 +941,9 @@ fn perform_maneuver(
     }
 }
 
-fn generate_radio_chatter(enemy: &Enemy, distance: f32, health_ratio: f32) -> Option<String> {
+fn generate_radio_chatter(enemy: &Enemy, distance: f32, health_ratio: f32, rng: &mut fastrand::Rng) -> Option<String> {
     // Don't spam chatter
-    if fastrand::f32() > 0.3 {
+    if rng.f32() > 0.3 {
         return None;
     }
     
@@ -964,7 +1008,7 @@ fn generate_radio_chatter(enemy: &Enemy, distance: f32, health_ratio: f32) -> Op
     };
     
     if !message.is_empty() {
-        Some(message[fastrand::usize(0..message.len())].to_string())
+        Some(message[rng.usize(0..message.len())].to_string())
     } else {
         None
     }
@@ -972,21 +1016,20 @@ fn generate_radio_chatter(enemy: &Enemy, distance: f32, health_ratio: f32) -> Op
 
 pub fn enemy_shooting_system(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    enemy_query: Query<(Entity, &Transform, &Enemy)>,
+    shared_assets: Res<SharedAssets>,
+    mut bullet_pool: ResMut<EntityPool<EnemyBullet>>,
+    mut bursts: EventWriter<ParticleBurst>,
+    mut enemy_query: Query<(&Transform, &mut Enemy)>,
     player_query: Query<&Transform, With<Aircraft>>,
     time: Res<Time>,
-    mut shoot_timers: Local<HashMap<Entity, f32>>,
+    mut rng: ResMut<GameRng>,
 ) {
     if let Ok(player_transform) = player_query.single() {
-        // Update all timers
-        for timer in shoot_timers.values_mut() {
-            *timer -= time.delta_secs();
-        }
-        shoot_timers.retain(|_, timer| *timer > 0.0);
-        
-        for (enemy_entity, enemy_transform, enemy) in enemy_query.iter() {
+        for (enemy_transform, mut enemy) in enemy_query.iter_mut() {
+            if enemy.shoot_cooldown > 0.0 {
+                enemy.shoot_cooldown -= time.delta_secs();
+            }
+            
             let to_player = player_transform.translation - enemy_transform.translation;
             let distance = to_player.length();
             
@@ -1000,36 +1043,34 @@ pub fn enemy_shooting_system(
                 
             if can_attack {
                 // Check if this specific enemy is on cooldown
-                let can_shoot = !shoot_timers.contains_key(&enemy_entity);
+                let can_shoot = enemy.shoot_cooldown <= 0.0;
                 
                 // Reaction time affects when they start shooting
                 let reaction_delay_passed = enemy.state_timer < (2.0 - enemy.reaction_time);
                 
                 if can_shoot && reaction_delay_passed {
                     // Variable fire rate based on enemy type and personality
-                    let fire_rate = match (enemy.enemy_type, enemy.personality) {
-                        (EnemyType::Ace, _) => 0.8,
-                        (_, PilotPersonality::Aggressive) => 0.9,
-                        (EnemyType::Fighter, _) => 1.2,
-                        (_, PilotPersonality::Defensive) => 1.8,
-                        (EnemyType::Bomber, _) => 2.0,
+                    let weapon = &enemy.archetype.weapon;
+                    let fire_rate = match enemy.personality {
+                        PilotPersonality::Aggressive => weapon.cooldown.min(0.9),
+                        PilotPersonality::Defensive => weapon.cooldown.min(1.8),
+                        _ => weapon.cooldown,
                     };
+                    let (bullet_speed, damage, base_accuracy) = (weapon.bullet_speed, weapon.damage, weapon.spread);
                     
-                    shoot_timers.insert(enemy_entity, fire_rate);
+                    enemy.shoot_cooldown = fire_rate;
                     
-                    // Calculate lead for better accuracy
-                    let bullet_speed = 120.0;
-                    let time_to_target = distance / bullet_speed;
+                    // Calculate lead for better accuracy; only half of it, so they can be outflown
                     let player_velocity = player_transform.forward() * 50.0; // Approximate player speed
-                    let predicted_position = player_transform.translation + player_velocity * time_to_target * 0.5;
+                    let predicted_position = lead_position(
+                        enemy_transform.translation,
+                        player_transform.translation,
+                        player_velocity,
+                        bullet_speed,
+                        0.5,
+                    );
                     
                     // Accuracy varies by enemy type, personality, and skill
-                    let base_accuracy = match enemy.enemy_type {
-                        EnemyType::Ace => 0.05,
-                        EnemyType::Fighter => 0.15,
-                        EnemyType::Bomber => 0.25,
-                    };
-                    
                     let personality_modifier = match enemy.personality {
                         PilotPersonality::Veteran => 0.7,
                         PilotPersonality::Tactical => 0.8,
@@ -1041,9 +1082,9 @@ pub fn enemy_shooting_system(
                     let accuracy_spread = base_accuracy * personality_modifier * (2.0 - enemy.maneuver_skill);
                     
                     let spread = Vec3::new(
-                        (fastrand::f32() - 0.5) * accuracy_spread,
-                        (fastrand::f32() - 0.5) * accuracy_spread,
-                        (fastrand::f32() - 0.5) * accuracy_spread,
+                        (rng.ai.f32() - 0.5) * accuracy_spread,
+                        (rng.ai.f32() - 0.5) * accuracy_spread,
+                        (rng.ai.f32() - 0.5) * accuracy_spread,
                     );
                     
                     let to_predicted = (predicted_position - enemy_transform.translation).normalize() + spread;
@@ -1052,99 +1093,37 @@ pub fn enemy_shooting_system(
                     // Spawn bullet with offset for visual appeal
                     let bullet_spawn = enemy_transform.translation + enemy_transform.forward() * 4.0;
                     
-                    commands.spawn((
-                        Mesh3d(meshes.add(Sphere::new(0.3))),
-                        MeshMaterial3d(materials.add(StandardMaterial {
-                            base_color: Color::srgb(1.0, 0.0, 0.0),
-                            emissive: Color::srgb(1.0, 0.0, 0.0).into(),
-                            ..default()
-                        })),
-                        Transform::from_translation(bullet_spawn),
+                    let bullet_transform = Transform::from_translation(bullet_spawn).with_scale(Vec3::splat(0.3));
+                    bullet_pool.spawn(&mut commands, (
+                        Mesh3d(shared_assets.sphere.clone()),
+                        MeshMaterial3d(shared_assets.glow(Tint::Enemy)),
+                        bullet_transform,
+                        InterpolatedTransform::new(bullet_transform),
                         EnemyBullet {
                             velocity: bullet_velocity,
-                            damage: enemy.damage,
+                            damage,
                             lifetime: 3.0,
                         },
-                        GameEntity,
                     ));
+                    bursts.write(ParticleBurst {
+                        preset: ParticlePreset::MuzzleFlash,
+                        position: bullet_spawn,
+                        velocity: enemy_transform.forward() * 20.0,
+                        count: 3,
+                        color: Some(Color::srgb(1.0, 0.4, 0.2)),
+                    });
                 }
             }
         }
     }
 }
 
-pub fn player_shooting_system(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    player_query: Query<&Transform, With<Aircraft>>,
-    keyboard: Res<ButtonInput<KeyCode>>,
-    mouse: Res<ButtonInput<MouseButton>>,
-    time: Res<Time>,
-    mut shoot_cooldown: Local<f32>,
-    active_powerups: Res<crate::powerups::ActivePowerUps>,
-) {
-    // Always update cooldown
-    if *shoot_cooldown > 0.0 {
-        *shoot_cooldown -= time.delta_secs();
-    }
-    
-    // Adjust fire rate based on powerups
-    let fire_rate = if active_powerups.rapid_fire { 0.1 } else { 0.25 };
-    
-    // Use Left Mouse Button or F key for shooting (not Space)
-    if (*shoot_cooldown <= 0.0) && (keyboard.pressed(KeyCode::KeyF) || mouse.pressed(MouseButton::Left)) {
-        if let Ok(player_transform) = player_query.single() {
-            *shoot_cooldown = fire_rate;
-            
-            // Determine bullet pattern based on powerups
-            let bullet_offsets = if active_powerups.triple_shot {
-                vec![-3.0, 0.0, 3.0]
-            } else {
-                vec![-2.0, 2.0]
-            };
-            
-            // Enhanced damage with powerups
-            let damage = if active_powerups.homing_missiles { 50.0 } else { 25.0 };
-            
-            // Spawn bullets
-            for offset in bullet_offsets {
-                let bullet_spawn = player_transform.translation 
-                    + player_transform.forward() * 5.0
-                    + player_transform.right() * offset;
-                
-                let (color, emissive) = if active_powerups.homing_missiles {
-                    (Color::srgb(1.0, 0.0, 0.5), Color::srgb(1.0, 0.0, 0.5))
-                } else if active_powerups.triple_shot {
-                    (Color::srgb(1.0, 0.0, 1.0), Color::srgb(1.0, 0.0, 1.0))
-                } else {
-                    (Color::srgb(0.0, 1.0, 1.0), Color::srgb(0.0, 1.0, 1.0))
-                };
-                
-                commands.spawn((
-                    Mesh3d(meshes.add(Sphere::new(0.3))),
-                    MeshMaterial3d(materials.add(StandardMaterial {
-                        base_color: color,
-                        emissive: emissive.into(),
-                        ..default()
-                    })),
-                    Transform::from_translation(bullet_spawn),
-                    PlayerBullet {
-                        velocity: player_transform.forward() * 250.0,
-                        damage,
-                        lifetime: 3.0,
-                    },
-                    GameEntity,
-                ));
-            }
-        }
-    }
-}
-
 pub fn update_bullets_system(
     mut commands: Commands,
     mut bullet_query: Query<(Entity, &mut Transform, &mut PlayerBullet), Without<EnemyBullet>>,
     mut enemy_bullet_query: Query<(Entity, &mut Transform, &mut EnemyBullet)>,
+    mut bullet_pool: ResMut<EntityPool<PlayerBullet>>,
+    mut enemy_bullet_pool: ResMut<EntityPool<EnemyBullet>>,
     time: Res<Time>,
 ) {
     // Update player bullets
@@ -1153,7 +1132,7 @@ pub fn update_bullets_system(
         bullet.lifetime -= time.delta_secs();
         
         if bullet.lifetime <= 0.0 {
-            commands.entity(entity).despawn();
+            bullet_pool.release(&mut commands, entity);
         }
     }
     
@@ -1163,7 +1142,7 @@ pub fn update_bullets_system(
         bullet.lifetime -= time.delta_secs();
         
         if bullet.lifetime <= 0.0 {
-            commands.entity(entity).despawn();
+            enemy_bullet_pool.release(&mut commands, entity);
         }
     }
 }
@@ -1171,49 +1150,218 @@ pub fn update_bullets_system(
 pub fn bullet_collision_system(
     mut commands: Commands,
     bullet_query: Query<(Entity, &Transform, &PlayerBullet)>,
-    mut enemy_query: Query<(Entity, &Transform, &mut Health, &mut Enemy), Without<PlayerBullet>>,
+    mut enemy_query: Query<(&Transform, &mut Health, &mut Enemy), Without<PlayerBullet>>,
+    mut ground_query: Query<(&Transform, &mut Health, &GroundUnit), (Without<PlayerBullet>, Without<Enemy>)>,
+    mut boss_query: Query<(Entity, &Transform, &mut Health, &mut Boss), (Without<PlayerBullet>, Without<Enemy>, Without<GroundUnit>)>,
+    mut part_query: Query<(&BossPart, &mut Health), (Without<Boss>, Without<Enemy>, Without<GroundUnit>)>,
     mut destroyed_events: EventWriter<EnemyDestroyedEvent>,
     mut game_stats: ResMut<GameStats>,
+    mut bullet_pool: ResMut<EntityPool<PlayerBullet>>,
+    time: Res<Time>,
+    spatial_index: Res<SpatialIndex>,
 ) {
-    for (bullet_entity, bullet_transform, bullet) in bullet_query.iter() {
-        for (enemy_entity, enemy_transform, mut health, mut enemy) in enemy_query.iter_mut() {
-            let distance = bullet_transform.translation.distance(enemy_transform.translation);
+    'bullets: for (bullet_entity, bullet_transform, bullet) in bullet_query.iter() {
+        // The stretch of path the bullet covered this step in update_bullets_system
+        let bullet_end = bullet_transform.translation;
+        let bullet_start = bullet_end - bullet.velocity * time.delta_secs();
+        
+        for enemy_entity in spatial_index.enemies.query_segment(bullet_start, bullet_end, 5.0) {
+            let Ok((enemy_transform, mut health, mut enemy)) = enemy_query.get_mut(enemy_entity) else {
+                continue;
+            };
             
-            if distance < 5.0 {  // Increased hit box for easier targeting
-                // Damage enemy
-                health.current -= bullet.damage;
+            // Increased hit box for easier targeting
+            if segment_hits_sphere(bullet_start, bullet_end, enemy_transform.translation, 5.0) {
+                if let Some(destroyed) = damage_enemy(
+                    &mut commands,
+                    &mut game_stats,
+                    enemy_entity,
+                    enemy_transform,
+                    &mut health,
+                    &mut enemy,
+                    bullet.damage,
+                ) {
+                    destroyed_events.write(destroyed);
+                }
                 
                 // Remove bullet
-                commands.entity(bullet_entity).despawn();
-                
-                // Check if enemy is destroyed
-                if health.current <= 0.0 {
-                    // Send destroyed event
-                    destroyed_events.write(EnemyDestroyedEvent {
-                        position: enemy_transform.translation,
-                        enemy_type: enemy.enemy_type,
-                    });
-                    
-                    // Award points
-                    let points = match enemy.enemy_type {
-                        EnemyType::Fighter => 50,
-                        EnemyType::Bomber => 100,
-                        EnemyType::Ace => 200,
+                bullet_pool.release(&mut commands, bullet_entity);
+                continue 'bullets;
+            }
+        }
+        
+        for unit_entity in spatial_index.ground_units.query_segment(bullet_start, bullet_end, GROUND_UNIT_HIT_RADIUS) {
+            let Ok((unit_transform, mut health, unit)) = ground_query.get_mut(unit_entity) else {
+                continue;
+            };
+            if segment_hits_sphere(bullet_start, bullet_end, unit.hit_center(unit_transform), unit.kind.hit_sphere().1) {
+                if let Some(destroyed) = damage_ground_unit(
+                    &mut commands,
+                    &mut game_stats,
+                    unit_entity,
+                    unit_transform,
+                    &mut health,
+                    unit,
+                    bullet.damage,
+                ) {
+                    destroyed_events.write(destroyed);
+                }
+                bullet_pool.release(&mut commands, bullet_entity);
+                continue 'bullets;
+            }
+        }
+        
+        for (boss_entity, boss_transform, mut boss_health, mut boss) in boss_query.iter_mut() {
+            let parts = boss.parts.iter().filter_map(|&part| part_query.get(part).ok().map(|(boss_part, _)| (part, boss_part)));
+            let Some((hit, _)) = boss_hit(boss_transform, boss.kind, parts, bullet_start, bullet_end, 0.0) else {
+                continue;
+            };
+            let destroyed = match hit {
+                BossHit::Part(part_entity) => {
+                    let Ok((part, mut part_health)) = part_query.get_mut(part_entity) else {
+                        continue;
                     };
-                    game_stats.score += points;
-                    game_stats.enemies_destroyed += 1;
-                    
-                    // Remove enemy
-                    commands.entity(enemy_entity).despawn();
-                } else if health.current < health.max * 0.3 && !matches!(enemy.behavior_state, EnemyBehaviorState::Retreating) {
-                    // Low health - retreat
-                    enemy.behavior_state = EnemyBehaviorState::Retreating;
-                    enemy.state_timer = 5.0;
+                    damage_boss_part(
+                        &mut commands,
+                        &mut game_stats,
+                        part_entity,
+                        part,
+                        &mut part_health,
+                        boss_transform,
+                        &mut boss_health,
+                        &mut boss,
+                        bullet.damage,
+                    )
                 }
-                
-                break;
+                BossHit::Hull => damage_boss(
+                    &mut commands,
+                    &mut game_stats,
+                    boss_entity,
+                    boss_transform,
+                    &mut boss_health,
+                    &boss,
+                    bullet.damage,
+                ),
+            };
+            if let Some(destroyed) = destroyed {
+                destroyed_events.write(destroyed);
             }
+            bullet_pool.release(&mut commands, bullet_entity);
+            break;
+        }
+    }
+}
+
+// Where to aim a projectile fired from `shooter` at `projectile_speed` so it meets a target
+// holding its current velocity, leading the target by `lead_fraction` of the full amount
+pub fn lead_position(shooter: Vec3, target: Vec3, target_velocity: Vec3, projectile_speed: f32, lead_fraction: f32) -> Vec3 {
+    let time_to_target = (target - shooter).length() / projectile_speed;
+    target + target_velocity * time_to_target * lead_fraction
+}
+
+// Takes `damage` off an enemy. One it destroys is scored and starts to crash, and the event to
+// send for it is returned; one left badly hurt flies worse and retreats. Enemies already
+// destroyed earlier in the step, but not yet turned into wrecks, take no more hits.
+pub fn damage_enemy(
+    commands: &mut Commands,
+    game_stats: &mut GameStats,
+    enemy_entity: Entity,
+    transform: &Transform,
+    health: &mut Health,
+    enemy: &mut Enemy,
+    damage: f32,
+) -> Option<EnemyDestroyedEvent> {
+    if health.current <= 0.0 {
+        return None;
+    }
+    health.current -= damage;
+    
+    if health.current <= 0.0 {
+        // Award points
+        game_stats.score += enemy.archetype.score;
+        game_stats.enemies_destroyed += 1;
+        
+        start_crash(commands, enemy_entity, transform, enemy);
+        return Some(EnemyDestroyedEvent {
+            position: transform.translation,
+            enemy: DestroyedEnemy::Aircraft(enemy.archetype.clone()),
+        });
+    }
+    
+    let damage_state = DamageState::of(health);
+    if damage_state != enemy.damage_state {
+        enemy.speed = enemy.archetype.speed * damage_state.speed_factor();
+        enemy.maneuver_skill *= damage_state.skill_factor() / enemy.damage_state.skill_factor();
+        enemy.damage_state = damage_state;
+    }
+    
+    if health.current < health.max * 0.3 && !matches!(enemy.behavior_state, EnemyBehaviorState::Retreating) {
+        // Low health - retreat
+        enemy.behavior_state = EnemyBehaviorState::Retreating;
+        enemy.state_timer = 5.0;
+    }
+    None
+}
+
+// Turns a destroyed enemy into a wreck that spirals down from where it was flying
+pub fn start_crash(commands: &mut Commands, enemy_entity: Entity, transform: &Transform, enemy: &Enemy) {
+    let direction = if enemy.evasion_angle < 0.0 { -1.0 } else { 1.0 };
+    commands.entity(enemy_entity).remove::<(Enemy, Health)>().insert(Crashing {
+        archetype: enemy.archetype.clone(),
+        velocity: transform.forward() * enemy.speed,
+        spiral_rate: CRASH_SPIRAL_RATE * direction,
+        roll_rate: CRASH_ROLL_RATE * direction,
+        roll: 0.0,
+        time_left: CRASH_TIMEOUT,
+    });
+}
+
+// Wrecks fall under gravity along a spiral, and blow up where they hit the ground
+pub fn crashing_aircraft_system(
+    mut commands: Commands,
+    mut wreck_query: Query<(Entity, &mut Transform, &mut Crashing)>,
+    mut bursts: EventWriter<ParticleBurst>,
+    height_field: Res<TerrainHeightField>,
+    time: Res<Time>,
+) {
+    for (entity, mut transform, mut crash) in wreck_query.iter_mut() {
+        let delta = time.delta_secs();
+        crash.velocity = Quat::from_rotation_y(crash.spiral_rate * delta) * crash.velocity;
+        crash.velocity.y -= CRASH_GRAVITY * delta;
+        crash.roll += crash.roll_rate * delta;
+        crash.time_left -= delta;
+        transform.translation += crash.velocity * delta;
+        if let Ok(direction) = Dir3::new(crash.velocity) {
+            transform.rotation = Transform::IDENTITY.looking_to(direction, Vec3::Y).rotation * Quat::from_rotation_z(crash.roll);
+        }
+        
+        let ground = height_field.height_at(transform.translation.x, transform.translation.z);
+        if transform.translation.y > ground && crash.time_left > 0.0 {
+            continue;
         }
+        let position = transform.translation.with_y(transform.translation.y.max(ground));
+        bursts.write(ParticleBurst {
+            preset: ParticlePreset::Explosion,
+            position,
+            velocity: Vec3::Y * 8.0,
+            count: 60,
+            color: Some(crash.archetype.explosion_color()),
+        });
+        bursts.write(ParticleBurst {
+            preset: ParticlePreset::Fire,
+            position,
+            velocity: Vec3::Y * 4.0,
+            count: 30,
+            color: None,
+        });
+        bursts.write(ParticleBurst {
+            preset: ParticlePreset::Smoke,
+            position,
+            velocity: Vec3::Y * 2.0,
+            count: 20,
+            color: None,
+        });
+        commands.entity(entity).despawn();
     }
 }
 
@@ -1223,12 +1371,16 @@ pub fn player_damage_system(
     mut player_query: Query<(&Transform, &mut Health), With<Aircraft>>,
     mut game_state: ResMut<NextState<crate::game_state::GameState>>,
     active_powerups: Res<crate::powerups::ActivePowerUps>,
+    mut bullet_pool: ResMut<EntityPool<EnemyBullet>>,
+    time: Res<Time>,
 ) {
     if let Ok((player_transform, mut player_health)) = player_query.single_mut() {
         for (bullet_entity, bullet_transform, bullet) in enemy_bullet_query.iter() {
-            let distance = bullet_transform.translation.distance(player_transform.translation);
+            let bullet_end = bullet_transform.translation;
+            let bullet_start = bullet_end - bullet.velocity * time.delta_secs();
             
-            if distance < 5.0 {  // Increased hit box for easier targeting
+            // Increased hit box for easier targeting
+            if segment_hits_sphere(bullet_start, bullet_end, player_transform.translation, 5.0) {
                 // Check for shield
                 if !active_powerups.shield {
                     // Damage player (no shield)
@@ -1239,7 +1391,7 @@ pub fn player_damage_system(
                 }
                 
                 // Remove bullet
-                commands.entity(bullet_entity).despawn();
+                bullet_pool.release(&mut commands, bullet_entity);
                 
                 // Check if player is destroyed
                 if player_health.current <= 0.0 {
@@ -1251,40 +1403,77 @@ pub fn player_damage_system(
 }
 
 pub fn spawn_explosion_particles(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
     mut destroyed_events: EventReader<EnemyDestroyedEvent>,
+    mut bursts: EventWriter<ParticleBurst>,
 ) {
     for event in destroyed_events.read() {
-        // Spawn explosion particles
-        for _ in 0..20 {
-            let velocity = Vec3::new(
-                (fastrand::f32() - 0.5) * 30.0,
-                fastrand::f32() * 20.0,
-                (fastrand::f32() - 0.5) * 30.0,
-            );
-            
-            let color = match event.enemy_type {
-                EnemyType::Fighter => Color::srgb(1.0, 0.5, 0.0),
-                EnemyType::Bomber => Color::srgb(0.8, 0.8, 0.0),
-                EnemyType::Ace => Color::srgb(0.5, 0.5, 1.0),
-            };
-            
-            commands.spawn((
-                Mesh3d(meshes.add(Sphere::new(0.5))),
-                MeshMaterial3d(materials.add(StandardMaterial {
-                    base_color: color,
-                    emissive: color.into(),
-                    ..default()
-                })),
-                Transform::from_translation(event.position),
-                crate::targets::ParticleEffect {
-                    lifetime: 1.0,
-                    velocity,
-                },
-                GameEntity,
-            ));
+        match &event.enemy {
+            // The big explosion comes when the wreck hits the ground; this is the hit that brings it down
+            DestroyedEnemy::Aircraft(archetype) => {
+                bursts.write(ParticleBurst {
+                    preset: ParticlePreset::Explosion,
+                    position: event.position,
+                    velocity: Vec3::Y * 5.0,
+                    count: 20,
+                    color: Some(archetype.explosion_color()),
+                });
+            }
+            // Bigger installations go up in bigger explosions
+            DestroyedEnemy::Ground(kind) => {
+                let size = kind.health() / 100.0;
+                for (preset, count, velocity) in [
+                    (ParticlePreset::Explosion, 40.0 * size, Vec3::Y * 10.0),
+                    (ParticlePreset::Fire, 25.0 * size, Vec3::Y * 5.0),
+                    (ParticlePreset::Smoke, 20.0 * size, Vec3::Y * 3.0),
+                ] {
+                    bursts.write(ParticleBurst {
+                        preset,
+                        position: event.position,
+                        velocity,
+                        count: count as usize,
+                        color: None,
+                    });
+                }
+            }
+            // A boss goes up in several blasts spread over its size
+            DestroyedEnemy::Boss(kind) => {
+                let size = kind.bounding_radius() * 0.5;
+                for offset in [Vec3::ZERO, Vec3::new(-1.0, 0.3, 0.5), Vec3::new(1.0, -0.3, -0.5)] {
+                    let position = event.position + offset * size;
+                    for (preset, count, velocity) in [
+                        (ParticlePreset::Explosion, 80, Vec3::Y * 12.0),
+                        (ParticlePreset::Fire, 50, Vec3::Y * 6.0),
+                        (ParticlePreset::Smoke, 40, Vec3::Y * 3.0),
+                    ] {
+                        bursts.write(ParticleBurst {
+                            preset,
+                            position,
+                            velocity,
+                            count,
+                            color: None,
+                        });
+                    }
+                }
+            }
+            // Engines burn on afterwards, so they go with a gout of fire as well
+            DestroyedEnemy::BossPart(kind) => {
+                bursts.write(ParticleBurst {
+                    preset: ParticlePreset::Explosion,
+                    position: event.position,
+                    velocity: Vec3::ZERO,
+                    count: 30,
+                    color: None,
+                });
+                if *kind == BossPartKind::Engine {
+                    bursts.write(ParticleBurst {
+                        preset: ParticlePreset::Fire,
+                        position: event.position,
+                        velocity: Vec3::Y * 4.0,
+                        count: 20,
+                        color: None,
+                    });
+                }
+            }
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+use crate::powerups::ActivePowerUps;
+use crate::replay::PlayerInput;
+use crate::Aircraft;
+
+// Which controller flies the player's aircraft. Both read the same Aircraft component, so
+// speed and maneuverability upgrades apply to either.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FlightModel {
+    #[default]
+    Arcade,
+    Simulation,
+}
+
+impl FlightModel {
+    pub fn label(&self) -> &'static str {
+        match self {
+            FlightModel::Arcade => "Arcade",
+            FlightModel::Simulation => "Simulation",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            FlightModel::Arcade => "Point the nose and go - the aircraft never stalls",
+            FlightModel::Simulation => "Lift, drag and gravity - loops and rolls, but watch your airspeed",
+        }
+    }
+}
+
+const GRAVITY: f32 = 9.8;
+// Air density, wing area and mass folded into one factor: lift = LIFT_FACTOR * speed² * CL
+const LIFT_FACTOR: f32 = 0.016;
+// Lift coefficient gained per radian of angle of attack, up to the stall
+const LIFT_SLOPE: f32 = 5.0;
+const STALL_ANGLE: f32 = 0.28;
+const ZERO_LIFT_DRAG: f32 = 0.08;
+// Drag from making lift; this is what bleeds speed in hard turns
+const INDUCED_DRAG: f32 = 0.12;
+const PITCH_STABILITY: f32 = 2.0;
+const YAW_STABILITY: f32 = 3.0;
+// Full throttle tops out at this multiple of the aircraft's speed stat
+const TOP_SPEED_RATIO: f32 = 1.6;
+
+// Turns an attitude by `angles` (x pitch, y yaw, z roll, in radians) about the aircraft's own axes.
+// Composing quaternions keeps loops and rolls smooth in any attitude, and renormalizing stops
+// rounding error building up over a long flight.
+pub fn turn_in_body_frame(rotation: Quat, angles: Vec3) -> Quat {
+    (rotation * Quat::from_scaled_axis(angles)).normalize()
+}
+
+// Zero with the wings level, positive when rolled left, ±π when upside down
+pub fn bank_angle(rotation: Quat) -> f32 {
+    let right = rotation * Vec3::X;
+    let up = rotation * Vec3::Y;
+    right.y.atan2(up.y)
+}
+
+// The same heading with the nose on the horizon and the wings level
+pub fn level_attitude(rotation: Quat) -> Quat {
+    let forward = rotation * Vec3::NEG_Z;
+    let mut heading = Vec3::new(forward.x, 0.0, forward.z);
+    if heading.length_squared() < 1e-6 {
+        // Pointing straight up or down: take the shortest way to the horizon
+        heading = rotation * Vec3::Y * -forward.y.signum();
+        heading.y = 0.0;
+    }
+    Transform::IDENTITY.looking_to(heading, Vec3::Y).rotation
+}
+
+// Lift coefficient for an angle of attack in radians. Past the stall the wing abruptly loses
+// half its lift and keeps losing it as the angle grows.
+pub fn lift_coefficient(angle_of_attack: f32) -> f32 {
+    let angle = angle_of_attack.abs();
+    let coefficient = if angle <= STALL_ANGLE {
+        LIFT_SLOPE * angle
+    } else {
+        LIFT_SLOPE * STALL_ANGLE * 0.5 * angle.cos().max(0.0)
+    };
+    coefficient * angle_of_attack.signum()
+}
+
+pub fn simulation_flight_controls(
+    input: Res<PlayerInput>,
+    time: Res<Time>,
+    mut query: Query<(&mut Transform, &mut Aircraft)>,
+    active_powerups: Res<ActivePowerUps>,
+) {
+    let delta = time.delta_secs();
+
+    for (mut transform, mut aircraft) in query.iter_mut() {
+        let design_speed = aircraft.speed * active_powerups.speed_multiplier;
+        let agility = aircraft.roll_speed / 1.5;
+        let speed = aircraft.velocity.length();
+
+        // Control surfaces need airflow: sluggish when slow, twitchy when fast
+        let control_authority = (speed / design_speed).clamp(0.15, 1.5) * agility;
+
+        // Mouse flies the stick (up/down pitches, left/right rolls); A/D work the rudder
+        let mouse_delta = aircraft.mouse_delta;
+        transform.rotate_local_x(-mouse_delta.y.clamp(-200.0, 200.0) * 0.002 * control_authority);
+        transform.rotate_local_z(-mouse_delta.x.clamp(-200.0, 200.0) * 0.003 * control_authority);
+        aircraft.mouse_delta *= 0.2;
+        if input.roll_left {
+            transform.rotate_local_y(0.6 * control_authority * delta);
+        }
+        if input.roll_right {
+            transform.rotate_local_y(-0.6 * control_authority * delta);
+        }
+
+        // Angle of attack and sideslip from the airflow over the body
+        let local_velocity = transform.rotation.inverse() * aircraft.velocity;
+        let forward_airspeed = -local_velocity.z;
+        let angle_of_attack = (-local_velocity.y).atan2(forward_airspeed);
+        let sideslip = local_velocity.x.atan2(forward_airspeed);
+
+        // Weathervane stability turns the nose back into the airflow. Pitch trims to the angle that
+        // makes one g of lift, so hands-off flight holds its altitude at any speed.
+        let dynamic_pressure = LIFT_FACTOR * speed * speed;
+        let trim_angle = (GRAVITY / (dynamic_pressure * LIFT_SLOPE).max(0.01)).min(STALL_ANGLE * 0.8);
+        let stability = (speed / design_speed).clamp(0.0, 1.5) * delta;
+        transform.rotate_local_x(-(angle_of_attack - trim_angle) * PITCH_STABILITY * stability);
+        transform.rotate_local_y(-sideslip * YAW_STABILITY * stability);
+
+        let throttle = if input.boost {
+            3.5 // Afterburner
+        } else if input.throttle_up {
+            1.0
+        } else if input.throttle_down {
+            0.1
+        } else {
+            0.4
+        };
+        if input.boost {
+            aircraft.boost_timer = 0.05;
+        } else if aircraft.boost_timer > 0.0 {
+            aircraft.boost_timer -= delta;
+        }
+
+        // Thrust is sized so full throttle settles at TOP_SPEED_RATIO times the speed stat
+        let max_thrust = LIFT_FACTOR * ZERO_LIFT_DRAG * (TOP_SPEED_RATIO * design_speed).powi(2);
+        let mut acceleration = transform.forward() * max_thrust * throttle + Vec3::NEG_Y * GRAVITY;
+
+        if speed > 0.1 {
+            let airflow = aircraft.velocity / speed;
+            let lift_coefficient = lift_coefficient(angle_of_attack);
+            let lift_direction = transform.right().cross(airflow).normalize_or_zero();
+            let drag_coefficient = ZERO_LIFT_DRAG + INDUCED_DRAG * lift_coefficient * lift_coefficient;
+
+            acceleration += lift_direction * dynamic_pressure * lift_coefficient;
+            acceleration -= airflow * dynamic_pressure * drag_coefficient;
+        }
+
+        aircraft.velocity += acceleration * delta;
+        transform.translation += aircraft.velocity * delta;
+
+        aircraft.current_speed = aircraft.velocity.length();
+        // The camera follows the aircraft's own roll in this model
+        aircraft.current_roll = 0.0;
+    }
+}
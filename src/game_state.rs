@@ -10,6 +10,18 @@ pub enum GameState {
     UpgradeShop,
 }
 
+// Game time stops as soon as the pause is asked for rather than on entering Paused: the frame the
+// state changes on would otherwise still feed the fixed steps without being recorded, and a replay
+// of the run would step at different times from then on
+pub fn pause_run(next_state: &mut NextState<GameState>, virtual_time: &mut Time<Virtual>) {
+    virtual_time.pause();
+    next_state.set(GameState::Paused);
+}
+
+pub fn resume_game_time(mut virtual_time: ResMut<Time<Virtual>>) {
+    virtual_time.unpause();
+}
+
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
 pub enum GameMode {
     #[default]
@@ -44,6 +56,14 @@ pub struct GameStats {
     pub coins: u32,
     pub enemies_destroyed: u32,
     pub difficulty_level: f32,
+    // Coins paid out during the run on top of those for the score, e.g. for bosses
+    pub bonus_coins: u32,
+}
+
+impl GameStats {
+    pub fn coins_earned(&self) -> u32 {
+        self.score / 100 + self.bonus_coins
+    }
 }
 
 impl Default for GameStats {
@@ -58,6 +78,7 @@ impl Default for GameStats {
             coins: 0,
             enemies_destroyed: 0,
             difficulty_level: 1.0,
+            bonus_coins: 0,
         }
     }
 }
@@ -77,6 +98,83 @@ impl Default for ChallengeTimer {
     }
 }
 
+impl ChallengeTimer {
+    pub fn for_mode(mode: GameMode) -> Self {
+        let total_time = match mode {
+            GameMode::TimeAttack => 60.0,
+            GameMode::Survival => 10.0,
+            GameMode::RaceTheClock => 120.0,
+            _ => return Self::default(),
+        };
+        Self {
+            time_remaining: total_time,
+            total_time,
+        }
+    }
+}
+
+// All gameplay randomness comes from here so a seed reproduces a run. Each stream is forked from
+// the seed independently, so e.g. extra particle bursts never shift where the next enemy spawns.
+#[derive(Resource)]
+pub struct GameRng {
+    pub seed: u64,
+    pub world: fastrand::Rng,
+    pub spawns: fastrand::Rng,
+    pub ai: fastrand::Rng,
+    pub effects: fastrand::Rng,
+}
+
+impl GameRng {
+    pub fn from_seed(seed: u64) -> Self {
+        let mut root = fastrand::Rng::with_seed(seed);
+        Self {
+            seed,
+            world: root.fork(),
+            spawns: root.fork(),
+            ai: root.fork(),
+            effects: root.fork(),
+        }
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::from_seed(0)
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct SeedSettings {
+    // Seed entered on the main menu; None rolls a fresh one every run
+    pub requested: Option<u64>,
+    // One-shot seed for the next run only ("Replay Seed" on the game over screen)
+    pub replay: Option<u64>,
+}
+
+impl SeedSettings {
+    pub fn next_seed(&mut self) -> u64 {
+        self.replay
+            .take()
+            .or(self.requested)
+            .unwrap_or_else(|| fastrand::u32(..) as u64)
+    }
+}
+
+// Time since the current run started. Gameplay uses this instead of `Time::elapsed` so a run
+// behaves the same no matter how long the menus were open beforehand.
+#[derive(Resource, Default)]
+pub struct RunClock {
+    pub elapsed: f32,
+}
+
+// Countdowns that carry over between frames of a run; reset together with the seed
+#[derive(Resource, Default)]
+pub struct RunTimers {
+    pub enemy_spawn: f32,
+    pub powerup_spawn: f32,
+    pub combo: f32,
+}
+
 #[derive(Resource)]
 pub struct UpgradeData {
     pub speed_level: u32,
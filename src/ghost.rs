@@ -0,0 +1,299 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::game_state::{GameMode, CurrentGameMode, GameStats, GameRng, RunClock};
+use crate::{Aircraft, GameEntity};
+use crate::models::{AircraftModel, AircraftModels, Livery};
+use crate::save::write_atomically;
+
+// Bump this whenever the layout of GhostRun changes; ghosts from other versions are ignored
+pub const GHOST_VERSION: u32 = 1;
+
+// How often the player's aircraft is sampled, in seconds of run time
+const GHOST_SAMPLE_INTERVAL: f32 = 0.1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GhostSample {
+    pub time: f32,
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4],
+    pub score: u32,
+}
+
+// The path and score progression of one run, stored per game mode and seed
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GhostRun {
+    pub score: u32,
+    pub samples: Vec<GhostSample>,
+}
+
+// What a ghost file holds on disk. Files written before the header have no version and read as 0.
+#[derive(Serialize, Deserialize)]
+struct GhostFile {
+    #[serde(default)]
+    version: u32,
+    run: GhostRun,
+}
+
+impl GhostRun {
+    // Where the ghost was at `time`, or None once its run is over
+    pub fn pose_at(&self, time: f32) -> Option<Transform> {
+        let next = self.samples.partition_point(|sample| sample.time <= time);
+        let after = self.samples.get(next)?;
+        let Some(before) = next.checked_sub(1).map(|index| &self.samples[index]) else {
+            return Some(sample_transform(after));
+        };
+
+        let t = ((time - before.time) / (after.time - before.time)).clamp(0.0, 1.0);
+        let from = sample_transform(before);
+        let to = sample_transform(after);
+        Some(Transform {
+            translation: from.translation.lerp(to.translation, t),
+            rotation: from.rotation.slerp(to.rotation, t),
+            ..default()
+        })
+    }
+
+    pub fn score_at(&self, time: f32) -> u32 {
+        let next = self.samples.partition_point(|sample| sample.time <= time);
+        next.checked_sub(1)
+            .map(|index| self.samples[index].score)
+            .unwrap_or(0)
+    }
+
+    // When the ghost first had at least `score` points
+    pub fn time_to_reach(&self, score: u32) -> Option<f32> {
+        self.samples
+            .iter()
+            .find(|sample| sample.score >= score)
+            .map(|sample| sample.time)
+    }
+}
+
+fn sample_transform(sample: &GhostSample) -> Transform {
+    Transform {
+        translation: Vec3::from_array(sample.translation),
+        rotation: Quat::from_array(sample.rotation),
+        ..default()
+    }
+}
+
+pub fn supports_ghost(mode: GameMode) -> bool {
+    matches!(mode, GameMode::TimeAttack | GameMode::RaceTheClock)
+}
+
+// Positive `time_delta` means the player reached their current score later than the ghost did;
+// negative `points_delta` means the player has fewer points than the ghost had at this time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GhostComparison {
+    pub time_delta: Option<f32>,
+    pub points_delta: i64,
+}
+
+#[derive(Resource, Default)]
+pub struct GhostState {
+    // Personal best for this mode and seed, if there is one
+    pub best: Option<GhostRun>,
+    // The run in progress
+    pub recording: Option<GhostRun>,
+    pub next_sample_time: f32,
+    pub last_score: u32,
+    pub score_reached_at: f32,
+}
+
+impl GhostState {
+    pub fn compare(&self, score: u32, time: f32) -> Option<GhostComparison> {
+        let best = self.best.as_ref()?;
+        Some(GhostComparison {
+            time_delta: best
+                .time_to_reach(score)
+                .map(|ghost_time| self.score_reached_at - ghost_time),
+            points_delta: score as i64 - best.score_at(time) as i64,
+        })
+    }
+}
+
+#[derive(Component)]
+pub struct GhostAircraft;
+
+// Where personal-best ghosts are kept. `dir` is None when ghosts are disabled.
+#[derive(Resource)]
+pub struct GhostStore {
+    pub dir: Option<PathBuf>,
+}
+
+impl Default for GhostStore {
+    fn default() -> Self {
+        Self {
+            dir: dirs::data_dir().map(|dir| dir.join("sky-hunter").join("ghosts")),
+        }
+    }
+}
+
+impl GhostStore {
+    pub fn disabled() -> Self {
+        Self { dir: None }
+    }
+
+    pub fn path_for(&self, mode: GameMode, seed: u64) -> Option<PathBuf> {
+        let mode_name = match mode {
+            GameMode::FreePlay => "free-play",
+            GameMode::TimeAttack => "time-attack",
+            GameMode::TargetHunt => "target-hunt",
+            GameMode::Survival => "survival",
+            GameMode::RaceTheClock => "race-the-clock",
+        };
+        self.dir.as_ref().map(|dir| dir.join(format!("{mode_name}-{seed}.ron")))
+    }
+}
+
+pub fn load_ghost(path: &Path) -> Option<GhostRun> {
+    let contents = fs::read_to_string(path).ok()?;
+    match ron::from_str::<GhostFile>(&contents) {
+        Ok(file) if file.version == GHOST_VERSION => Some(file.run),
+        Ok(file) => {
+            warn!("Ignoring ghost {} with unsupported version {}", path.display(), file.version);
+            None
+        }
+        Err(err) => {
+            warn!("Ignoring unreadable ghost {}: {err}", path.display());
+            None
+        }
+    }
+}
+
+pub fn write_ghost(path: &Path, ghost: &GhostRun) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = GhostFile { version: GHOST_VERSION, run: ghost.clone() };
+    let contents = ron::to_string(&file).map_err(std::io::Error::other)?;
+    write_atomically(path, &contents)
+}
+
+// Runs after setup_game: loads the personal best for this mode and seed and spawns its aircraft
+pub fn start_ghost_run(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    models: Res<AircraftModels>,
+    mut ghost_state: ResMut<GhostState>,
+    store: Res<GhostStore>,
+    game_mode: Res<CurrentGameMode>,
+    rng: Res<GameRng>,
+) {
+    *ghost_state = GhostState::default();
+    if !supports_ghost(game_mode.mode) {
+        return;
+    }
+    ghost_state.recording = Some(GhostRun::default());
+
+    let Some(best) = store
+        .path_for(game_mode.mode, rng.seed)
+        .and_then(|path| load_ghost(&path))
+    else {
+        return;
+    };
+
+    let ghost_material = materials.add(StandardMaterial {
+        base_color: Color::srgba(0.6, 0.85, 1.0, 0.35),
+        emissive: LinearRgba::rgb(0.1, 0.2, 0.3),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        ..default()
+    });
+    let ghost_entity = commands.spawn((
+        best.pose_at(0.0).unwrap_or_default(),
+        Visibility::default(),
+        GhostAircraft,
+        GameEntity,
+    )).id();
+    let livery = Livery { body: ghost_material.clone(), trim: ghost_material };
+    models.spawn(&mut commands, ghost_entity, AircraftModel::Player, livery);
+
+    ghost_state.best = Some(best);
+}
+
+pub fn record_ghost_sample(
+    mut ghost_state: ResMut<GhostState>,
+    run_clock: Res<RunClock>,
+    game_stats: Res<GameStats>,
+    player_query: Query<&Transform, With<Aircraft>>,
+) {
+    if ghost_state.recording.is_none() {
+        return;
+    }
+
+    if game_stats.score != ghost_state.last_score {
+        ghost_state.last_score = game_stats.score;
+        ghost_state.score_reached_at = run_clock.elapsed;
+    }
+
+    if run_clock.elapsed < ghost_state.next_sample_time {
+        return;
+    }
+    let Ok(transform) = player_query.single() else {
+        return;
+    };
+
+    ghost_state.next_sample_time += GHOST_SAMPLE_INTERVAL;
+    if let Some(recording) = &mut ghost_state.recording {
+        recording.samples.push(GhostSample {
+            time: run_clock.elapsed,
+            translation: transform.translation.to_array(),
+            rotation: transform.rotation.to_array(),
+            score: game_stats.score,
+        });
+    }
+}
+
+pub fn update_ghost(
+    ghost_state: Res<GhostState>,
+    run_clock: Res<RunClock>,
+    fixed_time: Res<Time<Fixed>>,
+    mut ghosts: Query<(&mut Transform, &mut Visibility), With<GhostAircraft>>,
+) {
+    let Some(best) = &ghost_state.best else {
+        return;
+    };
+
+    // The player is drawn part way between their last two steps, so show the ghost at that moment too
+    let shown_time = run_clock.elapsed
+        - fixed_time.timestep().as_secs_f32() * (1.0 - fixed_time.overstep_fraction());
+
+    for (mut transform, mut visibility) in ghosts.iter_mut() {
+        match best.pose_at(shown_time) {
+            Some(pose) => {
+                *transform = pose;
+                *visibility = Visibility::Inherited;
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+}
+
+// Keeps the finished run as the new ghost if it beat the stored one
+pub fn finish_ghost_run(
+    mut ghost_state: ResMut<GhostState>,
+    store: Res<GhostStore>,
+    game_mode: Res<CurrentGameMode>,
+    game_stats: Res<GameStats>,
+    rng: Res<GameRng>,
+) {
+    let Some(mut recording) = ghost_state.recording.take() else {
+        return;
+    };
+    recording.score = game_stats.score;
+
+    if ghost_state.best.as_ref().is_some_and(|best| best.score >= recording.score) {
+        return;
+    }
+
+    if let Some(path) = store.path_for(game_mode.mode, rng.seed) {
+        match write_ghost(&path, &recording) {
+            Ok(()) => info!("New personal best ghost saved to {}", path.display()),
+            Err(err) => error!("Failed to write ghost {}: {err}", path.display()),
+        }
+    }
+    ghost_state.best = Some(recording);
+}
@@ -0,0 +1,503 @@
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+use crate::{Aircraft, FlightCamera, GameEntity};
+use crate::assets::{SharedAssets, Tint};
+use crate::enemies::{DestroyedEnemy, Enemy, EnemyDestroyedEvent, Health, lead_position};
+use crate::game_state::{GameRng, GameState, GameStats};
+use crate::missiles::proportional_navigation;
+use crate::models::GroundUnitModels;
+use crate::noise::{hash, hash01};
+use crate::particles::{ParticleBurst, ParticlePreset};
+use crate::physics::InterpolatedTransform;
+use crate::pool::{EntityPool, Overflow, PoolConfig, Poolable};
+use crate::powerups::ActivePowerUps;
+use crate::spatial::SpatialIndex;
+use crate::terrain::{SEA_LEVEL, TerrainHeightField};
+
+// At most one ground unit stands in each square cell of this size
+const GROUND_SITE_CELL_SIZE: f32 = 350.0;
+const GROUND_SITE_CHANCE: f32 = 0.3;
+const GROUND_SITE_LAYER: u32 = 0x0000_8000;
+// Nothing is placed this close to where runs start, so the player has time to get airborne
+const GROUND_SITE_CLEAR_RADIUS: f32 = 600.0;
+// Ground flatter than this (the normal's vertical part) is needed to build on
+const STEEPEST_GROUND_SITE: f32 = 0.8;
+// Units are put down when the player comes within the first distance and taken away again
+// past the second
+const GROUND_UNIT_SPAWN_RADIUS: f32 = 900.0;
+const GROUND_UNIT_DESPAWN_RADIUS: f32 = 1300.0;
+// Largest hit radius of any kind of unit, for spatial queries
+pub const GROUND_UNIT_HIT_RADIUS: f32 = 7.0;
+
+const FLAK_SPEED: f32 = 160.0;
+// Shells go off this close to the player, or when their timed fuse runs out near where they
+// were aimed; either way everything within the burst radius is hurt, less at the edge
+const FLAK_PROXIMITY: f32 = 10.0;
+const FLAK_BURST_RADIUS: f32 = 18.0;
+const FLAK_DAMAGE: f32 = 12.0;
+// Largest error in each axis of the aim direction, and in the fuse time as a share of it
+const FLAK_SPREAD: f32 = 0.06;
+const FLAK_FUSE_ERROR: f32 = 0.15;
+
+// Launch rails point at the player, raised this far above the horizon
+pub const SAM_LAUNCH_ELEVATION: f32 = 0.7;
+const SAM_LAUNCH_SPEED: f32 = 40.0;
+const SAM_MAX_SPEED: f32 = 130.0;
+const SAM_THRUST: f32 = 90.0;
+const SAM_TURN_RATE: f32 = 2.5;
+// Proportional navigation only works once a missile is roughly on course; until its heading is
+// within this angle of the player it turns straight at them
+const SAM_PURSUIT_ANGLE: f32 = 0.6;
+const SAM_FUEL: f32 = 5.0;
+const SAM_LIFETIME: f32 = 9.0;
+const SAM_FUSE_RADIUS: f32 = 6.0;
+const SAM_DAMAGE: f32 = 35.0;
+
+// Radar stations see the player this far out, unless they fly lower than the second distance
+// over the ground; patrols this close to the station are sent after them
+const RADAR_DETECTION_RANGE: f32 = 700.0;
+const RADAR_MIN_ALTITUDE: f32 = 25.0;
+const RADAR_ALERT_RADIUS: f32 = 900.0;
+// How long an alerted enemy keeps chasing after the station loses the player
+const RADAR_ALERT_TIME: f32 = 8.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroundUnitKind {
+    AaGun,
+    SamSite,
+    RadarStation,
+}
+
+impl GroundUnitKind {
+    pub const ALL: [GroundUnitKind; 3] = [GroundUnitKind::AaGun, GroundUnitKind::SamSite, GroundUnitKind::RadarStation];
+
+    pub fn health(&self) -> f32 {
+        match self {
+            GroundUnitKind::AaGun => 80.0,
+            GroundUnitKind::SamSite => 120.0,
+            GroundUnitKind::RadarStation => 150.0,
+        }
+    }
+
+    // Points for destroying it
+    pub fn score(&self) -> u32 {
+        match self {
+            GroundUnitKind::AaGun => 150,
+            GroundUnitKind::SamSite => 250,
+            GroundUnitKind::RadarStation => 200,
+        }
+    }
+
+    // How far it reaches: its guns, launcher or radar
+    pub fn range(&self) -> f32 {
+        match self {
+            GroundUnitKind::AaGun => 450.0,
+            GroundUnitKind::SamSite => 700.0,
+            GroundUnitKind::RadarStation => RADAR_DETECTION_RANGE,
+        }
+    }
+
+    // Seconds between shots or launches
+    pub fn cooldown(&self) -> f32 {
+        match self {
+            GroundUnitKind::AaGun => 1.0,
+            GroundUnitKind::SamSite => 8.0,
+            GroundUnitKind::RadarStation => 0.0,
+        }
+    }
+
+    // The sphere hits are checked against: its centre's height above the unit's base, and its radius
+    pub fn hit_sphere(&self) -> (f32, f32) {
+        match self {
+            GroundUnitKind::AaGun => (2.0, 4.5),
+            GroundUnitKind::SamSite => (2.0, 5.0),
+            GroundUnitKind::RadarStation => (4.0, GROUND_UNIT_HIT_RADIUS),
+        }
+    }
+
+    pub fn hud_color(&self) -> Color {
+        match self {
+            GroundUnitKind::AaGun => Color::srgb(1.0, 0.6, 0.2),
+            GroundUnitKind::SamSite => Color::srgb(1.0, 0.25, 0.25),
+            GroundUnitKind::RadarStation => Color::srgb(1.0, 0.9, 0.3),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct GroundUnit {
+    pub kind: GroundUnitKind,
+    pub cooldown: f32,
+    // Radar stations: whether the player is being tracked right now
+    pub tracking: bool,
+}
+
+impl GroundUnit {
+    pub fn hit_center(&self, transform: &Transform) -> Vec3 {
+        transform.translation + Vec3::Y * self.kind.hit_sphere().0
+    }
+}
+
+#[derive(Component)]
+pub struct FlakShell {
+    pub velocity: Vec3,
+    pub fuse: f32,
+}
+
+impl Poolable for FlakShell {
+    const POOL: PoolConfig = PoolConfig { size: 32, overflow: Overflow::Grow };
+}
+
+// A surface-to-air missile, guided at the player
+#[derive(Component)]
+pub struct SamMissile {
+    pub velocity: Vec3,
+    pub fuel: f32,
+    pub lifetime: f32,
+}
+
+impl Poolable for SamMissile {
+    const POOL: PoolConfig = PoolConfig { size: 8, overflow: Overflow::Grow };
+}
+
+// The unit standing in a GROUND_SITE_CELL_SIZE cell, if it has one, and where its base is
+pub fn ground_site_in_cell(height_field: &TerrainHeightField, cell: IVec2) -> Option<(GroundUnitKind, Vec3)> {
+    let seed = height_field.seed ^ GROUND_SITE_LAYER;
+    if hash01(seed, cell.x, cell.y) >= GROUND_SITE_CHANCE {
+        return None;
+    }
+    let x = (cell.x as f32 + 0.2 + hash01(seed.wrapping_add(1), cell.x, cell.y) * 0.6) * GROUND_SITE_CELL_SIZE;
+    let z = (cell.y as f32 + 0.2 + hash01(seed.wrapping_add(2), cell.x, cell.y) * 0.6) * GROUND_SITE_CELL_SIZE;
+    let base = Vec3::new(x, height_field.height_at(x, z), z);
+    let on_water = base.y <= SEA_LEVEL;
+    let too_steep = height_field.normal_at(x, z).y < STEEPEST_GROUND_SITE;
+    if on_water || too_steep || Vec2::new(x, z).length() < GROUND_SITE_CLEAR_RADIUS {
+        return None;
+    }
+    let kind = match hash(seed.wrapping_add(3), cell.x, cell.y) % 10 {
+        0..=4 => GroundUnitKind::AaGun,
+        5..=7 => GroundUnitKind::SamSite,
+        _ => GroundUnitKind::RadarStation,
+    };
+    Some((kind, base))
+}
+
+// Which sites have a unit standing on them right now, and which have been destroyed this run
+// and stay empty
+#[derive(Resource, Default)]
+pub struct GroundSites {
+    pub active: HashMap<IVec2, Entity>,
+    pub destroyed: HashSet<IVec2>,
+}
+
+pub fn reset_ground_sites(mut sites: ResMut<GroundSites>) {
+    *sites = GroundSites::default();
+}
+
+// Puts units down on the sites around the player and takes away ones left far behind. Sites
+// come from the terrain's seed, so the same run always meets the same defences.
+pub fn spawn_ground_units(
+    mut commands: Commands,
+    mut sites: ResMut<GroundSites>,
+    models: Res<GroundUnitModels>,
+    height_field: Res<TerrainHeightField>,
+    player_query: Query<&Transform, With<Aircraft>>,
+    unit_query: Query<&GroundUnit>,
+) {
+    let Ok(player_transform) = player_query.single() else {
+        return;
+    };
+    let player = Vec2::new(player_transform.translation.x, player_transform.translation.z);
+
+    // A unit missing from the world was destroyed
+    let sites = &mut *sites;
+    sites.active.retain(|cell, entity| {
+        if !unit_query.contains(*entity) {
+            sites.destroyed.insert(*cell);
+            return false;
+        }
+        let center = (cell.as_vec2() + 0.5) * GROUND_SITE_CELL_SIZE;
+        if center.distance(player) > GROUND_UNIT_DESPAWN_RADIUS {
+            commands.entity(*entity).despawn();
+            return false;
+        }
+        true
+    });
+
+    let min = ((player - GROUND_UNIT_SPAWN_RADIUS) / GROUND_SITE_CELL_SIZE).floor().as_ivec2();
+    let max = ((player + GROUND_UNIT_SPAWN_RADIUS) / GROUND_SITE_CELL_SIZE).floor().as_ivec2();
+    for x in min.x..=max.x {
+        for z in min.y..=max.y {
+            let cell = IVec2::new(x, z);
+            if sites.active.contains_key(&cell) || sites.destroyed.contains(&cell) {
+                continue;
+            }
+            let Some((kind, base)) = ground_site_in_cell(&height_field, cell) else {
+                continue;
+            };
+            if Vec2::new(base.x, base.z).distance(player) > GROUND_UNIT_SPAWN_RADIUS {
+                continue;
+            }
+            let unit = commands.spawn((
+                Transform::from_translation(base),
+                Visibility::default(),
+                GroundUnit {
+                    kind,
+                    // Staggered so a group of guns doesn't open fire all at once
+                    cooldown: kind.cooldown() * hash01(height_field.seed, x, z),
+                    tracking: false,
+                },
+                Health {
+                    current: kind.health(),
+                    max: kind.health(),
+                },
+                GameEntity,
+            )).id();
+            models.spawn(&mut commands, unit, kind);
+            sites.active.insert(cell, unit);
+        }
+    }
+}
+
+// Guns and launchers turn towards the player and fire when they're in range; radar stations
+// send nearby patrols after a player they can see
+pub fn ground_units_system(
+    mut commands: Commands,
+    shared_assets: Res<SharedAssets>,
+    mut flak_pool: ResMut<EntityPool<FlakShell>>,
+    mut sam_pool: ResMut<EntityPool<SamMissile>>,
+    mut unit_query: Query<(&mut Transform, &mut GroundUnit)>,
+    player_query: Query<(&Transform, &InterpolatedTransform), (With<Aircraft>, Without<GroundUnit>)>,
+    mut enemy_query: Query<(&Transform, &mut Enemy), Without<GroundUnit>>,
+    mut bursts: EventWriter<ParticleBurst>,
+    height_field: Res<TerrainHeightField>,
+    spatial_index: Res<SpatialIndex>,
+    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+) {
+    let Ok((player_transform, player_interpolated)) = player_query.single() else {
+        return;
+    };
+    let delta = time.delta_secs();
+    let player = player_transform.translation;
+    let player_velocity = (player_interpolated.current.translation - player_interpolated.previous.translation) / delta;
+    let player_altitude = player.y - height_field.height_at(player.x, player.z);
+
+    for (mut transform, mut unit) in unit_query.iter_mut() {
+        unit.cooldown -= delta;
+        let muzzle = unit.hit_center(&transform);
+        let in_range = muzzle.distance(player) < unit.kind.range();
+
+        match unit.kind {
+            GroundUnitKind::RadarStation => {
+                unit.tracking = in_range && player_altitude > RADAR_MIN_ALTITUDE;
+                if !unit.tracking {
+                    continue;
+                }
+                for enemy_entity in spatial_index.enemies.query_sphere(transform.translation, RADAR_ALERT_RADIUS) {
+                    let Ok((enemy_transform, mut enemy)) = enemy_query.get_mut(enemy_entity) else {
+                        continue;
+                    };
+                    if enemy_transform.translation.distance(transform.translation) < RADAR_ALERT_RADIUS {
+                        enemy.alert_timer = RADAR_ALERT_TIME;
+                    }
+                }
+            }
+            GroundUnitKind::AaGun | GroundUnitKind::SamSite => {
+                if !in_range {
+                    continue;
+                }
+                let facing = (player - transform.translation).with_y(0.0);
+                if let Ok(direction) = Dir3::new(facing) {
+                    transform.look_to(direction, Vec3::Y);
+                }
+                if unit.cooldown > 0.0 {
+                    continue;
+                }
+                unit.cooldown = unit.kind.cooldown();
+
+                if unit.kind == GroundUnitKind::AaGun {
+                    let aim_point = lead_position(muzzle, player, player_velocity, FLAK_SPEED, 1.0);
+                    let spread = Vec3::new(rng.ai.f32() - 0.5, rng.ai.f32() - 0.5, rng.ai.f32() - 0.5) * FLAK_SPREAD;
+                    let direction = ((aim_point - muzzle).normalize_or_zero() + spread).normalize_or_zero();
+                    let fuse_error = 1.0 + (rng.ai.f32() - 0.5) * 2.0 * FLAK_FUSE_ERROR;
+                    let shell_transform = Transform::from_translation(muzzle).with_scale(Vec3::splat(0.4));
+                    flak_pool.spawn(&mut commands, (
+                        Mesh3d(shared_assets.sphere.clone()),
+                        MeshMaterial3d(shared_assets.glow(Tint::Enemy)),
+                        shell_transform,
+                        InterpolatedTransform::new(shell_transform),
+                        FlakShell {
+                            velocity: direction * FLAK_SPEED,
+                            fuse: muzzle.distance(aim_point) / FLAK_SPEED * fuse_error,
+                        },
+                    ));
+                    bursts.write(ParticleBurst {
+                        preset: ParticlePreset::MuzzleFlash,
+                        position: muzzle,
+                        velocity: direction * 20.0,
+                        count: 4,
+                        color: None,
+                    });
+                } else {
+                    let direction = (transform.forward() * SAM_LAUNCH_ELEVATION.cos() + Vec3::Y * SAM_LAUNCH_ELEVATION.sin()).normalize();
+                    let missile_transform = Transform::from_translation(muzzle + direction * 3.0)
+                        .looking_to(direction, Vec3::Y)
+                        .with_scale(Vec3::new(0.3, 0.3, 1.5));
+                    sam_pool.spawn(&mut commands, (
+                        Mesh3d(shared_assets.sphere.clone()),
+                        MeshMaterial3d(shared_assets.glow(Tint::Missile)),
+                        missile_transform,
+                        InterpolatedTransform::new(missile_transform),
+                        SamMissile {
+                            velocity: direction * SAM_LAUNCH_SPEED,
+                            fuel: SAM_FUEL,
+                            lifetime: SAM_LIFETIME,
+                        },
+                    ));
+                }
+            }
+        }
+    }
+}
+
+// Flak shells burst near the player or when their fuse runs out; SAMs steer at the player by
+// proportional navigation and go off when they get close. Either can be outflown, and neither
+// survives hitting the ground.
+pub fn update_ground_fire(
+    mut commands: Commands,
+    mut flak_query: Query<(Entity, &mut Transform, &mut FlakShell), Without<Aircraft>>,
+    mut sam_query: Query<(Entity, &mut Transform, &mut SamMissile), (Without<Aircraft>, Without<FlakShell>)>,
+    mut player_query: Query<(&Transform, &InterpolatedTransform, &mut Health), With<Aircraft>>,
+    mut flak_pool: ResMut<EntityPool<FlakShell>>,
+    mut sam_pool: ResMut<EntityPool<SamMissile>>,
+    mut bursts: EventWriter<ParticleBurst>,
+    mut camera_query: Query<&mut FlightCamera>,
+    mut game_state: ResMut<NextState<GameState>>,
+    active_powerups: Res<ActivePowerUps>,
+    height_field: Res<TerrainHeightField>,
+    time: Res<Time>,
+) {
+    let Ok((player_transform, player_interpolated, mut player_health)) = player_query.single_mut() else {
+        return;
+    };
+    let delta = time.delta_secs();
+    let player = player_transform.translation;
+    let player_velocity = (player_interpolated.current.translation - player_interpolated.previous.translation) / delta;
+    let mut damage_taken = 0.0;
+
+    for (entity, mut transform, mut shell) in flak_query.iter_mut() {
+        shell.fuse -= delta;
+        transform.translation += shell.velocity * delta;
+        let position = transform.translation;
+        if position.y <= height_field.height_at(position.x, position.z) {
+            flak_pool.release(&mut commands, entity);
+            continue;
+        }
+        let distance = position.distance(player);
+        if shell.fuse > 0.0 && distance > FLAK_PROXIMITY {
+            continue;
+        }
+        if distance < FLAK_BURST_RADIUS {
+            damage_taken += FLAK_DAMAGE * (1.0 - distance / FLAK_BURST_RADIUS);
+        }
+        bursts.write(ParticleBurst {
+            preset: ParticlePreset::Smoke,
+            position,
+            velocity: Vec3::ZERO,
+            count: 10,
+            color: Some(Color::srgba(0.08, 0.08, 0.08, 0.8)),
+        });
+        bursts.write(ParticleBurst {
+            preset: ParticlePreset::Explosion,
+            position,
+            velocity: Vec3::ZERO,
+            count: 6,
+            color: None,
+        });
+        flak_pool.release(&mut commands, entity);
+    }
+
+    for (entity, mut transform, mut missile) in sam_query.iter_mut() {
+        missile.lifetime -= delta;
+        if missile.fuel > 0.0 {
+            missile.fuel -= delta;
+            let speed = (missile.velocity.length() + SAM_THRUST * delta).min(SAM_MAX_SPEED);
+            let to_player = player - transform.translation;
+            let acceleration = if missile.velocity.angle_between(to_player) > SAM_PURSUIT_ANGLE {
+                let turn = (to_player.normalize_or_zero() - missile.velocity.normalize_or_zero()).normalize_or_zero();
+                turn * SAM_TURN_RATE * missile.velocity.length()
+            } else {
+                proportional_navigation(transform.translation, missile.velocity, player, player_velocity, SAM_TURN_RATE)
+            };
+            missile.velocity = (missile.velocity + acceleration * delta).normalize_or_zero() * speed;
+        } else {
+            missile.velocity *= 1.0 - 0.3 * delta;
+            missile.velocity.y -= 9.8 * delta;
+        }
+        transform.translation += missile.velocity * delta;
+        if let Ok(direction) = Dir3::new(missile.velocity) {
+            transform.look_to(direction, Vec3::Y);
+        }
+
+        let position = transform.translation;
+        let hit_player = position.distance(player) < SAM_FUSE_RADIUS;
+        let hit_ground = position.y <= height_field.height_at(position.x, position.z);
+        if !hit_player && !hit_ground && missile.lifetime > 0.0 {
+            continue;
+        }
+        if hit_player {
+            damage_taken += SAM_DAMAGE;
+        }
+        bursts.write(ParticleBurst {
+            preset: ParticlePreset::Explosion,
+            position,
+            velocity: Vec3::ZERO,
+            count: 25,
+            color: None,
+        });
+        sam_pool.release(&mut commands, entity);
+    }
+
+    if damage_taken > 0.0 {
+        if active_powerups.shield {
+            damage_taken *= 0.2; // Shield absorbs 80% of the blast
+        }
+        player_health.current = (player_health.current - damage_taken).max(0.0);
+        if let Ok(mut camera) = camera_query.single_mut() {
+            camera.shake_amount = (damage_taken * 0.15).clamp(1.0, 6.0);
+            camera.shake_timer = 0.3;
+        }
+        if player_health.current <= 0.0 {
+            game_state.set(GameState::GameOver);
+        }
+    }
+}
+
+// Takes `damage` off a ground unit. One it destroys is scored and removed, and the event to send
+// for it is returned. Units already destroyed earlier in the step take no more hits.
+pub fn damage_ground_unit(
+    commands: &mut Commands,
+    game_stats: &mut GameStats,
+    unit_entity: Entity,
+    transform: &Transform,
+    health: &mut Health,
+    unit: &GroundUnit,
+    damage: f32,
+) -> Option<EnemyDestroyedEvent> {
+    if health.current <= 0.0 {
+        return None;
+    }
+    health.current -= damage;
+    if health.current > 0.0 {
+        return None;
+    }
+
+    game_stats.score += unit.kind.score();
+    game_stats.enemies_destroyed += 1;
+    commands.entity(unit_entity).despawn();
+    Some(EnemyDestroyedEvent {
+        position: unit.hit_center(transform),
+        enemy: DestroyedEnemy::Ground(unit.kind),
+    })
+}
@@ -0,0 +1,257 @@
+use bevy::prelude::*;
+use bevy::input::InputPlugin;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use crate::archetypes::{ArchetypeStatus, EnemyArchetypes};
+use crate::game_state::{GameState, GameMode, CurrentGameMode, GameStats, ChallengeTimer, GameRng, SeedSettings, RunClock};
+use crate::replay::{ReplayRecorder, ReplayStore, load_replay, start_playback, write_replay};
+use crate::save::SaveFile;
+use crate::ghost::GhostStore;
+use crate::flight::FlightModel;
+use crate::SkyHunterPlugin;
+
+// Loading the asset files only takes a moment, unless something is wrong
+const ARCHETYPE_LOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct HeadlessOptions {
+    pub mode: GameMode,
+    pub flight_model: FlightModel,
+    pub seconds: f32,
+    pub tick_rate: f32,
+    pub seed: Option<u64>,
+    pub replay: Option<PathBuf>,
+    pub record: Option<PathBuf>,
+}
+
+impl Default for HeadlessOptions {
+    fn default() -> Self {
+        Self {
+            mode: GameMode::Survival,
+            flight_model: FlightModel::Arcade,
+            seconds: 60.0,
+            tick_rate: 60.0,
+            seed: None,
+            replay: None,
+            record: None,
+        }
+    }
+}
+
+impl HeadlessOptions {
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut args = args.iter().skip(1);
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => {}
+                "--mode" => {
+                    let value = args.next().ok_or("--mode needs a value")?;
+                    options.mode = parse_game_mode(value)
+                        .ok_or_else(|| format!("unknown game mode '{value}'"))?;
+                }
+                "--flight-model" => {
+                    let value = args.next().ok_or("--flight-model needs a value")?;
+                    options.flight_model = parse_flight_model(value)
+                        .ok_or_else(|| format!("unknown flight model '{value}'"))?;
+                }
+                "--seconds" => {
+                    let value = args.next().ok_or("--seconds needs a value")?;
+                    options.seconds = value.parse()
+                        .map_err(|_| format!("invalid --seconds value '{value}'"))?;
+                }
+                "--tick-rate" => {
+                    let value = args.next().ok_or("--tick-rate needs a value")?;
+                    options.tick_rate = value.parse()
+                        .map_err(|_| format!("invalid --tick-rate value '{value}'"))?;
+                }
+                "--seed" => {
+                    let value = args.next().ok_or("--seed needs a value")?;
+                    options.seed = Some(value.parse()
+                        .map_err(|_| format!("invalid --seed value '{value}'"))?);
+                }
+                "--replay" => {
+                    let value = args.next().ok_or("--replay needs a file")?;
+                    options.replay = Some(PathBuf::from(value));
+                }
+                "--record" => {
+                    let value = args.next().ok_or("--record needs a file")?;
+                    options.record = Some(PathBuf::from(value));
+                }
+                other => return Err(format!("unknown argument '{other}'")),
+            }
+        }
+
+        if options.tick_rate <= 0.0 {
+            return Err("--tick-rate must be positive".to_string());
+        }
+
+        Ok(options)
+    }
+}
+
+fn parse_game_mode(value: &str) -> Option<GameMode> {
+    match value.to_ascii_lowercase().as_str() {
+        "free-play" | "freeplay" => Some(GameMode::FreePlay),
+        "time-attack" | "timeattack" => Some(GameMode::TimeAttack),
+        "target-hunt" | "targethunt" => Some(GameMode::TargetHunt),
+        "survival" => Some(GameMode::Survival),
+        "race-the-clock" | "racetheclock" => Some(GameMode::RaceTheClock),
+        _ => None,
+    }
+}
+
+fn parse_flight_model(value: &str) -> Option<FlightModel> {
+    match value.to_ascii_lowercase().as_str() {
+        "arcade" => Some(FlightModel::Arcade),
+        "simulation" | "sim" => Some(FlightModel::Simulation),
+        _ => None,
+    }
+}
+
+// Builds an app that runs the full simulation without a window, renderer or egui.
+// Time only advances by `tick_rate` per update, so runs don't depend on the host's speed.
+pub fn headless_app(tick_rate: f32) -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        // Edits to asset files mid-run would make runs differ
+        AssetPlugin {
+            watch_for_changes_override: Some(false),
+            ..default()
+        },
+        TransformPlugin,
+        InputPlugin,
+        StatesPlugin,
+    ))
+    .init_asset::<Mesh>()
+    .init_asset::<StandardMaterial>()
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(1.0 / tick_rate)))
+    // Never touch the player's real save file, replays or ghosts from a headless run
+    .insert_resource(SaveFile::disabled())
+    .insert_resource(ReplayStore::disabled())
+    .insert_resource(GhostStore::disabled())
+    .add_plugins(SkyHunterPlugin);
+    
+    // The very first update always has a zero frame time; get it out of the way here so a replay
+    // recorded in the windowed game gets its real first frame time when played back
+    app.update();
+    wait_for_enemy_archetypes(&mut app);
+    app
+}
+
+// Asset files load in the background; a run can't start without its enemies
+fn wait_for_enemy_archetypes(app: &mut App) {
+    let started = Instant::now();
+    loop {
+        match &app.world().resource::<EnemyArchetypes>().status {
+            ArchetypeStatus::Ready => return,
+            ArchetypeStatus::Failed(err) => panic!("couldn't load enemy archetypes: {err}"),
+            ArchetypeStatus::Loading if started.elapsed() > ARCHETYPE_LOAD_TIMEOUT => {
+                panic!("enemy archetypes took more than {ARCHETYPE_LOAD_TIMEOUT:?} to load")
+            }
+            ArchetypeStatus::Loading => {
+                std::thread::sleep(Duration::from_millis(1));
+                app.update();
+            }
+        }
+    }
+}
+
+pub fn start_run(app: &mut App, mode: GameMode, seed: Option<u64>) {
+    let world = app.world_mut();
+    world.insert_resource(CurrentGameMode { mode });
+    world.insert_resource(SeedSettings { requested: seed, replay: None });
+    world.insert_resource(ChallengeTimer::for_mode(mode));
+    world.resource_mut::<NextState<GameState>>().set(GameState::Playing);
+}
+
+// Steps the app until `seconds` of game time have passed or the run ends, returning the frames simulated
+pub fn simulate(app: &mut App, seconds: f32, tick_rate: f32) -> u32 {
+    let total_ticks = (seconds * tick_rate).ceil() as u32;
+
+    // The first update applies the transition into Playing and simulates the first frame
+    for tick in 0..total_ticks {
+        app.update();
+
+        if *app.world().resource::<State<GameState>>().get() != GameState::Playing {
+            // This update only applied the transition out of Playing
+            return tick;
+        }
+    }
+
+    total_ticks
+}
+
+// Steps the app through a replay started with `start_playback`, returning the frames played
+pub fn simulate_replay(app: &mut App) -> u32 {
+    let mut ticks = 0;
+    loop {
+        app.update();
+        if *app.world().resource::<State<GameState>>().get() != GameState::Playing {
+            return ticks;
+        }
+        ticks += 1;
+    }
+}
+
+pub fn run(args: &[String]) {
+    let options = match HeadlessOptions::parse(args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("sky-hunter: {err}");
+            eprintln!("usage: sky-hunter --headless [--mode <mode>] [--flight-model <arcade|simulation>] [--seconds <n>] [--tick-rate <hz>] [--seed <n>] [--record <file>]");
+            eprintln!("       sky-hunter --headless --replay <file>");
+            std::process::exit(2);
+        }
+    };
+
+    let mut app = headless_app(options.tick_rate);
+    let ticks = if let Some(path) = &options.replay {
+        let replay = match load_replay(path) {
+            Ok(replay) => replay,
+            Err(err) => {
+                eprintln!("sky-hunter: could not load replay {}: {err}", path.display());
+                std::process::exit(1);
+            }
+        };
+        if let Err(err) = start_playback(app.world_mut(), replay) {
+            eprintln!("sky-hunter: could not play replay {}: {err}", path.display());
+            std::process::exit(1);
+        }
+        simulate_replay(&mut app)
+    } else {
+        app.insert_resource(options.flight_model);
+        start_run(&mut app, options.mode, options.seed);
+        simulate(&mut app, options.seconds, options.tick_rate)
+    };
+    
+    if let Some(path) = &options.record {
+        let recorder = app.world().resource::<ReplayRecorder>();
+        if let Some(replay) = recorder.current.as_ref().or(recorder.last.as_ref()) {
+            if let Err(err) = write_replay(path, replay) {
+                eprintln!("sky-hunter: could not write replay {}: {err}", path.display());
+            }
+        }
+    }
+
+    let ended_by = match app.world().resource::<State<GameState>>().get() {
+        GameState::GameOver => "game over",
+        _ => "time limit",
+    };
+
+    let stats = app.world().resource::<GameStats>();
+    println!("mode: {:?}", app.world().resource::<CurrentGameMode>().mode);
+    println!("flight_model: {:?}", app.world().resource::<FlightModel>());
+    println!("seed: {}", app.world().resource::<GameRng>().seed);
+    println!("ticks: {ticks} ({:.1}s simulated, ended by {ended_by})", app.world().resource::<RunClock>().elapsed);
+    println!("score: {}", stats.score);
+    println!("targets_hit: {}", stats.targets_hit);
+    println!("enemies_destroyed: {}", stats.enemies_destroyed);
+    println!("max_combo: {}", stats.max_combo);
+    println!("time_played: {:.2}", stats.time_played);
+    println!("difficulty_level: {:.3}", stats.difficulty_level);
+    println!("coins: {}", stats.coins);
+}
@@ -0,0 +1,127 @@
+use bevy::prelude::*;
+use crate::terrain::{TerrainStreamer, TERRAIN_LOD_RESOLUTIONS};
+use crate::enemies::{EnemyBullet, PlayerBullet};
+use crate::pool::EntityPool;
+use crate::particles::ParticleEmitter;
+use crate::FlightCamera;
+
+// What the debug overlay flags as too much to draw
+pub const ENTITY_BUDGET: usize = 5_000;
+pub const TRIANGLE_BUDGET: usize = 400_000;
+
+// Clouds reuse this many shapes, and switch to their coarse mesh past this distance
+pub const CLOUD_SHAPES: u32 = 6;
+pub const CLOUD_LOD_DISTANCE: f32 = 600.0;
+
+// Swaps an entity's mesh for a cheaper one once it's far from the camera
+#[derive(Component)]
+pub struct DistanceLod {
+    pub near: Handle<Mesh>,
+    pub far: Handle<Mesh>,
+    pub switch_distance: f32,
+    pub is_far: bool,
+}
+
+impl DistanceLod {
+    pub fn new(near: Handle<Mesh>, far: Handle<Mesh>, switch_distance: f32) -> Self {
+        Self {
+            near,
+            far,
+            switch_distance,
+            is_far: false,
+        }
+    }
+}
+
+pub fn update_distance_lod(
+    camera_query: Query<&GlobalTransform, With<FlightCamera>>,
+    mut query: Query<(&GlobalTransform, &mut DistanceLod, &mut Mesh3d)>,
+) {
+    let Ok(camera) = camera_query.single() else {
+        return;
+    };
+    let camera_position = camera.translation();
+
+    for (transform, mut lod, mut mesh) in query.iter_mut() {
+        let distance_squared = transform.translation().distance_squared(camera_position);
+        // A little hysteresis so anything sitting on the boundary doesn't flicker between meshes
+        let threshold = lod.switch_distance * if lod.is_far { 0.9 } else { 1.1 };
+        let is_far = distance_squared > threshold * threshold;
+        if is_far != lod.is_far {
+            lod.is_far = is_far;
+            mesh.0 = if is_far { lod.far.clone() } else { lod.near.clone() };
+        }
+    }
+}
+
+pub fn triangle_count(mesh: &Mesh) -> usize {
+    match mesh.indices() {
+        Some(indices) => indices.len() / 3,
+        None => mesh.count_vertices() / 3,
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SceneStats {
+    pub entities: usize,
+    // Every mesh that isn't hidden, before frustum culling; an upper bound on what gets drawn
+    pub meshes: usize,
+    pub triangles: usize,
+    // Loaded terrain chunks at each level of detail, finest first
+    pub terrain_chunks: [usize; TERRAIN_LOD_RESOLUTIONS.len()],
+    pub near_models: usize,
+    pub far_models: usize,
+    // Bullets out in the world, and hidden in their pools for reuse
+    pub pooled_active: usize,
+    pub pooled_free: usize,
+    pub particles: usize,
+}
+
+// F3 shows what the scene costs to draw; the stats are only gathered while it's open
+#[derive(Resource, Default)]
+pub struct DebugOverlay {
+    pub visible: bool,
+    pub stats: SceneStats,
+}
+
+pub fn gather_scene_stats(
+    mut overlay: ResMut<DebugOverlay>,
+    entities: Query<Entity>,
+    mesh_query: Query<(&Mesh3d, Option<&Visibility>)>,
+    lods: Query<&DistanceLod>,
+    meshes: Res<Assets<Mesh>>,
+    streamer: Res<TerrainStreamer>,
+    player_bullets: Res<EntityPool<PlayerBullet>>,
+    enemy_bullets: Res<EntityPool<EnemyBullet>>,
+    emitters: Query<&ParticleEmitter>,
+) {
+    if !overlay.visible {
+        return;
+    }
+
+    let mut stats = SceneStats {
+        entities: entities.iter().count(),
+        pooled_active: player_bullets.active() + enemy_bullets.active(),
+        pooled_free: player_bullets.free() + enemy_bullets.free(),
+        particles: emitters.iter().map(ParticleEmitter::len).sum(),
+        ..default()
+    };
+    for (mesh, visibility) in mesh_query.iter() {
+        if visibility == Some(&Visibility::Hidden) {
+            continue;
+        }
+        stats.meshes += 1;
+        stats.triangles += meshes.get(&mesh.0).map(triangle_count).unwrap_or(0);
+    }
+    for lod in streamer.loaded_lods() {
+        stats.terrain_chunks[lod] += 1;
+    }
+    for lod in lods.iter() {
+        if lod.is_far {
+            stats.far_models += 1;
+        } else {
+            stats.near_models += 1;
+        }
+    }
+    overlay.stats = stats;
+}
@@ -1,6 +1,6 @@
 use bevy::prelude::*;
+use bevy::app::{RunFixedMainLoop, RunFixedMainLoopSystem};
 use bevy::pbr::CascadeShadowConfigBuilder;
-use bevy::input::mouse::MouseMotion;
 use bevy::window::{WindowMode, PrimaryWindow};
 use bevy_egui::EguiPlugin;
 
@@ -10,6 +10,27 @@ mod targets;
 mod enemies;
 mod powerups;
 mod models;
+mod save;
+mod replay;
+mod ghost;
+mod physics;
+mod noise;
+mod flight;
+mod spatial;
+mod terrain;
+mod assets;
+mod pool;
+mod particles;
+mod particle_render;
+mod missiles;
+mod weapons;
+mod targeting;
+mod archetypes;
+mod ground;
+mod boss;
+mod radar;
+mod lod;
+mod headless;
 mod tests;
 
 use game_state::*;
@@ -17,8 +38,34 @@ use ui::*;
 use targets::*;
 use enemies::*;
 use powerups::*;
+use save::*;
+use replay::*;
+use ghost::*;
+use physics::*;
+use flight::*;
+use spatial::*;
+use terrain::*;
+use assets::*;
+use pool::*;
+use particles::*;
+use missiles::*;
+use weapons::*;
+use targeting::*;
+use models::{AircraftModel, AircraftModels, ModelsPlugin};
+use particle_render::ParticleRenderPlugin;
+use archetypes::*;
+use ground::*;
+use boss::*;
+use radar::*;
+use lod::*;
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.iter().any(|arg| arg == "--headless") {
+        headless::run(&args);
+        return;
+    }
+    
     App::new()
         .add_plugins((DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
@@ -27,65 +74,14 @@ fn main() {
                 ..default()
             }),
             ..default()
-        }), EguiPlugin { enable_multipass_for_primary_context: false }))
-        .init_state::<GameState>()
-        .init_resource::<CurrentGameMode>()
-        .init_resource::<GameStats>()
-        .init_resource::<ChallengeTimer>()
-        .init_resource::<UpgradeData>()
-        .init_resource::<ActivePowerUps>()
-        .add_event::<TargetHitEvent>()
-        .add_event::<EnemyDestroyedEvent>()
-        .add_event::<RadioChatterEvent>()
+        }), EguiPlugin { enable_multipass_for_primary_context: false }, SkyHunterPlugin, ParticleRenderPlugin))
         .add_systems(Startup, setup_menu_camera)
-        .add_systems(OnEnter(GameState::Playing), (setup_game, capture_mouse))
+        .add_systems(OnEnter(GameState::Playing), capture_mouse)
         .add_systems(OnExit(GameState::Playing), release_mouse)
-        .add_systems(OnEnter(GameState::MainMenu), (cleanup_game_entities, cleanup_game_stats))
-        .add_systems(
-            Update,
-            (
-                main_menu_ui,
-                update_high_score,
-            ).run_if(in_state(GameState::MainMenu)),
-        )
-        .add_systems(
-            Update,
-            (
-                flight_controls,
-                spawn_engine_trails,
-                update_engine_trails,
-                spawn_targets_system,
-                collision_detection_system,
-                magnet_effect_system,
-                animate_targets,
-                particle_system,
-                spawn_hit_particles,
-                combo_timeout_system,
-                update_challenge_timer,
-                check_game_over,
-                game_hud,
-                handle_escape_key,
-            ).run_if(in_state(GameState::Playing)),
-        )
+        .add_systems(Update, main_menu_ui.run_if(in_state(GameState::MainMenu)))
         .add_systems(
             Update,
-            (
-                spawn_enemies_system,
-                enemy_ai_system,
-                enemy_shooting_system,
-                player_shooting_system,
-                update_bullets_system,
-                bullet_collision_system,
-                player_damage_system,
-                player_enemy_collision_system,
-                spawn_explosion_particles,
-                spawn_powerups_system,
-                animate_powerups,
-                collect_powerups_system,
-                update_powerup_effects,
-                cleanup_expired_powerups,
-                update_shield_visual,
-            ).run_if(in_state(GameState::Playing)),
+            (game_hud, weapon_hud, missile_lock_hud, radar_hud, debug_overlay_ui, handle_escape_key, toggle_debug_overlay, cycle_radar_zoom).run_if(in_state(GameState::Playing)),
         )
         .add_systems(OnEnter(GameState::Paused), release_mouse)
         .add_systems(OnExit(GameState::Paused), capture_mouse)
@@ -95,9 +91,8 @@ fn main() {
         )
         .add_systems(
             Update,
-            (game_over_screen, save_coins).run_if(in_state(GameState::GameOver)),
+            game_over_screen.run_if(in_state(GameState::GameOver)),
         )
-        .add_systems(OnExit(GameState::GameOver), cleanup_game)
         .add_systems(
             Update,
             upgrade_shop_ui.run_if(in_state(GameState::UpgradeShop)),
@@ -105,6 +100,188 @@ fn main() {
         .run();
 }
 
+// All gameplay state and simulation systems. Windowing, input capture and egui screens are
+// added on top of this in `main`, so the plugin also runs under MinimalPlugins (see headless.rs).
+pub struct SkyHunterPlugin;
+
+impl Plugin for SkyHunterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ModelsPlugin)
+            .init_state::<GameState>()
+            .init_resource::<CurrentGameMode>()
+            .init_resource::<GameStats>()
+            .init_resource::<ChallengeTimer>()
+            .init_resource::<UpgradeData>()
+            .init_resource::<ActivePowerUps>()
+            .init_resource::<SaveFile>()
+            .init_resource::<GameRng>()
+            .init_resource::<SeedSettings>()
+            .init_resource::<RunClock>()
+            .init_resource::<RunTimers>()
+            .init_resource::<PlayerInput>()
+            .init_resource::<ReplayRecorder>()
+            .init_resource::<ReplayStore>()
+            .init_resource::<GhostState>()
+            .init_resource::<GhostStore>()
+            .init_resource::<SpatialIndex>()
+            .init_resource::<FlightModel>()
+            .init_resource::<TerrainHeightField>()
+            .init_resource::<TerrainStreamer>()
+            .init_resource::<DebugOverlay>()
+            .init_resource::<EntityPool<PlayerBullet>>()
+            .init_resource::<EntityPool<EnemyBullet>>()
+            .init_resource::<EntityPool<Missile>>()
+            .init_resource::<EntityPool<FlakShell>>()
+            .init_resource::<EntityPool<SamMissile>>()
+            .init_resource::<GroundSites>()
+            .init_resource::<BossWaves>()
+            .init_resource::<MissileLock>()
+            .init_resource::<Loadout>()
+            .init_resource::<TargetLock>()
+            .init_resource::<Radar>()
+            .add_event::<TargetHitEvent>()
+            .add_event::<EnemyDestroyedEvent>()
+            .add_event::<RadioChatterEvent>()
+            .add_event::<ParticleBurst>()
+            .init_asset::<EnemyArchetype>()
+            .init_asset_loader::<EnemyArchetypeLoader>()
+            .add_systems(Startup, (load_progress, setup_shared_assets, load_enemy_archetypes))
+            .add_systems(Update, update_enemy_archetypes)
+            // Player input comes either from the devices or from a replay being played back
+            .add_systems(
+                PreUpdate,
+                (
+                    read_live_input
+                        .after(bevy::input::InputSystem)
+                        .run_if(not(resource_exists::<ReplayPlayback>)),
+                    feed_replay_input.run_if(resource_exists::<ReplayPlayback>),
+                ),
+            )
+            // Resuming from pause also enters Playing, so only build a new world when there isn't one
+            .add_systems(
+                OnEnter(GameState::Playing),
+                (
+                    begin_run,
+                    start_recording,
+                    setup_game,
+                    start_ghost_run,
+                    (
+                        reset_pool::<PlayerBullet>,
+                        reset_pool::<EnemyBullet>,
+                        reset_pool::<Missile>,
+                        reset_pool::<FlakShell>,
+                        reset_pool::<SamMissile>,
+                        reset_ground_sites,
+                        reset_boss_waves,
+                        reset_loadout,
+                    ),
+                )
+                    .chain()
+                    .run_if(not(any_with_component::<Aircraft>)),
+            )
+            .add_systems(OnExit(GameState::Paused), resume_game_time)
+            .add_systems(
+                OnEnter(GameState::MainMenu),
+                (cleanup_game_entities, cleanup_game_stats, finish_recording, stop_playback),
+            )
+            .add_systems(
+                Update,
+                update_high_score.run_if(in_state(GameState::MainMenu)),
+            )
+            // The simulation steps at a fixed rate in a fixed order, so the same inputs always produce
+            // the same run; moving entities are drawn interpolated between their last two steps
+            .insert_resource(Time::<Fixed>::from_hz(SIMULATION_HZ))
+            .add_systems(
+                RunFixedMainLoop,
+                (
+                    (restore_simulated_transforms, accumulate_mouse_input)
+                        .in_set(RunFixedMainLoopSystem::BeforeFixedMainLoop),
+                    interpolate_transforms.in_set(RunFixedMainLoopSystem::AfterFixedMainLoop),
+                ).run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                FixedUpdate,
+                ((
+                    tick_run_clock,
+                    rebuild_spatial_index,
+                    flight_controls.run_if(resource_equals(FlightModel::Arcade)),
+                    simulation_flight_controls.run_if(resource_equals(FlightModel::Simulation)),
+                    terrain_collision_system,
+                    spawn_targets_system,
+                    collision_detection_system,
+                    magnet_effect_system,
+                    animate_targets,
+                    combo_timeout_system,
+                    update_challenge_timer,
+                    check_game_over,
+                ).chain_ignore_deferred(), (
+                    spawn_enemies_system,
+                    enemy_ai_system,
+                    keep_enemies_above_terrain,
+                    crashing_aircraft_system,
+                    enemy_shooting_system,
+                    (spawn_ground_units, ground_units_system, update_ground_fire).chain_ignore_deferred(),
+                    (boss_waves_system, boss_flight_system, boss_guns_system).chain_ignore_deferred(),
+                    (
+                        apply_weapon_powerups,
+                        cycle_secondary_weapon,
+                        update_target_lock,
+                        update_missile_lock,
+                        fire_player_weapons,
+                    ).chain_ignore_deferred(),
+                    update_bullets_system,
+                    update_missiles,
+                    bullet_collision_system,
+                    missile_collision_system,
+                    player_damage_system,
+                    player_enemy_collision_system,
+                    spawn_powerups_system,
+                    animate_powerups,
+                    collect_powerups_system,
+                    update_powerup_effects,
+                    cleanup_expired_powerups,
+                    record_ghost_sample,
+                ).chain_ignore_deferred()).chain_ignore_deferred().run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(FixedLast, record_simulated_transforms.run_if(in_state(GameState::Playing)))
+            // Camera and effects only affect what's drawn, so they follow the frame rate
+            .add_systems(
+                Update,
+                (
+                    follow_camera,
+                    spawn_hit_particles,
+                    spawn_explosion_particles,
+                    emit_particle_bursts,
+                    emit_engine_exhaust,
+                    emit_damage_trails,
+                    emit_missile_smoke,
+                    update_particle_emitters,
+                    update_shield_visual,
+                    update_ghost,
+                    stream_terrain_chunks,
+                    update_distance_lod,
+                    gather_scene_stats,
+                ).chain().run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                Last,
+                (
+                    record_frame.run_if(not(resource_exists::<ReplayPlayback>)),
+                    advance_playback.run_if(resource_exists::<ReplayPlayback>),
+                ).run_if(in_state(GameState::Playing)),
+            )
+            // Watching a replay doesn't earn coins, set high scores or replace ghosts
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                (
+                    (save_coins, finish_ghost_run).run_if(not(resource_exists::<ReplayPlayback>)),
+                    finish_recording,
+                ),
+            )
+            .add_systems(OnExit(GameState::GameOver), (cleanup_game, stop_playback));
+    }
+}
+
 #[derive(Component)]
 pub struct Aircraft {
     speed: f32,
@@ -112,6 +289,11 @@ pub struct Aircraft {
     current_roll: f32,
     target_roll: f32,
     boost_timer: f32,
+    // Speed flown during the last step, boost and throttle included
+    current_speed: f32,
+    velocity: Vec3,
+    // Mouse movement not yet turned into yaw/pitch; decays a little each frame for smoothing
+    mouse_delta: Vec2,
 }
 
 #[derive(Component)]
@@ -126,12 +308,6 @@ struct GameEntity;
 #[derive(Component)]
 struct MenuCamera;
 
-#[derive(Component)]
-struct EngineTrail {
-    lifetime: f32,
-    max_lifetime: f32,
-}
-
 fn setup_menu_camera(mut commands: Commands) {
     commands.spawn((
         Camera3d::default(),
@@ -141,68 +317,57 @@ fn setup_menu_camera(mut commands: Commands) {
     ));
 }
 
+// Resets everything a run depends on so the seed and inputs alone determine what happens
+fn begin_run(
+    mut rng: ResMut<GameRng>,
+    mut seed_settings: ResMut<SeedSettings>,
+    mut active_powerups: ResMut<ActivePowerUps>,
+    mut run_clock: ResMut<RunClock>,
+    mut run_timers: ResMut<RunTimers>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+    mut terrain: ResMut<TerrainHeightField>,
+) {
+    *rng = GameRng::from_seed(seed_settings.next_seed());
+    *terrain = TerrainHeightField::from_seed(rng.world.u32(..));
+    active_powerups.reset();
+    *run_clock = RunClock::default();
+    *run_timers = RunTimers::default();
+    // Time left over from the menus would shift where the fixed steps fall in a replay
+    let overstep = fixed_time.overstep();
+    fixed_time.discard_overstep(overstep);
+    info!("Starting run with seed {}", rng.seed);
+}
+
 fn setup_game(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    aircraft_models: Res<AircraftModels>,
     menu_camera: Query<Entity, With<MenuCamera>>,
     upgrades: Res<UpgradeData>,
+    mut rng: ResMut<GameRng>,
+    terrain: Res<TerrainHeightField>,
+    mut terrain_streamer: ResMut<TerrainStreamer>,
 ) {
     // Remove menu camera
     for camera in menu_camera.iter() {
         commands.entity(camera).despawn();
     }
     
-    // Terrain with height variation
-    let terrain_size = 400.0;
-    let terrain_resolution = 32;
-    let height_scale = 15.0;
-    
-    // Create terrain chunks for better performance
-    for chunk_x in -2..=2 {
-        for chunk_z in -2..=2 {
-            let offset_x = chunk_x as f32 * terrain_size;
-            let offset_z = chunk_z as f32 * terrain_size;
-            
-            let terrain_mesh = models::create_terrain_chunk(
-                terrain_size,
-                terrain_resolution,
-                |x, z| {
-                    // Simple height function using sine waves
-                    let nx = (x + offset_x) * 0.005;
-                    let nz = (z + offset_z) * 0.005;
-                    height_scale * (
-                        nx.sin() * 0.5 +
-                        (nx * 2.0).sin() * 0.25 +
-                        nz.cos() * 0.5 +
-                        (nz * 3.0).cos() * 0.25
-                    ) * 0.25
-                },
-            );
-            
-            commands.spawn((
-                Mesh3d(meshes.add(terrain_mesh)),
-                MeshMaterial3d(materials.add(StandardMaterial {
-                    base_color: Color::srgb(0.2, 0.4, 0.2),
-                    metallic: 0.0,
-                    perceptual_roughness: 0.9,
-                    double_sided: true,
-                    cull_mode: None,
-                    ..default()
-                })),
-                Transform::from_xyz(offset_x, 0.0, offset_z),
-                GameEntity,
-            ));
-        }
-    }
+    // Terrain is streamed in around the aircraft as it flies
+    terrain_streamer.reset(*terrain);
     
     // Calculate upgrade bonuses
     let speed_multiplier = get_speed_bonus(upgrades.speed_level);
     let maneuverability_multiplier = get_maneuverability_bonus(upgrades.maneuverability_level);
     
-    // Aircraft (player) - parent entity
+    // Aircraft (player) - parent entity, starting clear of the ground it's about to fly over
+    let start_height = (0..=8)
+        .map(|step| terrain.height_at(0.0, step as f32 * -50.0))
+        .fold(0.0, f32::max) + 50.0;
+    let aircraft_transform = Transform::from_xyz(0.0, start_height, 0.0);
     let aircraft_entity = commands.spawn((
-        Transform::from_xyz(0.0, 50.0, 0.0),
+        aircraft_transform,
         Visibility::default(),
         Aircraft {
             speed: 50.0 * speed_multiplier,
@@ -210,7 +375,11 @@ fn setup_game(
             current_roll: 0.0,
             target_roll: 0.0,
             boost_timer: 0.0,
+            current_speed: 50.0 * speed_multiplier,
+            velocity: aircraft_transform.forward() * 50.0 * speed_multiplier,
+            mouse_delta: Vec2::ZERO,
         },
+        InterpolatedTransform::new(aircraft_transform),
         Health {
             current: 100.0,
             max: 100.0,
@@ -218,88 +387,7 @@ fn setup_game(
         GameEntity,
     )).id();
     
-    // Fuselage
-    let fuselage = commands.spawn((
-        Mesh3d(meshes.add(Cuboid::new(1.5, 1.0, 4.0))),
-        MeshMaterial3d(materials.add(StandardMaterial {
-            base_color: Color::srgb(0.8, 0.2, 0.2),
-            metallic: 0.8,
-            perceptual_roughness: 0.3,
-            ..default()
-        })),
-        Transform::from_xyz(0.0, 0.0, 0.0),
-    )).id();
-    
-    // Nose cone
-    let nose = commands.spawn((
-        Mesh3d(meshes.add(Cylinder::new(0.5, 1.5))),
-        MeshMaterial3d(materials.add(StandardMaterial {
-            base_color: Color::srgb(0.6, 0.15, 0.15),
-            metallic: 0.9,
-            perceptual_roughness: 0.2,
-            ..default()
-        })),
-        Transform::from_xyz(0.0, 0.0, -2.75)
-            .with_rotation(Quat::from_rotation_x(std::f32::consts::PI / 2.0)),
-    )).id();
-    
-    // Left wing
-    let left_wing = commands.spawn((
-        Mesh3d(meshes.add(Cuboid::new(6.0, 0.2, 2.0))),
-        MeshMaterial3d(materials.add(StandardMaterial {
-            base_color: Color::srgb(0.9, 0.3, 0.3),
-            metallic: 0.7,
-            perceptual_roughness: 0.4,
-            ..default()
-        })),
-        Transform::from_xyz(-4.0, 0.0, 0.5),
-    )).id();
-    
-    // Right wing
-    let right_wing = commands.spawn((
-        Mesh3d(meshes.add(Cuboid::new(6.0, 0.2, 2.0))),
-        MeshMaterial3d(materials.add(StandardMaterial {
-            base_color: Color::srgb(0.9, 0.3, 0.3),
-            metallic: 0.7,
-            perceptual_roughness: 0.4,
-            ..default()
-        })),
-        Transform::from_xyz(4.0, 0.0, 0.5),
-    )).id();
-    
-    // Tail fin
-    let tail_fin = commands.spawn((
-        Mesh3d(meshes.add(Cuboid::new(0.2, 2.0, 1.5))),
-        MeshMaterial3d(materials.add(StandardMaterial {
-            base_color: Color::srgb(0.7, 0.2, 0.2),
-            metallic: 0.6,
-            perceptual_roughness: 0.5,
-            ..default()
-        })),
-        Transform::from_xyz(0.0, 1.0, 2.5),
-    )).id();
-    
-    // Horizontal stabilizer
-    let h_stabilizer = commands.spawn((
-        Mesh3d(meshes.add(Cuboid::new(3.0, 0.15, 1.0))),
-        MeshMaterial3d(materials.add(StandardMaterial {
-            base_color: Color::srgb(0.7, 0.2, 0.2),
-            metallic: 0.6,
-            perceptual_roughness: 0.5,
-            ..default()
-        })),
-        Transform::from_xyz(0.0, 0.0, 2.5),
-    )).id();
-    
-    // Build the aircraft hierarchy
-    commands.entity(aircraft_entity).add_children(&[
-        fuselage,
-        nose,
-        left_wing,
-        right_wing,
-        tail_fin,
-        h_stabilizer,
-    ]);
+    aircraft_models.spawn(&mut commands, aircraft_entity, AircraftModel::Player, aircraft_models.livery(AircraftModel::Player));
     
     // Camera attached to aircraft
     commands.spawn((
@@ -308,8 +396,8 @@ fn setup_game(
             clear_color: ClearColorConfig::Custom(Color::srgb(0.5, 0.7, 1.0)),
             ..default()
         },
-        Transform::from_xyz(0.0, 55.0, 15.0)
-            .looking_at(Vec3::new(0.0, 50.0, 0.0), Vec3::Y),
+        Transform::from_xyz(0.0, start_height + 5.0, 15.0)
+            .looking_at(aircraft_transform.translation, Vec3::Y),
         FlightCamera {
             shake_amount: 0.0,
             shake_timer: 0.0,
@@ -340,59 +428,34 @@ fn setup_game(
         affects_lightmapped_meshes: false,
     });
     
-    // Add some environmental decoration - trees with detailed models
-    for i in 0..50 {
-        let x = (fastrand::f32() - 0.5) * 1000.0;
-        let z = (fastrand::f32() - 0.5) * 1000.0;
-        
-        // Create detailed tree with branches
-        commands.spawn((
-            Mesh3d(meshes.add(models::create_tree_mesh(i as u32))),
-            MeshMaterial3d(materials.add(StandardMaterial {
-                base_color: Color::srgb(0.4, 0.3, 0.2),
-                perceptual_roughness: 0.9,
-                ..default()
-            })),
-            Transform::from_xyz(x, 0.0, z),
-            GameEntity,
-        ));
-        
-        // Tree leaves (canopy)
-        let canopy_size = 8.0 + fastrand::f32() * 4.0;
-        let height_variation = ((i % 10) as f32 / 10.0) * 10.0;
-        let trunk_height = 15.0 + height_variation;
-        
-        commands.spawn((
-            Mesh3d(meshes.add(Sphere::new(canopy_size))),
-            MeshMaterial3d(materials.add(StandardMaterial {
-                base_color: Color::srgb(0.2, 0.6, 0.2),
-                perceptual_roughness: 0.8,
-                ..default()
-            })),
-            Transform::from_xyz(x, trunk_height + canopy_size * 0.5, z),
-            GameEntity,
-        ));
-    }
-    
-    // Add volumetric clouds
+    // Add volumetric clouds. They share one material and a handful of shapes, each with a
+    // coarser version for when it's far away.
+    let cloud_material = materials.add(StandardMaterial {
+        base_color: Color::srgba(1.0, 1.0, 1.0, 0.6),
+        alpha_mode: AlphaMode::Blend,
+        perceptual_roughness: 1.0,
+        double_sided: true,
+        cull_mode: None,
+        ..default()
+    });
+    let cloud_shapes: Vec<(Handle<Mesh>, Handle<Mesh>)> = (0..CLOUD_SHAPES)
+        .map(|seed| (
+            meshes.add(models::create_volumetric_cloud_mesh(seed, 8, 12)),
+            meshes.add(models::create_volumetric_cloud_mesh(seed, 4, 6)),
+        ))
+        .collect();
     for i in 0..30 {
-        let x = (fastrand::f32() - 0.5) * 2000.0;
-        let z = (fastrand::f32() - 0.5) * 2000.0;
-        let y = 150.0 + fastrand::f32() * 150.0;
+        let x = (rng.world.f32() - 0.5) * 2000.0;
+        let z = (rng.world.f32() - 0.5) * 2000.0;
+        let y = 150.0 + rng.world.f32() * 150.0;
+        let (near, far) = &cloud_shapes[i % cloud_shapes.len()];
         
-        // Use volumetric cloud mesh
         commands.spawn((
-            Mesh3d(meshes.add(models::create_volumetric_cloud_mesh(i as u32))),
-            MeshMaterial3d(materials.add(StandardMaterial {
-                base_color: Color::srgba(1.0, 1.0, 1.0, 0.6),
-                alpha_mode: AlphaMode::Blend,
-                perceptual_roughness: 1.0,
-                double_sided: true,
-                cull_mode: None,
-                ..default()
-            })),
+            Mesh3d(near.clone()),
+            MeshMaterial3d(cloud_material.clone()),
             Transform::from_xyz(x, y, z)
                 .with_scale(Vec3::splat(2.0)),
+            DistanceLod::new(near.clone(), far.clone(), CLOUD_LOD_DISTANCE),
             GameEntity,
         ));
     }
@@ -424,33 +487,39 @@ fn cleanup_game_stats(
     game_stats.combo = 0;
     game_stats.targets_hit = 0;
     game_stats.time_played = 0.0;
+    game_stats.bonus_coins = 0;
     
     // Reset active powerups
     active_powerups.reset();
 }
 
+// Mouse movement is read once per frame but flown over however many fixed steps the frame runs
+fn accumulate_mouse_input(input: Res<PlayerInput>, mut query: Query<&mut Aircraft>) {
+    for mut aircraft in query.iter_mut() {
+        aircraft.mouse_delta += input.mouse_delta;
+    }
+}
+
 fn flight_controls(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input: Res<PlayerInput>,
     time: Res<Time>,
     mut query: Query<(&mut Transform, &mut Aircraft)>,
-    mut camera_query: Query<(&mut Transform, &mut FlightCamera), Without<Aircraft>>,
     game_state: Res<State<GameState>>,
-    mut mouse_delta: Local<Vec2>,
-    mut motion_events: EventReader<MouseMotion>,
     active_powerups: Res<ActivePowerUps>,
+    terrain: Res<TerrainHeightField>,
 ) {
     if *game_state != GameState::Playing {
         return;
     }
     
-    // Accumulate mouse movement
-    for event in motion_events.read() {
-        *mouse_delta += event.delta;
-    }
-    
     for (mut transform, mut aircraft) in query.iter_mut() {
         let delta = time.delta_secs();
         
+        let mouse_delta = aircraft.mouse_delta;
+        
+        // Pitch, yaw and roll for this step, about the aircraft's own axes
+        let mut turn = Vec3::ZERO;
+        
         // Enhanced mouse controls with improved responsiveness
         let sensitivity = 0.001; // Slightly increased for better control
         if mouse_delta.length() > 0.0 {
@@ -461,58 +530,57 @@ fn flight_controls(
             let smoothed_x = mouse_delta.x.clamp(-200.0, 200.0);
             let smoothed_y = mouse_delta.y.clamp(-200.0, 200.0);
             
-            // Yaw (left/right mouse movement) with momentum
-            let yaw_amount = -smoothed_x * adaptive_sensitivity;
-            transform.rotate_y(yaw_amount);
-            
-            // Pitch (up/down mouse movement) with realistic limits
-            let pitch_amount = -smoothed_y * adaptive_sensitivity;
-            let current_pitch = transform.rotation.to_euler(EulerRot::YXZ).1;
-            let new_pitch = (current_pitch + pitch_amount).clamp(-1.0, 0.8); // Asymmetric limits
-            transform.rotation = Quat::from_euler(
-                EulerRot::YXZ,
-                transform.rotation.to_euler(EulerRot::YXZ).0,
-                new_pitch,
-                transform.rotation.to_euler(EulerRot::YXZ).2,
-            );
+            // Mouse steers the nose: left/right yaws, up/down pitches with no limit, so loops are possible
+            turn.y -= smoothed_x * adaptive_sensitivity;
+            turn.x -= smoothed_y * adaptive_sensitivity;
             
             // Auto-roll based on yaw for realistic banking
             aircraft.target_roll = -smoothed_x * 0.015 * (1.0 + aircraft.speed / 100.0).min(2.0);
             
             // Reset mouse delta with decay for smoother control
-            *mouse_delta *= 0.2;
+            aircraft.mouse_delta *= 0.2;
         } else {
             aircraft.target_roll *= 0.95; // Gradual return to neutral
         }
         
-        // Manual roll controls (A/D) with improved banking
-        if keyboard_input.pressed(KeyCode::KeyA) {
+        // Manual roll controls (A/D) with improved banking; holding one flies a barrel roll
+        if input.roll_left {
             aircraft.target_roll = 0.7;
-            transform.rotate_y(aircraft.roll_speed * 0.3 * delta); // Banking affects turn rate
+            turn.y += aircraft.roll_speed * 0.3 * delta; // Banking affects turn rate
         }
-        if keyboard_input.pressed(KeyCode::KeyD) {
+        if input.roll_right {
             aircraft.target_roll = -0.7;
-            transform.rotate_y(-aircraft.roll_speed * 0.3 * delta);
+            turn.y -= aircraft.roll_speed * 0.3 * delta;
         }
         
         // Advanced roll physics
         aircraft.current_roll = aircraft.current_roll.lerp(aircraft.target_roll, delta * 4.0);
-        transform.rotate_local_z(aircraft.current_roll * aircraft.roll_speed * delta);
+        turn.z += aircraft.current_roll * aircraft.roll_speed * delta;
+        
+        // Hands off, the wings slowly roll back to level. Rolling out from upside down at the top
+        // of a loop finishes an Immelmann; it does nothing while the nose points straight up or down.
+        let hands_off = !input.roll_left && !input.roll_right && mouse_delta.length() < 1.0;
+        if hands_off {
+            let horizontal = 1.0 - transform.forward().y.abs();
+            turn.z -= bank_angle(transform.rotation) * horizontal * delta;
+        }
+        
+        transform.rotation = turn_in_body_frame(transform.rotation, turn);
         
         // Speed controls with acceleration/deceleration
         let base_speed = aircraft.speed * active_powerups.speed_multiplier;
         let mut target_speed = base_speed;
         
         // Throttle controls
-        if keyboard_input.pressed(KeyCode::KeyW) {
+        if input.throttle_up {
             target_speed = base_speed * 1.8;
             aircraft.boost_timer = 0.05; // Light afterburner effect
-        } else if keyboard_input.pressed(KeyCode::KeyS) {
+        } else if input.throttle_down {
             target_speed = base_speed * 0.5;
         }
         
         // Boost with energy management
-        if keyboard_input.pressed(KeyCode::Space) && aircraft.boost_timer <= 0.0 {
+        if input.boost && aircraft.boost_timer <= 0.0 {
             target_speed = base_speed * 3.0;
             aircraft.boost_timer = 0.2;
         }
@@ -534,15 +602,14 @@ fn flight_controls(
         let velocity = forward * current_speed;
         
         // Add lift based on speed and pitch
-        let pitch_angle = transform.rotation.to_euler(EulerRot::YXZ).1;
-        let lift_factor = (current_speed / base_speed).min(2.0) * pitch_angle.sin();
+        let lift_factor = (current_speed / base_speed).min(2.0) * forward.y;
         let lift = Vec3::Y * lift_factor * 10.0;
         
         transform.translation += (velocity + lift) * delta;
         
-        // Altitude management with ground effect
-        let ground_height = 5.0;
-        let effect_height = 20.0;
+        // Altitude management with ground effect, measured from the terrain below
+        let ground_height = terrain.height_at(transform.translation.x, transform.translation.z) + 5.0;
+        let effect_height = ground_height + 15.0;
         if transform.translation.y < effect_height {
             let ground_effect = 1.0 - (transform.translation.y - ground_height) / (effect_height - ground_height);
             let upward_force = ground_effect.max(0.0) * 50.0;
@@ -550,21 +617,33 @@ fn flight_controls(
             
             // Auto-level when very low
             if transform.translation.y < ground_height + 5.0 {
-                let level_rotation = transform.rotation.slerp(
-                    Quat::from_rotation_y(transform.rotation.to_euler(EulerRot::YXZ).0),
-                    delta * 2.0
-                );
+                let level_rotation = transform.rotation.slerp(level_attitude(transform.rotation), delta * 2.0);
                 transform.rotation = level_rotation;
             }
         }
         
-        // Prevent going below ground
-        transform.translation.y = transform.translation.y.max(ground_height);
+        aircraft.current_speed = current_speed;
+        aircraft.velocity = velocity + lift;
+    }
+}
+
+fn follow_camera(
+    time: Res<Time>,
+    aircraft_query: Query<(&Transform, &Aircraft)>,
+    mut camera_query: Query<(&mut Transform, &mut FlightCamera), Without<Aircraft>>,
+    active_powerups: Res<ActivePowerUps>,
+) {
+    let delta = time.delta_secs();
+    
+    for (transform, aircraft) in aircraft_query.iter() {
+        let forward = transform.forward();
+        let velocity = aircraft.velocity;
+        let base_speed = aircraft.speed * active_powerups.speed_multiplier;
         
         // Update camera with cinematic movement
         if let Ok((mut camera_transform, mut camera)) = camera_query.single_mut() {
             // Dynamic camera positioning
-            let speed_ratio = current_speed / base_speed;
+            let speed_ratio = aircraft.current_speed / base_speed;
             let base_offset = Vec3::new(0.0, 8.0, 20.0);
             
             // Pull camera back when boosting
@@ -602,7 +681,8 @@ fn flight_controls(
             // Look ahead with predictive targeting
             let velocity_prediction = velocity * 0.2;
             let look_target = transform.translation + forward * 20.0 + velocity_prediction;
-            camera_transform.look_at(look_target, Vec3::Y);
+            // Keep the aircraft's own up so loops and inverted flight read correctly
+            camera_transform.look_at(look_target, transform.up());
             
             // Dynamic camera roll
             let camera_roll = aircraft.current_roll * 0.4 * (1.0 - speed_ratio * 0.2).max(0.3);
@@ -612,10 +692,15 @@ fn flight_controls(
 }
 
 
+fn tick_run_clock(time: Res<Time>, mut run_clock: ResMut<RunClock>) {
+    run_clock.elapsed += time.delta_secs();
+}
+
 fn update_challenge_timer(
     mut timer: ResMut<ChallengeTimer>,
     game_mode: Res<CurrentGameMode>,
     mut game_stats: ResMut<GameStats>,
+    boss_query: Query<(), With<Boss>>,
     time: Res<Time>,
 ) {
     // Update time played
@@ -626,6 +711,8 @@ fn update_challenge_timer(
     game_stats.difficulty_level = 1.0 + (game_stats.time_played / 60.0) * difficulty_increase_rate;
     
     match game_mode.mode {
+        // The clock stops while a boss is fighting
+        GameMode::Survival if !boss_query.is_empty() => {}
         GameMode::TimeAttack | GameMode::Survival | GameMode::RaceTheClock => {
             timer.time_remaining -= time.delta_secs();
             if timer.time_remaining < 0.0 {
@@ -663,109 +750,29 @@ fn update_high_score(mut game_stats: ResMut<GameStats>) {
     }
 }
 
-fn save_coins(mut game_stats: ResMut<GameStats>, mut saved: Local<bool>) {
-    if !*saved {
-        let coins_earned = game_stats.score / 100;
-        game_stats.coins += coins_earned;
-        *saved = true;
-    }
-}
-
-fn spawn_engine_trails(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    aircraft_query: Query<&Transform, With<Aircraft>>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    time: Res<Time>,
-    mut spawn_timer: Local<f32>,
+fn save_coins(
+    mut game_stats: ResMut<GameStats>,
+    upgrades: Res<UpgradeData>,
+    save_file: Res<SaveFile>,
 ) {
-    *spawn_timer += time.delta_secs();
-    
-    // Spawn trails more frequently when boosting
-    let spawn_interval = if keyboard_input.pressed(KeyCode::Space) {
-        0.02
-    } else {
-        0.05
-    };
+    let coins_earned = game_stats.coins_earned();
+    game_stats.coins += coins_earned;
     
-    if *spawn_timer < spawn_interval {
-        return;
+    // Record the high score now so it survives quitting straight from the game over screen
+    if game_stats.score > game_stats.high_score {
+        game_stats.high_score = game_stats.score;
     }
     
-    *spawn_timer = 0.0;
-    
-    for transform in aircraft_query.iter() {
-        // Spawn trail particles behind the aircraft
-        // Removed unused variable
-        
-        // Base color changes with boost
-        let (base_color, emissive_strength, trail_size) = if keyboard_input.pressed(KeyCode::Space) {
-            (Color::srgb(1.0, 0.4, 0.1), 3.0, 0.5) // Orange for boost
-        } else if keyboard_input.pressed(KeyCode::KeyW) {
-            (Color::srgb(0.4, 0.7, 1.0), 2.0, 0.4) // Bright blue for speed
-        } else {
-            (Color::srgb(0.2, 0.5, 0.9), 1.5, 0.3) // Blue for normal
-        };
-        
-        // Spawn two trails for each engine
-        for offset in [Vec3::new(-3.0, -0.5, 3.0), Vec3::new(3.0, -0.5, 3.0)] {
-            let trail_offset = transform.rotation * offset;
-            let trail_pos = transform.translation + trail_offset;
-            
-            commands.spawn((
-                Mesh3d(meshes.add(Sphere::new(trail_size))),
-                MeshMaterial3d(materials.add(StandardMaterial {
-                    base_color,
-                    emissive: base_color.to_linear() * emissive_strength,
-                    alpha_mode: AlphaMode::Blend,
-                    ..default()
-                })),
-                Transform::from_translation(trail_pos),
-                EngineTrail {
-                    lifetime: 0.0,
-                    max_lifetime: if keyboard_input.pressed(KeyCode::Space) { 0.8 } else { 0.5 },
-                },
-                GameEntity,
-            ));
-        }
-    }
-}
-
-fn update_engine_trails(
-    mut commands: Commands,
-    mut trail_query: Query<(Entity, &mut Transform, &mut EngineTrail, &MeshMaterial3d<StandardMaterial>)>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    time: Res<Time>,
-) {
-    for (entity, mut transform, mut trail, material_handle) in trail_query.iter_mut() {
-        trail.lifetime += time.delta_secs();
-        
-        let lifetime_ratio = trail.lifetime / trail.max_lifetime;
-        
-        // Fade out and shrink
-        if let Some(material) = materials.get_mut(&material_handle.0) {
-            let alpha = 1.0 - lifetime_ratio;
-            material.base_color.set_alpha(alpha);
-            
-            // Scale down
-            let scale = 1.0 - (lifetime_ratio * 0.8);
-            transform.scale = Vec3::splat(scale);
-        }
-        
-        // Remove when lifetime expires
-        if trail.lifetime >= trail.max_lifetime {
-            commands.entity(entity).despawn();
-        }
-    }
+    save_progress(&save_file, &game_stats, &upgrades);
 }
 
 fn handle_escape_key(
     keyboard_input: Res<ButtonInput<KeyCode>>,
     mut game_state: ResMut<NextState<GameState>>,
+    mut virtual_time: ResMut<Time<Virtual>>,
 ) {
     if keyboard_input.just_pressed(KeyCode::Escape) {
-        game_state.set(GameState::Paused);
+        pause_run(&mut game_state, &mut virtual_time);
     }
 }
 
@@ -783,6 +790,15 @@ fn toggle_fullscreen(
     }
 }
 
+fn toggle_debug_overlay(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut overlay: ResMut<DebugOverlay>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F3) {
+        overlay.visible = !overlay.visible;
+    }
+}
+
 fn capture_mouse(
     mut windows: Query<&mut Window, With<PrimaryWindow>>,
 ) {
@@ -825,48 +841,40 @@ fn cleanup_game_entities(
 fn player_enemy_collision_system(
     mut commands: Commands,
     mut player_query: Query<(&Transform, &mut enemies::Health), With<Aircraft>>,
-    enemy_query: Query<(Entity, &Transform, &enemies::Enemy), Without<Aircraft>>,
+    mut enemy_query: Query<(&Transform, &mut enemies::Health, &enemies::Enemy), Without<Aircraft>>,
     mut destroyed_events: EventWriter<enemies::EnemyDestroyedEvent>,
     mut game_stats: ResMut<GameStats>,
     mut camera_query: Query<&mut FlightCamera>,
+    spatial_index: Res<SpatialIndex>,
+    archetypes: Res<EnemyArchetypes>,
 ) {
     if let Ok((player_transform, mut player_health)) = player_query.single_mut() {
-        for (enemy_entity, enemy_transform, enemy) in enemy_query.iter() {
-            let distance = player_transform.translation.distance(enemy_transform.translation);
-            
-            // Collision radius based on enemy type
-            let collision_radius = match enemy.enemy_type {
-                enemies::EnemyType::Bomber => 8.0,
-                enemies::EnemyType::Fighter => 6.0,
-                enemies::EnemyType::Ace => 5.0,
+        for enemy_entity in spatial_index.enemies.query_sphere(player_transform.translation, archetypes.largest_ramming_radius()) {
+            let Ok((enemy_transform, mut enemy_health, enemy)) = enemy_query.get_mut(enemy_entity) else {
+                continue;
             };
+            // Shot down earlier in this step; the wreck isn't a crash until the step's commands run
+            if enemy_health.current <= 0.0 {
+                continue;
+            }
+            let distance = player_transform.translation.distance(enemy_transform.translation);
             
-            if distance < collision_radius {
-                // Collision damage
-                let collision_damage = match enemy.enemy_type {
-                    enemies::EnemyType::Bomber => 40.0,
-                    enemies::EnemyType::Fighter => 30.0,
-                    enemies::EnemyType::Ace => 25.0,
-                };
-                
-                player_health.current = (player_health.current - collision_damage).max(0.0);
+            let ramming = &enemy.archetype.ramming;
+            if distance < ramming.radius {
+                player_health.current = (player_health.current - ramming.damage).max(0.0);
                 
                 // Award partial points for ramming
-                let ram_points = match enemy.enemy_type {
-                    enemies::EnemyType::Fighter => 25,
-                    enemies::EnemyType::Bomber => 50,
-                    enemies::EnemyType::Ace => 100,
-                };
-                game_stats.score += ram_points;
+                game_stats.score += ramming.points;
                 
                 // Send destroyed event
                 destroyed_events.write(enemies::EnemyDestroyedEvent {
                     position: enemy_transform.translation,
-                    enemy_type: enemy.enemy_type,
+                    enemy: enemies::DestroyedEnemy::Aircraft(enemy.archetype.clone()),
                 });
                 
-                // Remove enemy
-                commands.entity(enemy_entity).despawn();
+                // The collision takes the enemy down
+                enemy_health.current = 0.0;
+                enemies::start_crash(&mut commands, enemy_entity, enemy_transform, enemy);
                 
                 // Camera shake on collision
                 if let Ok(mut camera) = camera_query.single_mut() {
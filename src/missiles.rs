@@ -0,0 +1,351 @@
+use bevy::prelude::*;
+use crate::{Aircraft, game_state::GameStats};
+use crate::assets::SharedAssets;
+use crate::enemies::{Enemy, EnemyDestroyedEvent, Health, damage_enemy};
+use crate::particles::{ParticleBurst, ParticlePreset};
+use crate::physics::{InterpolatedTransform, segment_hits_sphere};
+use crate::pool::{EntityPool, Overflow, PoolConfig, Poolable};
+use crate::spatial::SpatialIndex;
+use crate::ground::{GROUND_UNIT_HIT_RADIUS, GroundUnit, damage_ground_unit};
+use crate::boss::{Boss, BossPart, boss_hit, damage_boss, damage_boss_part};
+use crate::terrain::TerrainHeightField;
+use crate::targeting::{TargetLock, enemies_in_cone};
+use crate::weapons::{Loadout, Projectile, Weapon};
+
+// Half-angle of the cone in front of the launcher (or the missile's seeker) that targets are picked from
+pub const MISSILE_LOCK_CONE: f32 = 0.5;
+pub const MISSILE_LOCK_RANGE: f32 = 600.0;
+// Seconds of thrust; once it's gone the missile stops steering and coasts until its lifetime runs out
+const MISSILE_FUEL: f32 = 3.0;
+const MISSILE_LIFETIME: f32 = 6.0;
+const MISSILE_THRUST: f32 = 150.0;
+const MISSILE_TURN_RATE: f32 = 4.0;
+const NAVIGATION_CONSTANT: f32 = 4.0;
+const MISSILE_FUSE_RADIUS: f32 = 8.0;
+const MISSILE_BLAST_RADIUS: f32 = 12.0;
+
+#[derive(Component)]
+pub struct Missile {
+    pub target: Option<Entity>,
+    pub velocity: Vec3,
+    pub fuel: f32,
+    pub lifetime: f32,
+    pub max_speed: f32,
+    pub damage: f32,
+}
+
+impl Poolable for Missile {
+    const POOL: PoolConfig = PoolConfig { size: 16, overflow: Overflow::Grow };
+}
+
+// The enemy the next missile will be launched at, shown on the HUD. Only set while missiles
+// are the selected secondary weapon and there are some left.
+#[derive(Resource, Default)]
+pub struct MissileLock {
+    pub target: Option<Entity>,
+}
+
+// The nearest enemy within the lock cone and range of a launcher or seeker at `position`, facing `forward`
+pub fn acquire_target<'a>(
+    position: Vec3,
+    forward: Vec3,
+    enemies: impl Iterator<Item = (Entity, &'a Transform)>,
+) -> Option<Entity> {
+    enemies_in_cone(position, forward, MISSILE_LOCK_CONE, MISSILE_LOCK_RANGE, enemies)
+        .first()
+        .map(|(entity, _)| *entity)
+}
+
+// Proportional navigation: turns the missile at a multiple of the rate its line of sight to the
+// target is rotating, which puts it on a collision course rather than chasing the target's tail.
+// The result is limited to `max_turn_rate` radians per second.
+pub fn proportional_navigation(
+    missile_position: Vec3,
+    missile_velocity: Vec3,
+    target_position: Vec3,
+    target_velocity: Vec3,
+    max_turn_rate: f32,
+) -> Vec3 {
+    let line_of_sight = target_position - missile_position;
+    let relative_velocity = target_velocity - missile_velocity;
+    let distance_squared = line_of_sight.length_squared();
+    if distance_squared < f32::EPSILON {
+        return Vec3::ZERO;
+    }
+    let line_of_sight_rate = line_of_sight.cross(relative_velocity) / distance_squared;
+    let acceleration = NAVIGATION_CONSTANT * line_of_sight_rate.cross(missile_velocity);
+    acceleration.clamp_length_max(max_turn_rate * missile_velocity.length())
+}
+
+pub fn update_missile_lock(
+    mut lock: ResMut<MissileLock>,
+    player_query: Query<&Transform, With<Aircraft>>,
+    enemy_query: Query<(Entity, &Transform), Or<(With<Enemy>, With<Boss>)>>,
+    loadout: Res<Loadout>,
+    target_lock: Res<TargetLock>,
+) {
+    let armed = loadout
+        .secondary()
+        .is_some_and(|slot| slot.weapon.projectile == Projectile::Missile && slot.has_ammo());
+    let player_transform = match player_query.single() {
+        Ok(transform) if armed => transform,
+        _ => {
+            lock.target = None;
+            return;
+        }
+    };
+
+    // The selected target when the seeker can see it, otherwise the nearest enemy it can
+    let in_reach = enemies_in_cone(
+        player_transform.translation,
+        *player_transform.forward(),
+        MISSILE_LOCK_CONE,
+        MISSILE_LOCK_RANGE,
+        enemy_query.iter(),
+    );
+    lock.target = in_reach
+        .iter()
+        .find(|(entity, _)| Some(*entity) == target_lock.target)
+        .or(in_reach.first())
+        .map(|(entity, _)| *entity);
+}
+
+// Dropped from under the launcher's fuselage, already flying as fast as it is
+pub fn launch_missile(
+    commands: &mut Commands,
+    missile_pool: &mut EntityPool<Missile>,
+    shared_assets: &SharedAssets,
+    launcher: &Transform,
+    launcher_speed: f32,
+    target: Option<Entity>,
+    weapon: &Weapon,
+) {
+    let launch_position = launcher.translation - launcher.up() * 1.5 + launcher.forward() * 2.0;
+    let missile_transform = Transform::from_translation(launch_position)
+        .with_rotation(launcher.rotation)
+        .with_scale(Vec3::new(0.25, 0.25, 1.2));
+    missile_pool.spawn(commands, (
+        Mesh3d(shared_assets.sphere.clone()),
+        MeshMaterial3d(shared_assets.glow(weapon.tint)),
+        missile_transform,
+        InterpolatedTransform::new(missile_transform),
+        Missile {
+            target,
+            velocity: launcher.forward() * launcher_speed.max(60.0),
+            fuel: MISSILE_FUEL,
+            lifetime: MISSILE_LIFETIME,
+            max_speed: weapon.projectile_speed,
+            damage: weapon.damage,
+        },
+    ));
+}
+
+pub fn update_missiles(
+    mut commands: Commands,
+    mut missile_query: Query<(Entity, &mut Transform, &mut Missile), (Without<Enemy>, Without<Boss>)>,
+    enemy_query: Query<(Entity, &Transform, &InterpolatedTransform), Or<(With<Enemy>, With<Boss>)>>,
+    mut missile_pool: ResMut<EntityPool<Missile>>,
+    mut bursts: EventWriter<ParticleBurst>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_secs();
+
+    for (entity, mut transform, mut missile) in missile_query.iter_mut() {
+        missile.lifetime -= delta;
+        if missile.lifetime <= 0.0 {
+            // Self-destructs once it's spent
+            bursts.write(ParticleBurst {
+                preset: ParticlePreset::Explosion,
+                position: transform.translation,
+                velocity: Vec3::ZERO,
+                count: 10,
+                color: None,
+            });
+            missile_pool.release(&mut commands, entity);
+            continue;
+        }
+
+        if missile.fuel > 0.0 {
+            missile.fuel -= delta;
+
+            // A missile whose target is gone looks for another in front of it
+            if missile.target.is_none_or(|target| !enemy_query.contains(target)) {
+                missile.target = acquire_target(
+                    transform.translation,
+                    missile.velocity.normalize_or_zero(),
+                    enemy_query.iter().map(|(entity, transform, _)| (entity, transform)),
+                );
+            }
+
+            let speed = (missile.velocity.length() + MISSILE_THRUST * delta).min(missile.max_speed);
+            if let Some((_, target_transform, target_interpolated)) = missile.target.and_then(|target| enemy_query.get(target).ok()) {
+                // How far the target moved over the last step
+                let target_velocity = (target_interpolated.current.translation - target_interpolated.previous.translation) / delta;
+                let acceleration = proportional_navigation(
+                    transform.translation,
+                    missile.velocity,
+                    target_transform.translation,
+                    target_velocity,
+                    MISSILE_TURN_RATE,
+                );
+                missile.velocity += acceleration * delta;
+            }
+            missile.velocity = missile.velocity.normalize_or_zero() * speed;
+        } else {
+            // Out of fuel: slows down and drops
+            missile.velocity *= 1.0 - 0.3 * delta;
+            missile.velocity.y -= 9.8 * delta;
+        }
+
+        transform.translation += missile.velocity * delta;
+        if let Ok(direction) = Dir3::new(missile.velocity) {
+            transform.look_to(direction, Vec3::Y);
+        }
+    }
+}
+
+// Missiles go off when they pass close to any enemy or boss, damaging everything in the blast, or
+// when they hit the ground, where the blast can take out ground units
+pub fn missile_collision_system(
+    mut commands: Commands,
+    missile_query: Query<(Entity, &Transform, &Missile)>,
+    mut enemy_query: Query<(Entity, &Transform, &mut Health, &mut Enemy), Without<Missile>>,
+    mut ground_query: Query<(&Transform, &mut Health, &GroundUnit), (Without<Missile>, Without<Enemy>)>,
+    mut boss_query: Query<(Entity, &Transform, &mut Health, &mut Boss), (Without<Missile>, Without<Enemy>, Without<GroundUnit>)>,
+    mut part_query: Query<(&BossPart, &mut Health), (Without<Boss>, Without<Enemy>, Without<GroundUnit>)>,
+    mut destroyed_events: EventWriter<EnemyDestroyedEvent>,
+    mut bursts: EventWriter<ParticleBurst>,
+    mut game_stats: ResMut<GameStats>,
+    mut missile_pool: ResMut<EntityPool<Missile>>,
+    height_field: Res<TerrainHeightField>,
+    spatial_index: Res<SpatialIndex>,
+    time: Res<Time>,
+) {
+    for (missile_entity, missile_transform, missile) in missile_query.iter() {
+        let missile_end = missile_transform.translation;
+        let missile_start = missile_end - missile.velocity * time.delta_secs();
+
+        // The point along this step's path closest to the first enemy inside the fuse radius
+        let detonation = spatial_index
+            .enemies
+            .query_segment(missile_start, missile_end, MISSILE_FUSE_RADIUS)
+            .filter_map(|enemy_entity| enemy_query.get(enemy_entity).ok())
+            .find(|(_, enemy_transform, health, _)| {
+                health.current > 0.0
+                    && segment_hits_sphere(missile_start, missile_end, enemy_transform.translation, MISSILE_FUSE_RADIUS)
+            })
+            .map(|(_, enemy_transform, _, _)| closest_point_on_segment(missile_start, missile_end, enemy_transform.translation))
+            .or_else(|| {
+                boss_query.iter().find_map(|(_, boss_transform, _, boss)| {
+                    let parts = boss.parts.iter().filter_map(|&part| part_query.get(part).ok().map(|(boss_part, _)| (part, boss_part)));
+                    boss_hit(boss_transform, boss.kind, parts, missile_start, missile_end, MISSILE_FUSE_RADIUS)
+                        .map(|(_, center)| closest_point_on_segment(missile_start, missile_end, center))
+                })
+            });
+
+        let ground = height_field.height_at(missile_end.x, missile_end.z);
+        let Some(position) = detonation.or((missile_end.y <= ground).then_some(missile_end)) else {
+            continue;
+        };
+
+        for enemy_entity in spatial_index.enemies.query_sphere(position, MISSILE_BLAST_RADIUS) {
+            let Ok((_, enemy_transform, mut health, mut enemy)) = enemy_query.get_mut(enemy_entity) else {
+                continue;
+            };
+            if enemy_transform.translation.distance(position) > MISSILE_BLAST_RADIUS {
+                continue;
+            }
+            if let Some(destroyed) = damage_enemy(
+                &mut commands,
+                &mut game_stats,
+                enemy_entity,
+                enemy_transform,
+                &mut health,
+                &mut enemy,
+                missile.damage,
+            ) {
+                destroyed_events.write(destroyed);
+            }
+        }
+        for unit_entity in spatial_index.ground_units.query_sphere(position, MISSILE_BLAST_RADIUS + GROUND_UNIT_HIT_RADIUS) {
+            let Ok((unit_transform, mut health, unit)) = ground_query.get_mut(unit_entity) else {
+                continue;
+            };
+            if unit.hit_center(unit_transform).distance(position) > MISSILE_BLAST_RADIUS + unit.kind.hit_sphere().1 {
+                continue;
+            }
+            if let Some(destroyed) = damage_ground_unit(
+                &mut commands,
+                &mut game_stats,
+                unit_entity,
+                unit_transform,
+                &mut health,
+                unit,
+                missile.damage,
+            ) {
+                destroyed_events.write(destroyed);
+            }
+        }
+        // Every part of a boss in the blast is hit, and so is the hull if the blast reaches it
+        for (boss_entity, boss_transform, mut boss_health, mut boss) in boss_query.iter_mut() {
+            if boss_transform.translation.distance(position) > MISSILE_BLAST_RADIUS + boss.kind.bounding_radius() {
+                continue;
+            }
+            for part_entity in boss.parts.clone() {
+                let Ok((part, mut part_health)) = part_query.get_mut(part_entity) else {
+                    continue;
+                };
+                if boss_transform.transform_point(part.offset).distance(position) > MISSILE_BLAST_RADIUS + part.kind.hit_radius() {
+                    continue;
+                }
+                if let Some(destroyed) = damage_boss_part(
+                    &mut commands,
+                    &mut game_stats,
+                    part_entity,
+                    part,
+                    &mut part_health,
+                    boss_transform,
+                    &mut boss_health,
+                    &mut boss,
+                    missile.damage,
+                ) {
+                    destroyed_events.write(destroyed);
+                }
+            }
+            let hull_in_blast = boss.kind.hull_spheres().iter().any(|(offset, radius)| {
+                boss_transform.transform_point(*offset).distance(position) <= MISSILE_BLAST_RADIUS + radius
+            });
+            if hull_in_blast {
+                if let Some(destroyed) = damage_boss(
+                    &mut commands,
+                    &mut game_stats,
+                    boss_entity,
+                    boss_transform,
+                    &mut boss_health,
+                    &boss,
+                    missile.damage,
+                ) {
+                    destroyed_events.write(destroyed);
+                }
+            }
+        }
+
+        bursts.write(ParticleBurst {
+            preset: ParticlePreset::Explosion,
+            position,
+            velocity: Vec3::ZERO,
+            count: 25,
+            color: None,
+        });
+        missile_pool.release(&mut commands, missile_entity);
+    }
+}
+
+fn closest_point_on_segment(start: Vec3, end: Vec3, point: Vec3) -> Vec3 {
+    let segment = end - start;
+    let length_squared = segment.length_squared();
+    if length_squared < f32::EPSILON {
+        return start;
+    }
+    start + segment * ((point - start).dot(segment) / length_squared).clamp(0.0, 1.0)
+}
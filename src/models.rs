@@ -2,17 +2,506 @@ use bevy::prelude::*;
 use bevy::render::mesh::{Indices, PrimitiveTopology};
 use bevy::asset::RenderAssetUsages;
 use std::f32::consts::PI;
+use serde::Deserialize;
+use crate::game_state::GameState;
+use crate::ground::{GroundUnitKind, SAM_LAUNCH_ELEVATION};
+use crate::boss::{BossKind, BossPart, BossPartKind};
+use crate::enemies::Health;
+use crate::physics::InterpolatedTransform;
+
+// Radians a control surface deflects per radian per second the airframe turns, up to a limit
+const CONTROL_SURFACE_GAIN: f32 = 0.25;
+const MAX_DEFLECTION: f32 = 0.45;
+// Radians a second a radar station's dish and a boss's propellers turn
+const RADAR_DISH_SPIN: f32 = 1.5;
+const PROPELLER_SPIN: f32 = 25.0;
+// How quickly surfaces move to where they're being commanded, per second
+const CONTROL_SURFACE_RESPONSE: f32 = 10.0;
 
 pub struct ModelsPlugin;
 
 impl Plugin for ModelsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup_custom_meshes);
+        app.add_systems(Startup, (setup_aircraft_models, setup_ground_unit_models, setup_boss_models))
+            .add_systems(Update, (animate_control_surfaces, spin_radar_dishes, spin_propellers).run_if(in_state(GameState::Playing)));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum AircraftModel {
+    Player,
+    Fighter,
+    Bomber,
+    Ace,
+}
+
+// A movable panel, hinged along `hinge` through `position` and reaching back from it by `size.z`.
+// `mix` is how far it deflects with the airframe's pitch, yaw and roll rates, in that order, so
+// the two ailerons of a pair have opposite roll signs.
+struct SurfaceLayout {
+    position: Vec3,
+    size: Vec3,
+    hinge: Vec3,
+    mix: Vec3,
+}
+
+const fn surface(position: [f32; 3], size: [f32; 3], hinge: Vec3, mix: [f32; 3]) -> SurfaceLayout {
+    SurfaceLayout {
+        position: Vec3::from_array(position),
+        size: Vec3::from_array(size),
+        hinge,
+        mix: Vec3::from_array(mix),
     }
 }
 
-fn setup_custom_meshes(_meshes: ResMut<Assets<Mesh>>) {
-    // Register custom meshes here if needed
+// Elevators and rudder on the tail built by `add_tail_surfaces`
+const TAIL_SURFACES: [SurfaceLayout; 3] = [
+    surface([0.8, 0.3, 2.85], [1.2, 0.05, 0.4], Vec3::X, [-1.0, 0.0, 0.0]),
+    surface([-0.8, 0.3, 2.85], [1.2, 0.05, 0.4], Vec3::X, [-1.0, 0.0, 0.0]),
+    surface([0.0, 0.8, 2.75], [0.05, 1.2, 0.35], Vec3::Y, [0.0, -1.0, 0.0]),
+];
+
+const ELEVONS: [SurfaceLayout; 2] = [
+    surface([1.6, 0.0, 0.6], [1.6, 0.05, 0.35], Vec3::X, [-1.0, 0.0, 1.0]),
+    surface([-1.6, 0.0, 0.6], [1.6, 0.05, 0.35], Vec3::X, [-1.0, 0.0, -1.0]),
+];
+
+impl AircraftModel {
+    const ALL: [AircraftModel; 4] = [AircraftModel::Player, AircraftModel::Fighter, AircraftModel::Bomber, AircraftModel::Ace];
+
+    fn mesh(&self) -> Mesh {
+        match self {
+            AircraftModel::Player => create_detailed_aircraft_mesh(),
+            AircraftModel::Fighter => create_enemy_fighter_mesh(),
+            AircraftModel::Bomber => create_enemy_bomber_mesh(),
+            AircraftModel::Ace => create_enemy_ace_mesh(),
+        }
+    }
+
+    // The meshes are built at roughly the player's size
+    pub fn scale(&self) -> f32 {
+        match self {
+            AircraftModel::Player => 1.0,
+            AircraftModel::Fighter => 2.0,
+            AircraftModel::Bomber => 1.8,
+            AircraftModel::Ace => 1.8,
+        }
+    }
+
+    // Body and trim colours
+    fn colors(&self) -> (Color, Color) {
+        match self {
+            AircraftModel::Player => (Color::srgb(0.8, 0.2, 0.2), Color::srgb(0.95, 0.95, 0.95)),
+            AircraftModel::Fighter => (Color::srgb(0.8, 0.2, 0.2), Color::srgb(0.8, 0.2, 0.2).darker(0.2)),
+            AircraftModel::Bomber => (Color::srgb(0.4, 0.4, 0.4), Color::srgb(0.25, 0.3, 0.2)),
+            AircraftModel::Ace => (Color::srgb(0.2, 0.2, 0.8), Color::srgb(0.9, 0.8, 0.1)),
+        }
+    }
+
+    fn surfaces(&self) -> Vec<SurfaceLayout> {
+        match self {
+            AircraftModel::Player => {
+                let mut surfaces = vec![
+                    surface([2.2, 0.0, -0.8], [1.2, 0.06, 0.45], Vec3::X, [0.0, 0.0, 1.0]),
+                    surface([-2.2, 0.0, -0.8], [1.2, 0.06, 0.45], Vec3::X, [0.0, 0.0, -1.0]),
+                ];
+                surfaces.extend(TAIL_SURFACES);
+                surfaces
+            }
+            AircraftModel::Fighter => ELEVONS.into(),
+            AircraftModel::Bomber => {
+                let mut surfaces = vec![
+                    surface([3.0, 0.0, 0.0], [1.4, 0.08, 0.4], Vec3::X, [0.0, 0.0, 1.0]),
+                    surface([-3.0, 0.0, 0.0], [1.4, 0.08, 0.4], Vec3::X, [0.0, 0.0, -1.0]),
+                ];
+                surfaces.extend(TAIL_SURFACES);
+                surfaces
+            }
+            AircraftModel::Ace => {
+                let mut surfaces: Vec<SurfaceLayout> = ELEVONS.into();
+                surfaces.extend([
+                    // All-moving canards, and a rudder on each of the twin fins
+                    surface([0.75, 0.0, -1.0], [0.7, 0.04, 0.4], Vec3::X, [1.0, 0.0, 0.0]),
+                    surface([-0.75, 0.0, -1.0], [0.7, 0.04, 0.4], Vec3::X, [1.0, 0.0, 0.0]),
+                    surface([0.4, 0.6, 1.3], [0.04, 0.6, 0.3], Vec3::Y, [0.0, -1.0, 0.0]),
+                    surface([-0.4, 0.6, 1.3], [0.04, 0.6, 0.3], Vec3::Y, [0.0, -1.0, 0.0]),
+                ]);
+                surfaces
+            }
+        }
+    }
+}
+
+// Materials for the hull and the control surfaces
+#[derive(Clone)]
+pub struct Livery {
+    pub body: Handle<StandardMaterial>,
+    pub trim: Handle<StandardMaterial>,
+}
+
+struct ModelAssets {
+    hull: Handle<Mesh>,
+    surfaces: Vec<(SurfaceLayout, Handle<Mesh>)>,
+    livery: Livery,
+    formation_livery: Livery,
+}
+
+// Every aircraft model's meshes and liveries, built once at startup and shared by everything
+// spawned with them, in the same order as `AircraftModel::ALL`
+#[derive(Resource)]
+pub struct AircraftModels {
+    models: Vec<ModelAssets>,
+}
+
+impl AircraftModels {
+    pub fn new(meshes: &mut Assets<Mesh>, materials: &mut Assets<StandardMaterial>) -> Self {
+        let mut paint = |base_color: Color, metallic: f32| materials.add(StandardMaterial {
+            base_color,
+            metallic,
+            perceptual_roughness: 0.35,
+            // The procedural meshes aren't consistently wound
+            cull_mode: None,
+            double_sided: true,
+            ..default()
+        });
+        let models = AircraftModel::ALL
+            .iter()
+            .map(|model| {
+                let (body, trim) = model.colors();
+                let livery = Livery { body: paint(body, 0.7), trim: paint(trim, 0.6) };
+                // Formation members carry lighter markings
+                let formation_livery = Livery { body: livery.body.clone(), trim: paint(body.lighter(0.1), 0.6) };
+                let surfaces = model
+                    .surfaces()
+                    .into_iter()
+                    .map(|layout| {
+                        // Shifted back so the panel swings about its leading edge
+                        let panel = Mesh::from(Cuboid::from_size(layout.size)).translated_by(Vec3::Z * layout.size.z * 0.5);
+                        let panel = meshes.add(panel);
+                        (layout, panel)
+                    })
+                    .collect();
+                ModelAssets {
+                    hull: meshes.add(model.mesh()),
+                    surfaces,
+                    livery,
+                    formation_livery,
+                }
+            })
+            .collect();
+        Self { models }
+    }
+
+    pub fn livery(&self, model: AircraftModel) -> Livery {
+        self.models[model as usize].livery.clone()
+    }
+
+    pub fn formation_livery(&self, model: AircraftModel) -> Livery {
+        self.models[model as usize].formation_livery.clone()
+    }
+
+    // Builds `model` under `aircraft`: the hull, scaled to size, with its control surfaces on it
+    pub fn spawn(&self, commands: &mut Commands, aircraft: Entity, model: AircraftModel, livery: Livery) {
+        let assets = &self.models[model as usize];
+        let hull = commands.spawn((
+            Mesh3d(assets.hull.clone()),
+            MeshMaterial3d(livery.body),
+            Transform::from_scale(Vec3::splat(model.scale())),
+        )).id();
+        for (layout, panel) in &assets.surfaces {
+            let part = commands.spawn((
+                Mesh3d(panel.clone()),
+                MeshMaterial3d(livery.trim.clone()),
+                Transform::from_translation(layout.position),
+                ControlSurface {
+                    aircraft,
+                    hinge: layout.hinge,
+                    mix: layout.mix,
+                    deflection: 0.0,
+                },
+            )).id();
+            commands.entity(hull).add_child(part);
+        }
+        commands.entity(aircraft).add_child(hull);
+    }
+}
+
+pub fn setup_aircraft_models(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(AircraftModels::new(&mut meshes, &mut materials));
+}
+
+#[derive(Component)]
+pub struct ControlSurface {
+    aircraft: Entity,
+    hinge: Vec3,
+    mix: Vec3,
+    pub deflection: f32,
+}
+
+// One piece of a ground unit or boss: a simple shape, its paint, where it sits on the model, and
+// whether it turns on its own
+struct PrimitivePart {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+    transform: Transform,
+    spinner: Option<Spinner>,
+}
+
+#[derive(Clone, Copy)]
+enum Spinner {
+    RadarDish,
+    Propeller,
+}
+
+fn spawn_primitive_parts(commands: &mut Commands, parent: Entity, parts: &[PrimitivePart]) {
+    for part in parts {
+        let mut piece = commands.spawn((
+            Mesh3d(part.mesh.clone()),
+            MeshMaterial3d(part.material.clone()),
+            part.transform,
+        ));
+        match part.spinner {
+            Some(Spinner::RadarDish) => {
+                piece.insert(RadarDish);
+            }
+            Some(Spinner::Propeller) => {
+                piece.insert(Propeller);
+            }
+            None => {}
+        }
+        let piece = piece.id();
+        commands.entity(parent).add_child(piece);
+    }
+}
+
+// Every kind of ground unit, built from primitive shapes once at startup, in the same order as
+// `GroundUnitKind::ALL`. Units are built facing -Z, the way their guns point.
+#[derive(Resource)]
+pub struct GroundUnitModels {
+    units: Vec<Vec<PrimitivePart>>,
+}
+
+impl GroundUnitModels {
+    pub fn new(meshes: &mut Assets<Mesh>, materials: &mut Assets<StandardMaterial>) -> Self {
+        let mut paint = |base_color: Color| materials.add(StandardMaterial {
+            base_color,
+            perceptual_roughness: 0.8,
+            ..default()
+        });
+        let olive = paint(Color::srgb(0.32, 0.36, 0.22));
+        let steel = paint(Color::srgb(0.25, 0.26, 0.28));
+        let concrete = paint(Color::srgb(0.6, 0.6, 0.58));
+        let white = paint(Color::srgb(0.9, 0.9, 0.9));
+        let mut part = |shape: Mesh, material: &Handle<StandardMaterial>, transform: Transform| PrimitivePart {
+            mesh: meshes.add(shape),
+            material: material.clone(),
+            transform,
+            spinner: None,
+        };
+
+        // Barrels are raised this far above the horizon
+        let barrel_elevation = 0.8_f32;
+        let units = GroundUnitKind::ALL
+            .iter()
+            .map(|kind| match kind {
+                GroundUnitKind::AaGun => {
+                    let barrel = |x: f32| Transform::from_xyz(x, 2.4 + barrel_elevation.sin() * 2.0, -barrel_elevation.cos() * 2.0)
+                        .with_rotation(Quat::from_rotation_x(barrel_elevation));
+                    vec![
+                        part(Cylinder::new(2.5, 1.2).into(), &olive, Transform::from_xyz(0.0, 0.6, 0.0)),
+                        part(Cuboid::new(2.4, 1.4, 2.4).into(), &olive, Transform::from_xyz(0.0, 1.9, 0.0)),
+                        part(Cuboid::new(0.25, 0.25, 4.0).into(), &steel, barrel(-0.5)),
+                        part(Cuboid::new(0.25, 0.25, 4.0).into(), &steel, barrel(0.5)),
+                    ]
+                }
+                GroundUnitKind::SamSite => {
+                    let tube = |x: f32| Transform::from_xyz(x, 2.5, -0.5)
+                        .with_rotation(Quat::from_rotation_x(SAM_LAUNCH_ELEVATION - PI / 2.0));
+                    vec![
+                        part(Cuboid::new(5.0, 1.0, 3.5).into(), &olive, Transform::from_xyz(0.0, 0.5, 0.0)),
+                        part(Cuboid::new(3.0, 0.5, 1.0).into(), &steel, Transform::from_xyz(0.0, 1.25, 0.0)),
+                        part(Cylinder::new(0.35, 5.0).into(), &white, tube(-0.8)),
+                        part(Cylinder::new(0.35, 5.0).into(), &white, tube(0.8)),
+                    ]
+                }
+                GroundUnitKind::RadarStation => {
+                    let mut dish = part(
+                        Cuboid::new(6.0, 3.0, 0.3).into(),
+                        &white,
+                        Transform::from_xyz(0.0, 8.5, 0.0).with_rotation(Quat::from_rotation_x(-0.3)),
+                    );
+                    dish.spinner = Some(Spinner::RadarDish);
+                    vec![
+                        part(Cuboid::new(6.0, 4.0, 6.0).into(), &concrete, Transform::from_xyz(0.0, 2.0, 0.0)),
+                        part(Cylinder::new(0.3, 4.0).into(), &steel, Transform::from_xyz(0.0, 6.0, 0.0)),
+                        dish,
+                    ]
+                }
+            })
+            .collect();
+        Self { units }
+    }
+
+    // Builds `kind` under `unit`, standing on its origin
+    pub fn spawn(&self, commands: &mut Commands, unit: Entity, kind: GroundUnitKind) {
+        spawn_primitive_parts(commands, unit, &self.units[kind as usize]);
+    }
+}
+
+pub fn setup_ground_unit_models(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(GroundUnitModels::new(&mut meshes, &mut materials));
+}
+
+#[derive(Component)]
+pub struct RadarDish;
+
+pub fn spin_radar_dishes(mut dishes: Query<&mut Transform, With<RadarDish>>, time: Res<Time>) {
+    for mut transform in dishes.iter_mut() {
+        transform.rotate_y(RADAR_DISH_SPIN * time.delta_secs());
+    }
+}
+
+// Every kind of boss hull, and of the turrets and engines fitted to them, built from primitive
+// shapes once at startup in the same order as `BossKind::ALL` and `BossPartKind::ALL`. Bosses are
+// built facing -Z, and so are their turrets' guns and engines' propellers.
+#[derive(Resource)]
+pub struct BossModels {
+    hulls: Vec<Vec<PrimitivePart>>,
+    parts: Vec<Vec<PrimitivePart>>,
+}
+
+impl BossModels {
+    pub fn new(meshes: &mut Assets<Mesh>, materials: &mut Assets<StandardMaterial>) -> Self {
+        let mut paint = |base_color: Color| materials.add(StandardMaterial {
+            base_color,
+            perceptual_roughness: 0.7,
+            ..default()
+        });
+        let gunmetal = paint(Color::srgb(0.3, 0.33, 0.3));
+        let glass = paint(Color::srgb(0.2, 0.3, 0.4));
+        let canvas = paint(Color::srgb(0.72, 0.72, 0.68));
+        let crimson = paint(Color::srgb(0.5, 0.12, 0.1));
+        let steel = paint(Color::srgb(0.22, 0.23, 0.25));
+        let black = paint(Color::srgb(0.05, 0.05, 0.05));
+        let mut part = |shape: Mesh, material: &Handle<StandardMaterial>, transform: Transform| PrimitivePart {
+            mesh: meshes.add(shape),
+            material: material.clone(),
+            transform,
+            spinner: None,
+        };
+        // Cylinders are built upright; these lie along the boss
+        let lengthways = Quat::from_rotation_x(PI / 2.0);
+
+        let hulls = BossKind::ALL
+            .iter()
+            .map(|kind| match kind {
+                BossKind::Gunship => vec![
+                    part(Cylinder::new(2.5, 38.0).into(), &gunmetal, Transform::from_rotation(lengthways)),
+                    part(Sphere::new(2.5).into(), &gunmetal, Transform::from_xyz(0.0, 0.0, -19.0)),
+                    part(Sphere::new(2.5).into(), &gunmetal, Transform::from_xyz(0.0, 0.0, 19.0)),
+                    part(Sphere::new(1.4).into(), &glass, Transform::from_xyz(0.0, 1.5, -16.5)),
+                    part(Cuboid::new(46.0, 0.6, 7.0).into(), &gunmetal, Transform::from_xyz(0.0, 0.5, -2.0)),
+                    part(Cuboid::new(16.0, 0.4, 4.0).into(), &gunmetal, Transform::from_xyz(0.0, 1.0, 17.0)),
+                    part(Cuboid::new(0.5, 7.0, 5.0).into(), &gunmetal, Transform::from_xyz(0.0, 4.5, 17.0)),
+                ],
+                BossKind::Airship => vec![
+                    part(Sphere::new(1.0).into(), &canvas, Transform::from_scale(Vec3::new(9.0, 9.0, 30.0))),
+                    part(Cuboid::new(4.0, 3.0, 14.0).into(), &crimson, Transform::from_xyz(0.0, -10.0, 0.0)),
+                    part(Cuboid::new(0.4, 16.0, 7.0).into(), &crimson, Transform::from_xyz(0.0, 0.0, 26.0)),
+                    part(Cuboid::new(16.0, 0.4, 7.0).into(), &crimson, Transform::from_xyz(0.0, 0.0, 26.0)),
+                ],
+            })
+            .collect();
+
+        let parts = BossPartKind::ALL
+            .iter()
+            .map(|kind| match kind {
+                BossPartKind::Turret => vec![
+                    part(Sphere::new(1.4).into(), &steel, Transform::IDENTITY),
+                    part(Cuboid::new(0.2, 0.2, 2.6).into(), &black, Transform::from_xyz(-0.35, 0.2, -1.6)),
+                    part(Cuboid::new(0.2, 0.2, 2.6).into(), &black, Transform::from_xyz(0.35, 0.2, -1.6)),
+                ],
+                BossPartKind::Engine => {
+                    let mut propeller = part(Cuboid::new(5.0, 0.35, 0.15).into(), &black, Transform::from_xyz(0.0, 0.0, -2.7));
+                    propeller.spinner = Some(Spinner::Propeller);
+                    vec![
+                        part(Cylinder::new(1.1, 5.0).into(), &steel, Transform::from_rotation(lengthways)),
+                        part(Sphere::new(0.5).into(), &black, Transform::from_xyz(0.0, 0.0, -2.6)),
+                        propeller,
+                    ]
+                }
+            })
+            .collect();
+        Self { hulls, parts }
+    }
+
+    pub fn spawn_hull(&self, commands: &mut Commands, boss: Entity, kind: BossKind) {
+        spawn_primitive_parts(commands, boss, &self.hulls[kind as usize]);
+    }
+
+    pub fn spawn_part(&self, commands: &mut Commands, part: Entity, kind: BossPartKind) {
+        spawn_primitive_parts(commands, part, &self.parts[kind as usize]);
+    }
+}
+
+pub fn setup_boss_models(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(BossModels::new(&mut meshes, &mut materials));
+}
+
+#[derive(Component)]
+pub struct Propeller;
+
+// Propellers stop once their engine has been knocked out
+pub fn spin_propellers(
+    mut propellers: Query<(&mut Transform, &ChildOf), With<Propeller>>,
+    working_engines: Query<(), (With<BossPart>, With<Health>)>,
+    time: Res<Time>,
+) {
+    for (mut transform, child_of) in propellers.iter_mut() {
+        if working_engines.contains(child_of.parent()) {
+            transform.rotate_z(PROPELLER_SPIN * time.delta_secs());
+        }
+    }
+}
+
+// The airframe's pitch, yaw and roll rates in its own frame, from its rotation over one step
+pub fn body_rates(previous: Quat, current: Quat, delta: f32) -> Vec3 {
+    let rotation = previous.inverse() * current;
+    // Either sign of quaternion is the same rotation; take the short way round
+    let rotation = if rotation.w < 0.0 { -rotation } else { rotation };
+    rotation.to_scaled_axis() / delta
+}
+
+// Moves each control surface towards the deflection its aircraft's turn rates call for
+pub fn animate_control_surfaces(
+    mut surfaces: Query<(&mut ControlSurface, &mut Transform)>,
+    aircraft_query: Query<&InterpolatedTransform>,
+    fixed_time: Res<Time<Fixed>>,
+    time: Res<Time>,
+) {
+    let step = fixed_time.timestep().as_secs_f32();
+    let blend = 1.0 - (-CONTROL_SURFACE_RESPONSE * time.delta_secs()).exp();
+    for (mut surface, mut transform) in surfaces.iter_mut() {
+        let Ok(interpolated) = aircraft_query.get(surface.aircraft) else {
+            continue;
+        };
+        let rates = body_rates(interpolated.previous.rotation, interpolated.current.rotation, step);
+        let target = (surface.mix.dot(rates) * CONTROL_SURFACE_GAIN).clamp(-MAX_DEFLECTION, MAX_DEFLECTION);
+        surface.deflection += (target - surface.deflection) * blend;
+        transform.rotation = Quat::from_axis_angle(surface.hinge, surface.deflection);
+    }
 }
 
 pub fn create_detailed_aircraft_mesh() -> Mesh {
@@ -299,13 +788,11 @@ fn add_tail_surfaces(
     
     // Left side triangles
     indices.push(horiz_base);
-    indices.push(horiz_base + 4);
-    indices.push(horiz_base);
     indices.push(horiz_base + 5);
     indices.push(horiz_base + 4);
+    indices.push(horiz_base);
     indices.push(horiz_base + 3);
     indices.push(horiz_base + 5);
-    indices.push(horiz_base + 3);
     
     *vertex_offset += 6;
 }
@@ -313,13 +800,16 @@ fn add_tail_surfaces(
 pub fn create_terrain_chunk(
     size: f32,
     resolution: u32,
+    skirt_depth: f32,
     height_fn: impl Fn(f32, f32) -> f32,
+    color_fn: impl Fn(f32, f32) -> [f32; 4],
 ) -> Mesh {
     let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default());
     
     let mut positions = Vec::new();
     let mut normals = Vec::new();
     let mut uvs = Vec::new();
+    let mut colors = Vec::new();
     let mut indices = Vec::new();
     
     let step = size / resolution as f32;
@@ -333,6 +823,7 @@ pub fn create_terrain_chunk(
             
             positions.push([x, y, z]);
             uvs.push([i as f32 / resolution as f32, j as f32 / resolution as f32]);
+            colors.push(color_fn(x, z));
             
             // Calculate normal using neighboring points
             let dx = height_fn(x + step, z) - height_fn(x - step, z);
@@ -359,14 +850,73 @@ pub fn create_terrain_chunk(
         }
     }
     
+    // Skirt: a strip hanging down from each edge, so a neighbour with a coarser grid can't
+    // open a crack to the sky between them
+    if skirt_depth > 0.0 {
+        let edge_vertex = |side: u32, k: u32| match side {
+            0 => k,                                  // i = 0
+            1 => resolution * (resolution + 1) + k,  // i = resolution
+            2 => k * (resolution + 1),               // j = 0
+            _ => k * (resolution + 1) + resolution,  // j = resolution
+        };
+        for side in 0..4 {
+            let base = positions.len() as u32;
+            for k in 0..=resolution {
+                let top = edge_vertex(side, k) as usize;
+                let [x, y, z] = positions[top];
+                positions.push([x, y - skirt_depth, z]);
+                normals.push(normals[top]);
+                uvs.push(uvs[top]);
+                colors.push(colors[top]);
+            }
+            for k in 0..resolution {
+                let (top, next_top) = (edge_vertex(side, k), edge_vertex(side, k + 1));
+                let (bottom, next_bottom) = (base + k, base + k + 1);
+                indices.extend_from_slice(&[top, bottom, next_top, next_top, bottom, next_bottom]);
+            }
+        }
+    }
+    
     mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
     mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
     mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
     mesh.insert_indices(Indices::U32(indices));
     
     mesh
 }
 
+// A plain tapered prism standing in for a distant tree trunk
+pub fn create_low_poly_trunk_mesh(radius: f32, height: f32) -> Mesh {
+    let sides: u32 = 5;
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    let mut indices = Vec::new();
+    
+    for i in 0..sides {
+        let angle = i as f32 * 2.0 * PI / sides as f32;
+        let (sin, cos) = angle.sin_cos();
+        positions.push([radius * cos, 0.0, radius * sin]);
+        positions.push([radius * 0.7 * cos, height, radius * 0.7 * sin]);
+        normals.push([cos, 0.0, sin]);
+        normals.push([cos, 0.0, sin]);
+        uvs.push([i as f32 / sides as f32, 0.0]);
+        uvs.push([i as f32 / sides as f32, 1.0]);
+        
+        let (bottom, top) = (i * 2, i * 2 + 1);
+        let (next_bottom, next_top) = ((i + 1) % sides * 2, (i + 1) % sides * 2 + 1);
+        indices.extend_from_slice(&[bottom, top, next_bottom, next_bottom, top, next_top]);
+    }
+    
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default());
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.insert_indices(Indices::U32(indices));
+    mesh
+}
+
 pub fn create_tree_mesh(seed: u32) -> Mesh {
     let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default());
     
@@ -643,6 +1193,8 @@ pub fn create_enemy_bomber_mesh() -> Mesh {
     // Engine pods
     add_engine_pods(&mut positions, &mut normals, &mut uvs, &mut indices, &mut vertex_offset);
     
+    add_tail_surfaces(&mut positions, &mut normals, &mut uvs, &mut indices, &mut vertex_offset);
+    
     mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
     mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
     mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
@@ -652,11 +1204,37 @@ pub fn create_enemy_bomber_mesh() -> Mesh {
 }
 
 pub fn create_enemy_ace_mesh() -> Mesh {
-    // Similar to fighter but with more advanced features
-    let mesh = create_enemy_fighter_mesh();
+    // The fighter's airframe with twin canted fins; its canards are control surfaces
+    let mut mesh = create_enemy_fighter_mesh();
+    
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    let mut indices = Vec::new();
+    for side in [1.0, -1.0] {
+        let base_offset = positions.len() as u32;
+        positions.extend_from_slice(&[
+            [0.3 * side, 0.2, 0.4],   // Base front
+            [0.3 * side, 0.2, 1.4],   // Base rear
+            [0.45 * side, 0.95, 1.5], // Top rear
+            [0.45 * side, 0.95, 1.1], // Top front
+        ]);
+        for _ in 0..4 {
+            normals.push([side, 0.2, 0.0]);
+            uvs.push([0.0, 0.0]);
+        }
+        indices.extend_from_slice(&[
+            base_offset, base_offset + 1, base_offset + 2,
+            base_offset, base_offset + 2, base_offset + 3,
+        ]);
+    }
     
-    // Add canards and other advanced features
-    // (Implementation details would go here)
+    let fins = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+        .with_inserted_indices(Indices::U32(indices));
+    mesh.merge(&fins).expect("fins have the same attributes as the airframe");
     
     mesh
 }
@@ -720,8 +1298,9 @@ fn add_bomber_wings(
         [0.5, -wing_thickness/2.0, -1.0],
     ]);
     
-    for _ in 0..8 {
-        normals.push([0.0, 0.0, 0.0]); // Will be calculated
+    // Top face up, bottom face down
+    for i in 0..8 {
+        normals.push(if i < 4 { [0.0, 1.0, 0.0] } else { [0.0, -1.0, 0.0] });
         uvs.push([0.0, 0.0]);
     }
     
@@ -745,7 +1324,7 @@ fn add_bomber_wings(
         let mut pos = positions[base_offset as usize + i];
         pos[0] = -pos[0];
         positions.push(pos);
-        normals.push([0.0, 0.0, 0.0]);
+        normals.push(normals[base_offset as usize + i]);
         uvs.push([0.0, 0.0]);
     }
     
@@ -818,7 +1397,8 @@ fn add_engine_pods(
     }
 }
 
-pub fn create_volumetric_cloud_mesh(seed: u32) -> Mesh {
+// `rings` and `sectors` set how finely each puff is tessellated
+pub fn create_volumetric_cloud_mesh(seed: u32, rings: usize, sectors: usize) -> Mesh {
     let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default());
     
     let mut positions = Vec::new();
@@ -836,10 +1416,6 @@ pub fn create_volumetric_cloud_mesh(seed: u32) -> Mesh {
         let offset_z = ((seed + i as u32 * 3) % 7) as f32 - 3.5;
         let radius = 5.0 + ((seed + i as u32) % 4) as f32;
         
-        // Create low-poly sphere
-        let rings: usize = 8;
-        let sectors: usize = 12;
-        
         for r in 0..=rings {
             let phi = PI * r as f32 / rings as f32;
             let y = radius * phi.cos();
@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+
+// Seeded 2D gradient noise for terrain generation. Everything is integer hashing and plain
+// arithmetic, so the same seed gives the same world on every run.
+
+pub fn hash(seed: u32, x: i32, z: i32) -> u32 {
+    let mut h = seed
+        ^ (x as u32).wrapping_mul(0x27d4_eb2d)
+        ^ (z as u32).wrapping_mul(0x1656_67b1);
+    h = (h ^ (h >> 15)).wrapping_mul(0x85eb_ca6b);
+    h = (h ^ (h >> 13)).wrapping_mul(0xc2b2_ae35);
+    h ^ (h >> 16)
+}
+
+// A value in [0, 1) for a lattice point
+pub fn hash01(seed: u32, x: i32, z: i32) -> f32 {
+    (hash(seed, x, z) >> 8) as f32 / (1u32 << 24) as f32
+}
+
+const GRADIENTS: [Vec2; 8] = [
+    Vec2::new(1.0, 0.0),
+    Vec2::new(-1.0, 0.0),
+    Vec2::new(0.0, 1.0),
+    Vec2::new(0.0, -1.0),
+    Vec2::new(std::f32::consts::FRAC_1_SQRT_2, std::f32::consts::FRAC_1_SQRT_2),
+    Vec2::new(-std::f32::consts::FRAC_1_SQRT_2, std::f32::consts::FRAC_1_SQRT_2),
+    Vec2::new(std::f32::consts::FRAC_1_SQRT_2, -std::f32::consts::FRAC_1_SQRT_2),
+    Vec2::new(-std::f32::consts::FRAC_1_SQRT_2, -std::f32::consts::FRAC_1_SQRT_2),
+];
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+// Smooth noise in roughly [-1, 1] with features about one unit across
+pub fn gradient_noise(seed: u32, x: f32, z: f32) -> f32 {
+    let (x0, z0) = (x.floor(), z.floor());
+    let (fx, fz) = (x - x0, z - z0);
+    let (ix, iz) = (x0 as i32, z0 as i32);
+
+    let corner = |cx: i32, cz: i32| {
+        let gradient = GRADIENTS[(hash(seed, ix + cx, iz + cz) & 7) as usize];
+        gradient.dot(Vec2::new(fx - cx as f32, fz - cz as f32))
+    };
+
+    let (u, v) = (fade(fx), fade(fz));
+    let bottom = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * u;
+    let top = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * u;
+    (bottom + (top - bottom) * v) * std::f32::consts::SQRT_2
+}
+
+// Fractal noise: octaves of finer, fainter noise on top of each other, in roughly [-1, 1]
+pub fn fbm(seed: u32, x: f32, z: f32, octaves: u32) -> f32 {
+    let mut sum = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = 1.0;
+    let mut total = 0.0;
+    for octave in 0..octaves {
+        sum += gradient_noise(seed.wrapping_add(octave), x * frequency, z * frequency) * amplitude;
+        total += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    sum / total
+}
+
+// Fractal noise folded into sharp crests, in [0, 1]. Each octave is weighted by the one
+// before, so detail gathers along the ridges and the valleys between stay smooth.
+pub fn ridged(seed: u32, x: f32, z: f32, octaves: u32) -> f32 {
+    let mut sum = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = 1.0;
+    let mut total = 0.0;
+    let mut weight = 1.0;
+    for octave in 0..octaves {
+        let crest = 1.0 - gradient_noise(seed.wrapping_add(octave), x * frequency, z * frequency).abs();
+        let crest = crest * crest * weight;
+        weight = crest.clamp(0.0, 1.0);
+        sum += crest * amplitude;
+        total += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    (sum / total).clamp(0.0, 1.0)
+}
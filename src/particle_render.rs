@@ -0,0 +1,268 @@
+use std::ops::Range;
+use bevy::prelude::*;
+use bevy::asset::RenderAssetUsages;
+use bevy::core_pipeline::core_3d::Transparent3d;
+use bevy::ecs::system::{SystemParamItem, lifetimeless::{Read, SRes}};
+use bevy::pbr::{MeshPipeline, MeshPipelineKey, SetMeshViewBindGroup, ViewKeyCache};
+use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
+use bevy::render::mesh::allocator::MeshAllocator;
+use bevy::render::mesh::{Indices, MeshVertexBufferLayoutRef, PrimitiveTopology, RenderMesh, RenderMeshBufferInfo};
+use bevy::render::primitives::Aabb;
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_phase::{
+    AddRenderCommand, DrawFunctions, PhaseItem, PhaseItemExtraIndex, RenderCommand, RenderCommandResult,
+    SetItemPipeline, TrackedRenderPass, ViewSortedRenderPhases,
+};
+use bevy::render::render_resource::*;
+use bevy::render::renderer::{RenderDevice, RenderQueue};
+use bevy::render::sync_component::SyncComponentPlugin;
+use bevy::render::sync_world::RenderEntity;
+use bevy::render::view::{ExtractedView, RenderVisibleEntities};
+use bevy::render::{Extract, ExtractSchedule, Render, RenderApp, RenderSet};
+use crate::particles::ParticleEmitter;
+
+const SHADER_PATH: &str = "shaders/particles.wgsl";
+
+// Each particle is a small octahedron: no facing to work out, and it reads as a puff from any side
+const OCTAHEDRON: [Vec3; 6] = [Vec3::X, Vec3::NEG_X, Vec3::Y, Vec3::NEG_Y, Vec3::Z, Vec3::NEG_Z];
+const OCTAHEDRON_FACES: [[u16; 3]; 8] = [
+    [0, 2, 4], [4, 2, 1], [1, 2, 5], [5, 2, 0],
+    [4, 3, 0], [1, 3, 4], [5, 3, 1], [0, 3, 5],
+];
+
+// Draws every visible `ParticleEmitter` as one instanced draw of a shared unit octahedron, with
+// each particle's position, size and colour in a per-instance vertex buffer. Only the windowed
+// game adds it; headless runs simulate particles without drawing them.
+pub struct ParticleRenderPlugin;
+
+impl Plugin for ParticleRenderPlugin {
+    fn build(&self, app: &mut App) {
+        let mesh = app.world_mut().resource_mut::<Assets<Mesh>>().add(particle_mesh());
+        app.insert_resource(ParticleMesh(mesh))
+            .add_plugins((SyncComponentPlugin::<ParticleEmitter>::default(), ExtractResourcePlugin::<ParticleMesh>::default()));
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .add_render_command::<Transparent3d, DrawParticles>()
+            .init_resource::<SpecializedMeshPipelines<ParticlePipeline>>()
+            .init_resource::<ParticleInstanceBuffer>()
+            .add_systems(ExtractSchedule, extract_particle_emitters)
+            .add_systems(
+                Render,
+                (
+                    queue_particles.in_set(RenderSet::QueueMeshes),
+                    prepare_particle_instances.in_set(RenderSet::PrepareResources),
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<ParticlePipeline>();
+        }
+    }
+}
+
+fn particle_mesh() -> Mesh {
+    Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::RENDER_WORLD)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, OCTAHEDRON.map(|corner| corner.to_array()).to_vec())
+        .with_inserted_indices(Indices::U16(OCTAHEDRON_FACES.concat()))
+}
+
+#[derive(Resource, Clone, ExtractResource)]
+struct ParticleMesh(Handle<Mesh>);
+
+// The instance attributes at locations 3 and 4 in particles.wgsl: where the particle is, with its
+// size in w, then its colour
+type ParticleInstance = [Vec4; 2];
+
+// An emitter's particles as of this frame, and where they ended up in the shared instance buffer
+#[derive(Component)]
+struct ExtractedParticles {
+    instances: Vec<ParticleInstance>,
+    glow: bool,
+    center: Vec3,
+    range: Range<u32>,
+}
+
+// Every emitter's instances back to back, rewritten each frame into the same GPU buffer
+#[derive(Resource)]
+struct ParticleInstanceBuffer(RawBufferVec<ParticleInstance>);
+
+impl Default for ParticleInstanceBuffer {
+    fn default() -> Self {
+        let mut buffer = RawBufferVec::new(BufferUsages::VERTEX);
+        buffer.set_label(Some("particle_instances"));
+        Self(buffer)
+    }
+}
+
+// Emitters that are off screen or empty lose last frame's particles rather than keeping them
+fn extract_particle_emitters(
+    mut commands: Commands,
+    emitters: Extract<Query<(RenderEntity, &ViewVisibility, &ParticleEmitter, &Aabb)>>,
+) {
+    for (entity, visibility, emitter, aabb) in emitters.iter() {
+        if !visibility.get() || emitter.is_empty() {
+            commands.entity(entity).remove::<ExtractedParticles>();
+            continue;
+        }
+        let instances = emitter
+            .instances()
+            .map(|(position, size, color)| [position.extend(size), color.to_vec4()])
+            .collect();
+        commands.entity(entity).insert(ExtractedParticles {
+            instances,
+            glow: emitter.glows(),
+            center: aabb.center.into(),
+            range: 0..0,
+        });
+    }
+}
+
+fn prepare_particle_instances(
+    mut emitters: Query<&mut ExtractedParticles>,
+    mut buffer: ResMut<ParticleInstanceBuffer>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    buffer.0.clear();
+    for mut particles in emitters.iter_mut() {
+        let start = buffer.0.len() as u32;
+        for instance in &particles.instances {
+            buffer.0.push(*instance);
+        }
+        particles.range = start..buffer.0.len() as u32;
+    }
+    buffer.0.write_buffer(&render_device, &render_queue);
+}
+
+fn queue_particles(
+    draw_functions: Res<DrawFunctions<Transparent3d>>,
+    particle_pipeline: Res<ParticlePipeline>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<ParticlePipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    meshes: Res<RenderAssets<RenderMesh>>,
+    particle_mesh: Res<ParticleMesh>,
+    view_key_cache: Res<ViewKeyCache>,
+    emitters: Query<&ExtractedParticles>,
+    mut phases: ResMut<ViewSortedRenderPhases<Transparent3d>>,
+    views: Query<(&ExtractedView, &RenderVisibleEntities)>,
+) {
+    let Some(mesh) = meshes.get(&particle_mesh.0) else {
+        return;
+    };
+    let draw_particles = draw_functions.read().id::<DrawParticles>();
+
+    for (view, visible_entities) in &views {
+        let (Some(phase), Some(view_key)) = (
+            phases.get_mut(&view.retained_view_entity),
+            view_key_cache.get(&view.retained_view_entity),
+        ) else {
+            continue;
+        };
+        let rangefinder = view.rangefinder3d();
+
+        for &(entity, main_entity) in visible_entities.iter::<ParticleEmitter>() {
+            let Ok(particles) = emitters.get(entity) else {
+                continue;
+            };
+            // Glowing particles are added to what's behind them, smoke is blended over it
+            let blend = if particles.glow { MeshPipelineKey::BLEND_PREMULTIPLIED_ALPHA } else { MeshPipelineKey::BLEND_ALPHA };
+            let key = *view_key | MeshPipelineKey::from_primitive_topology(mesh.primitive_topology()) | blend;
+            let Ok(pipeline) = pipelines.specialize(&pipeline_cache, &particle_pipeline, key, &mesh.layout) else {
+                continue;
+            };
+            phase.add(Transparent3d {
+                entity: (entity, main_entity),
+                pipeline,
+                draw_function: draw_particles,
+                distance: rangefinder.distance_translation(&particles.center),
+                batch_range: 0..1,
+                extra_index: PhaseItemExtraIndex::None,
+                indexed: true,
+            });
+        }
+    }
+}
+
+#[derive(Resource)]
+struct ParticlePipeline {
+    shader: Handle<Shader>,
+    mesh_pipeline: MeshPipeline,
+}
+
+impl FromWorld for ParticlePipeline {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            shader: world.load_asset(SHADER_PATH),
+            mesh_pipeline: world.resource::<MeshPipeline>().clone(),
+        }
+    }
+}
+
+impl SpecializedMeshPipeline for ParticlePipeline {
+    type Key = MeshPipelineKey;
+
+    fn specialize(
+        &self,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayoutRef,
+    ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        let mut descriptor = self.mesh_pipeline.specialize(key, layout)?;
+        descriptor.label = Some("particle_pipeline".into());
+        // Particles have no mesh transform of their own, so only the view is bound
+        descriptor.layout.truncate(1);
+        descriptor.vertex.shader = self.shader.clone();
+        descriptor.vertex.buffers.push(VertexBufferLayout {
+            array_stride: size_of::<ParticleInstance>() as u64,
+            step_mode: VertexStepMode::Instance,
+            attributes: vec![
+                VertexAttribute { format: VertexFormat::Float32x4, offset: 0, shader_location: 3 },
+                VertexAttribute { format: VertexFormat::Float32x4, offset: VertexFormat::Float32x4.size(), shader_location: 4 },
+            ],
+        });
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment.shader = self.shader.clone();
+        }
+        Ok(descriptor)
+    }
+}
+
+type DrawParticles = (SetItemPipeline, SetMeshViewBindGroup<0>, DrawParticleInstances);
+
+struct DrawParticleInstances;
+
+impl<P: PhaseItem> RenderCommand<P> for DrawParticleInstances {
+    type Param = (SRes<RenderAssets<RenderMesh>>, SRes<MeshAllocator>, SRes<ParticleMesh>, SRes<ParticleInstanceBuffer>);
+    type ViewQuery = ();
+    type ItemQuery = Read<ExtractedParticles>;
+
+    fn render<'w>(
+        _item: &P,
+        _view: (),
+        particles: Option<&'w ExtractedParticles>,
+        (meshes, mesh_allocator, particle_mesh, instances): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let mesh_allocator = mesh_allocator.into_inner();
+        let mesh_id = particle_mesh.into_inner().0.id();
+        let (Some(particles), Some(mesh), Some(instance_buffer)) = (particles, meshes.into_inner().get(mesh_id), instances.into_inner().0.buffer()) else {
+            return RenderCommandResult::Skip;
+        };
+        let (Some(vertices), Some(indices)) = (mesh_allocator.mesh_vertex_slice(&mesh_id), mesh_allocator.mesh_index_slice(&mesh_id)) else {
+            return RenderCommandResult::Skip;
+        };
+        let RenderMeshBufferInfo::Indexed { index_format, count } = &mesh.buffer_info else {
+            return RenderCommandResult::Skip;
+        };
+
+        pass.set_vertex_buffer(0, vertices.buffer.slice(..));
+        pass.set_vertex_buffer(1, instance_buffer.slice(..));
+        pass.set_index_buffer(indices.buffer.slice(..), 0, *index_format);
+        pass.draw_indexed(indices.range.start..indices.range.start + count, vertices.range.start as i32, particles.range.clone());
+        RenderCommandResult::Success
+    }
+}
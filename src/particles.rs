@@ -0,0 +1,403 @@
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use bevy::render::view::{VisibilityClass, add_visibility_class};
+use crate::enemies::{Crashing, DamageState, Health};
+use crate::missiles::Missile;
+use crate::ground::SamMissile;
+use crate::boss::{Boss, BossPart, BossPartKind};
+use crate::physics::InterpolatedTransform;
+use crate::game_state::GameRng;
+use crate::replay::PlayerInput;
+use crate::{Aircraft, GameEntity};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParticlePreset {
+    BalloonPop,
+    Explosion,
+    EngineExhaust,
+    Smoke,
+    MuzzleFlash,
+    MissileTrail,
+    Fire,
+}
+
+struct PresetSettings {
+    lifetime: f32,
+    // Random speed added in any direction on top of the emitted velocity
+    spread: f32,
+    // Negative rises
+    gravity: f32,
+    drag: f32,
+    start_size: f32,
+    end_size: f32,
+    // Used when an emission doesn't give its own colour
+    color: Color,
+    // What the colour turns to by the end of a particle's life; it also fades out
+    end_color: Option<Color>,
+    max_particles: usize,
+    // Added on top of what's behind it rather than blended over it
+    glow: bool,
+}
+
+impl ParticlePreset {
+    fn settings(&self) -> PresetSettings {
+        match self {
+            ParticlePreset::BalloonPop => PresetSettings {
+                lifetime: 1.0,
+                spread: 14.0,
+                gravity: 9.8,
+                drag: 0.5,
+                start_size: 0.35,
+                end_size: 0.05,
+                color: Color::srgb(0.2, 0.8, 0.2),
+                end_color: None,
+                max_particles: 600,
+                glow: true,
+            },
+            ParticlePreset::Explosion => PresetSettings {
+                lifetime: 1.2,
+                spread: 20.0,
+                gravity: 4.0,
+                drag: 1.5,
+                start_size: 0.7,
+                end_size: 2.0,
+                color: Color::srgb(1.0, 0.5, 0.0),
+                end_color: Some(Color::srgb(0.15, 0.12, 0.1)),
+                max_particles: 800,
+                glow: true,
+            },
+            ParticlePreset::EngineExhaust => PresetSettings {
+                lifetime: 0.5,
+                spread: 1.5,
+                gravity: 0.0,
+                drag: 0.0,
+                start_size: 0.4,
+                end_size: 0.1,
+                color: Color::srgb(0.2, 0.5, 0.9),
+                end_color: None,
+                max_particles: 400,
+                glow: true,
+            },
+            ParticlePreset::Smoke => PresetSettings {
+                lifetime: 2.5,
+                spread: 2.0,
+                gravity: -1.5,
+                drag: 0.3,
+                start_size: 0.8,
+                end_size: 3.5,
+                color: Color::srgba(0.25, 0.25, 0.25, 0.6),
+                end_color: Some(Color::srgba(0.5, 0.5, 0.5, 0.6)),
+                max_particles: 500,
+                glow: false,
+            },
+            ParticlePreset::MuzzleFlash => PresetSettings {
+                lifetime: 0.08,
+                spread: 6.0,
+                gravity: 0.0,
+                drag: 0.0,
+                start_size: 0.8,
+                end_size: 0.2,
+                color: Color::srgb(1.0, 0.95, 0.6),
+                end_color: Some(Color::srgb(1.0, 0.5, 0.1)),
+                max_particles: 100,
+                glow: true,
+            },
+            ParticlePreset::MissileTrail => PresetSettings {
+                lifetime: 1.5,
+                spread: 0.6,
+                gravity: -0.5,
+                drag: 0.5,
+                start_size: 0.4,
+                end_size: 1.8,
+                color: Color::srgba(0.9, 0.9, 0.9, 0.7),
+                end_color: Some(Color::srgba(0.6, 0.6, 0.6, 0.7)),
+                max_particles: 1200,
+                glow: false,
+            },
+            ParticlePreset::Fire => PresetSettings {
+                lifetime: 0.6,
+                spread: 1.5,
+                gravity: -3.0,
+                drag: 1.0,
+                start_size: 1.0,
+                end_size: 0.3,
+                color: Color::srgb(1.0, 0.65, 0.15),
+                end_color: Some(Color::srgb(0.6, 0.1, 0.0)),
+                max_particles: 600,
+                glow: true,
+            },
+        }
+    }
+}
+
+struct Particle {
+    position: Vec3,
+    velocity: Vec3,
+    age: f32,
+    color: LinearRgba,
+}
+
+// Asks the emitter for `preset` to throw out `count` particles at `position`
+#[derive(Event, Clone, Copy)]
+pub struct ParticleBurst {
+    pub preset: ParticlePreset,
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub count: usize,
+    pub color: Option<Color>,
+}
+
+// An emitter owns the particles of one preset, kept in world space, and is drawn as one instanced
+// draw of a shared octahedron (see particle_render.rs), so a busy dogfight is a handful of draws
+// rather than an entity, mesh and material per spark. Bursts get a short-lived emitter each;
+// whatever trails particles for a while (an engine, a burning wreck, a missile) owns its own.
+#[derive(Component)]
+#[require(Transform, Visibility, VisibilityClass, Aabb)]
+#[component(on_add = add_visibility_class::<ParticleEmitter>)]
+pub struct ParticleEmitter {
+    pub preset: ParticlePreset,
+    particles: Vec<Particle>,
+}
+
+// The aircraft, missile or boss part an emitter belongs to. It isn't despawned with its source,
+// so a trail doesn't vanish along with whatever left it; once the source is gone the emitter is
+// despawned when its last particle burns out.
+#[derive(Component)]
+#[relationship(relationship_target = ParticleEmitters)]
+pub struct EmitterOf(pub Entity);
+
+#[derive(Component)]
+#[relationship_target(relationship = EmitterOf)]
+pub struct ParticleEmitters(Vec<Entity>);
+
+impl ParticleEmitter {
+    pub fn new(preset: ParticlePreset) -> Self {
+        Self { preset, particles: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    // Glowing presets are added on top of what's behind them rather than blended over it
+    pub fn glows(&self) -> bool {
+        self.preset.settings().glow
+    }
+
+    pub fn emit(&mut self, rng: &mut fastrand::Rng, position: Vec3, velocity: Vec3, count: usize, color: Option<Color>) {
+        let settings = self.preset.settings();
+        let color = color.unwrap_or(settings.color).to_linear();
+        let room = settings.max_particles.saturating_sub(self.particles.len());
+        for _ in 0..count.min(room) {
+            // A random direction and speed
+            let direction = Vec3::new(rng.f32() - 0.5, rng.f32() - 0.5, rng.f32() - 0.5).normalize_or_zero();
+            self.particles.push(Particle {
+                position,
+                velocity: velocity + direction * settings.spread * rng.f32(),
+                // Staggered a little so a burst doesn't vanish all at once
+                age: rng.f32() * settings.lifetime * 0.2,
+                color,
+            });
+        }
+    }
+
+    // Where each particle is drawn, how big and in what colour, given how far through its life it is
+    pub fn instances(&self) -> impl Iterator<Item = (Vec3, f32, LinearRgba)> + '_ {
+        let settings = self.preset.settings();
+        let end_color = settings.end_color.map(|color| color.to_linear());
+        self.particles.iter().map(move |particle| {
+            let t = (particle.age / settings.lifetime).clamp(0.0, 1.0);
+            let size = settings.start_size + (settings.end_size - settings.start_size) * t;
+            let mut color = match end_color {
+                Some(end) => particle.color.mix(&end, t),
+                None => particle.color,
+            };
+            color.alpha *= 1.0 - t;
+            (particle.position, size, color)
+        })
+    }
+}
+
+pub fn emit_particle_bursts(
+    mut commands: Commands,
+    mut bursts: EventReader<ParticleBurst>,
+    mut rng: ResMut<GameRng>,
+) {
+    for burst in bursts.read() {
+        let mut emitter = ParticleEmitter::new(burst.preset);
+        emitter.emit(&mut rng.effects, burst.position, burst.velocity, burst.count, burst.color);
+        commands.spawn((emitter, GameEntity));
+    }
+}
+
+// Hands `emit` the emitter for `preset` that `source` owns, spawning one for it if it has none
+// and `emit` gave it anything to hold
+fn emit_from(
+    commands: &mut Commands,
+    emitters: &mut Query<&mut ParticleEmitter>,
+    source: Entity,
+    owned: Option<&ParticleEmitters>,
+    preset: ParticlePreset,
+    emit: impl FnOnce(&mut ParticleEmitter),
+) {
+    let existing = owned
+        .into_iter()
+        .flat_map(|owned| owned.iter())
+        .find(|&entity| emitters.get(entity).is_ok_and(|emitter| emitter.preset == preset));
+    if let Some(mut emitter) = existing.and_then(|entity| emitters.get_mut(entity).ok()) {
+        emit(&mut emitter);
+        return;
+    }
+
+    let mut emitter = ParticleEmitter::new(preset);
+    emit(&mut emitter);
+    if !emitter.is_empty() {
+        commands.spawn((emitter, EmitterOf(source), GameEntity));
+    }
+}
+
+// Both engines trail exhaust, hotter and denser on the afterburner. It follows the player's input
+// rather than the keyboard, so a replay shows the throttle that was recorded.
+pub fn emit_engine_exhaust(
+    mut commands: Commands,
+    mut emitters: Query<&mut ParticleEmitter>,
+    aircraft_query: Query<(Entity, &Transform, Option<&ParticleEmitters>), With<Aircraft>>,
+    input: Res<PlayerInput>,
+    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+) {
+    // Orange when boosting, bright blue at speed, blue otherwise
+    let (color, rate) = if input.boost {
+        (Color::srgb(1.0, 0.4, 0.1), 50.0)
+    } else if input.throttle_up {
+        (Color::srgb(0.4, 0.7, 1.0), 30.0)
+    } else {
+        (Color::srgb(0.2, 0.5, 0.9), 20.0)
+    };
+
+    for (entity, transform, owned) in aircraft_query.iter() {
+        emit_from(&mut commands, &mut emitters, entity, owned, ParticlePreset::EngineExhaust, |emitter| {
+            for offset in [Vec3::new(-3.0, -0.5, 3.0), Vec3::new(3.0, -0.5, 3.0)] {
+                let count = random_count(&mut rng.effects, rate * time.delta_secs());
+                emitter.emit(&mut rng.effects, transform.translation + transform.rotation * offset, Vec3::ZERO, count, Some(color));
+            }
+        });
+    }
+}
+
+// Damaged aircraft trail smoke, thicker the worse the damage; critically damaged ones, wrecks
+// on their way down and bosses' knocked-out engines are on fire as well
+pub fn emit_damage_trails(
+    mut commands: Commands,
+    mut emitters: Query<&mut ParticleEmitter>,
+    // A boss's parts are placed relative to the boss, so they're handled separately
+    aircraft_query: Query<(Entity, &Transform, &Health, Option<&ParticleEmitters>), Without<BossPart>>,
+    wreck_query: Query<(Entity, &Transform, Option<&ParticleEmitters>), With<Crashing>>,
+    engine_query: Query<(Entity, &BossPart, Option<&ParticleEmitters>), Without<Health>>,
+    boss_query: Query<&Transform, With<Boss>>,
+    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+) {
+    let burning_engines = engine_query.iter().filter_map(|(entity, part, owned)| {
+        let boss_transform = boss_query.get(part.boss).ok()?;
+        (part.kind == BossPartKind::Engine).then(|| (entity, owned, boss_transform.transform_point(part.offset), DamageState::Critical))
+    });
+    let trails: Vec<(Entity, Option<&ParticleEmitters>, Vec3, DamageState)> = aircraft_query
+        .iter()
+        .map(|(entity, transform, health, owned)| (entity, owned, transform, DamageState::of(health)))
+        .chain(wreck_query.iter().map(|(entity, transform, owned)| (entity, owned, transform, DamageState::Critical)))
+        .map(|(entity, owned, transform, state)| (entity, owned, transform.translation + transform.back() * 2.0, state))
+        .chain(burning_engines)
+        .collect();
+
+    for (entity, owned, tail, state) in trails {
+        for (preset, rate) in [(ParticlePreset::Smoke, state.smoke_rate()), (ParticlePreset::Fire, state.fire_rate())] {
+            let count = random_count(&mut rng.effects, rate * time.delta_secs());
+            emit_from(&mut commands, &mut emitters, entity, owned, preset, |emitter| {
+                emitter.emit(&mut rng.effects, tail, Vec3::ZERO, count, None);
+            });
+        }
+    }
+}
+
+// Missiles, the player's and SAMs alike, leave a smoke trail, spread along the path flown since
+// the last frame so fast ones don't leave gaps, and burn bright while they still have fuel
+pub fn emit_missile_smoke(
+    mut commands: Commands,
+    mut emitters: Query<&mut ParticleEmitter>,
+    missile_query: Query<(Entity, &Transform, &InterpolatedTransform, &Missile, Option<&ParticleEmitters>)>,
+    sam_query: Query<(Entity, &Transform, &InterpolatedTransform, &SamMissile, Option<&ParticleEmitters>)>,
+    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+) {
+    let missiles: Vec<(Entity, &Transform, &InterpolatedTransform, f32, Option<&ParticleEmitters>)> = missile_query
+        .iter()
+        .map(|(entity, transform, interpolated, missile, owned)| (entity, transform, interpolated, missile.fuel, owned))
+        .chain(sam_query.iter().map(|(entity, transform, interpolated, missile, owned)| (entity, transform, interpolated, missile.fuel, owned)))
+        .collect();
+    for (entity, transform, interpolated, fuel, owned) in missiles {
+        let tail = transform.back() * 1.5;
+        emit_from(&mut commands, &mut emitters, entity, owned, ParticlePreset::MissileTrail, |emitter| {
+            for _ in 0..random_count(&mut rng.effects, 90.0 * time.delta_secs()) {
+                let along = interpolated.previous.translation.lerp(interpolated.current.translation, rng.effects.f32());
+                emitter.emit(&mut rng.effects, along + tail, Vec3::ZERO, 1, None);
+            }
+        });
+        if fuel > 0.0 {
+            let count = random_count(&mut rng.effects, 40.0 * time.delta_secs());
+            emit_from(&mut commands, &mut emitters, entity, owned, ParticlePreset::EngineExhaust, |emitter| {
+                emitter.emit(&mut rng.effects, transform.translation + tail, Vec3::ZERO, count, Some(Color::srgb(1.0, 0.6, 0.2)));
+            });
+        }
+    }
+}
+
+// Whole particles for an expected fractional count, so low rates still emit at the right average
+fn random_count(rng: &mut fastrand::Rng, expected: f32) -> usize {
+    (expected + rng.f32()).floor() as usize
+}
+
+// Ages and moves every particle, then fits the emitter's bounds around what's left so it's culled
+// like any other mesh. Empty emitters are hidden, or despawned once nothing owns them any more.
+pub fn update_particle_emitters(
+    mut commands: Commands,
+    mut emitters: Query<(Entity, &mut ParticleEmitter, &mut Aabb, &mut Visibility, Has<EmitterOf>)>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_secs();
+
+    for (entity, mut emitter, mut aabb, mut visibility, owned) in emitters.iter_mut() {
+        let settings = emitter.preset.settings();
+        emitter.particles.retain_mut(|particle| {
+            particle.age += delta;
+            particle.velocity.y -= settings.gravity * delta;
+            particle.velocity *= 1.0 - (settings.drag * delta).min(1.0);
+            particle.position += particle.velocity * delta;
+            particle.age < settings.lifetime
+        });
+
+        if emitter.is_empty() {
+            if !owned {
+                commands.entity(entity).despawn();
+            } else if *visibility != Visibility::Hidden {
+                *visibility = Visibility::Hidden;
+            }
+            continue;
+        }
+        if *visibility != Visibility::Inherited {
+            *visibility = Visibility::Inherited;
+        }
+
+        // Particles are kept in world space and the emitter's transform is left at the origin,
+        // so the bounds are world space too
+        let (min, max) = emitter.particles.iter().fold((Vec3::MAX, Vec3::MIN), |(min, max), particle| {
+            (min.min(particle.position), max.max(particle.position))
+        });
+        let size = Vec3::splat(settings.start_size.max(settings.end_size));
+        *aabb = Aabb::from_min_max(min - size, max + size);
+    }
+}
@@ -0,0 +1,65 @@
+use bevy::prelude::*;
+
+// Gameplay runs in FixedUpdate at this rate however fast frames are drawn
+pub const SIMULATION_HZ: f64 = 60.0;
+
+// The simulated pose of a moving entity after the last two fixed steps. Between steps its
+// Transform holds a blend of the two so motion stays smooth at any frame rate.
+#[derive(Component, Clone, Copy)]
+pub struct InterpolatedTransform {
+    pub previous: Transform,
+    pub current: Transform,
+}
+
+impl InterpolatedTransform {
+    pub fn new(transform: Transform) -> Self {
+        Self {
+            previous: transform,
+            current: transform,
+        }
+    }
+}
+
+// Before the fixed steps of a frame: put back the simulated pose so gameplay never sees a blended one
+pub fn restore_simulated_transforms(mut query: Query<(&mut Transform, &InterpolatedTransform)>) {
+    for (mut transform, interpolated) in query.iter_mut() {
+        *transform = interpolated.current;
+    }
+}
+
+// After every fixed step
+pub fn record_simulated_transforms(mut query: Query<(&Transform, &mut InterpolatedTransform)>) {
+    for (transform, mut interpolated) in query.iter_mut() {
+        interpolated.previous = interpolated.current;
+        interpolated.current = *transform;
+    }
+}
+
+// After the fixed steps of a frame: draw each entity part way between its last two simulated poses
+pub fn interpolate_transforms(
+    fixed_time: Res<Time<Fixed>>,
+    mut query: Query<(&mut Transform, &InterpolatedTransform)>,
+) {
+    let t = fixed_time.overstep_fraction();
+    for (mut transform, interpolated) in query.iter_mut() {
+        let (previous, current) = (interpolated.previous, interpolated.current);
+        *transform = Transform {
+            translation: previous.translation.lerp(current.translation, t),
+            rotation: previous.rotation.slerp(current.rotation, t),
+            scale: previous.scale.lerp(current.scale, t),
+        };
+    }
+}
+
+// Whether something moving from `start` to `end` during a step passed within `radius` of `center`.
+// Checking the whole path rather than the end point stops fast bullets skipping through hitboxes.
+pub fn segment_hits_sphere(start: Vec3, end: Vec3, center: Vec3, radius: f32) -> bool {
+    let segment = end - start;
+    let length_squared = segment.length_squared();
+    let t = if length_squared > 0.0 {
+        ((center - start).dot(segment) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (start + segment * t).distance_squared(center) < radius * radius
+}
@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use crate::GameEntity;
+
+// What a full pool does when asked for another entity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    // Spawn a new entity; it joins the pool when released
+    Grow,
+    // Take back the entity that has been out the longest
+    RecycleOldest,
+    // Spawn nothing
+    Skip,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PoolConfig {
+    // Entities spawned hidden at the start of a run, and the most the pool hands out unless it can grow
+    pub size: usize,
+    pub overflow: Overflow,
+}
+
+// A short-lived kind of entity that's recycled instead of spawned and despawned
+pub trait Poolable: Component {
+    const POOL: PoolConfig;
+}
+
+// Entities of one kind, either out in the world or hidden waiting to be reused. Released
+// entities lose their `T` component, so the systems that move and expire them skip them,
+// and are hidden; the next `spawn` inserts a fresh bundle over whatever they still carry.
+#[derive(Resource)]
+pub struct EntityPool<T: Poolable> {
+    pub config: PoolConfig,
+    free: Vec<Entity>,
+    // Oldest first
+    active: VecDeque<Entity>,
+    _kind: PhantomData<T>,
+}
+
+impl<T: Poolable> Default for EntityPool<T> {
+    fn default() -> Self {
+        Self::new(T::POOL)
+    }
+}
+
+impl<T: Poolable> EntityPool<T> {
+    pub fn new(config: PoolConfig) -> Self {
+        Self {
+            config,
+            free: Vec::new(),
+            active: VecDeque::new(),
+            _kind: PhantomData,
+        }
+    }
+
+    pub fn active(&self) -> usize {
+        self.active.len()
+    }
+
+    pub fn free(&self) -> usize {
+        self.free.len()
+    }
+
+    // Forgets the last run's entities, which have been despawned with the rest of the world,
+    // and spawns a fresh set of hidden ones
+    pub fn reset(&mut self, commands: &mut Commands) {
+        self.active.clear();
+        self.free.clear();
+        for _ in 0..self.config.size {
+            self.free.push(commands.spawn((Transform::default(), Visibility::Hidden, GameEntity)).id());
+        }
+    }
+
+    // Puts `bundle` on a pooled entity. The bundle must include `T`. Returns None when the pool
+    // is full and set to skip.
+    pub fn spawn(&mut self, commands: &mut Commands, bundle: impl Bundle) -> Option<Entity> {
+        let entity = if let Some(entity) = self.free.pop() {
+            commands.entity(entity).insert((bundle, Visibility::Inherited));
+            entity
+        } else if self.active.len() < self.config.size || self.config.overflow == Overflow::Grow {
+            commands.spawn((bundle, GameEntity)).id()
+        } else if self.config.overflow == Overflow::RecycleOldest {
+            let entity = self.active.pop_front()?;
+            commands.entity(entity).insert(bundle);
+            entity
+        } else {
+            return None;
+        };
+        self.active.push_back(entity);
+        Some(entity)
+    }
+
+    // Hides `entity` for reuse. Releasing one that's already back in the pool, e.g. a bullet that
+    // expired and hit something in the same step, does nothing; one spawned without the pool is
+    // despawned.
+    pub fn release(&mut self, commands: &mut Commands, entity: Entity) {
+        if let Some(index) = self.active.iter().position(|active| *active == entity) {
+            self.active.remove(index);
+            commands.entity(entity).remove::<T>().insert(Visibility::Hidden);
+            self.free.push(entity);
+        } else if !self.free.contains(&entity) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+pub fn reset_pool<T: Poolable>(mut commands: Commands, mut pool: ResMut<EntityPool<T>>) {
+    pool.reset(&mut commands);
+}
@@ -1,5 +1,9 @@
 use bevy::prelude::*;
-use crate::{Aircraft, GameEntity, game_state::GameStats, FlightCamera};
+use crate::physics::InterpolatedTransform;
+use crate::spatial::SpatialIndex;
+use crate::terrain::TerrainHeightField;
+use crate::weapons::{Loadout, Projectile};
+use crate::{Aircraft, GameEntity, game_state::{GameStats, GameRng, RunClock, RunTimers}, FlightCamera};
 
 #[derive(Component)]
 pub struct PowerUp {
@@ -16,7 +20,9 @@ pub enum PowerUpType {
     HealthPack,
     EnergyRecharge,
     TripleShot,
+    // Ammo pickups: missiles for the missile launcher, and a crate that tops up every secondary
     HomingMissiles,
+    AmmoCrate,
 }
 
 #[derive(Component)]
@@ -25,21 +31,16 @@ pub struct PowerUpEffect {
     pub remaining: f32,
 }
 
+// Power-ups that change the aircraft itself; weapon power-ups change the `Loadout`'s stats instead
 #[derive(Resource, Default)]
 pub struct ActivePowerUps {
-    pub rapid_fire: bool,
     pub shield: bool,
-    pub triple_shot: bool,
-    pub homing_missiles: bool,
     pub speed_multiplier: f32,
 }
 
 impl ActivePowerUps {
     pub fn reset(&mut self) {
-        self.rapid_fire = false;
         self.shield = false;
-        self.triple_shot = false;
-        self.homing_missiles = false;
         self.speed_multiplier = 1.0;
     }
 }
@@ -51,37 +52,39 @@ pub fn spawn_powerups_system(
     player_query: Query<&Transform, With<Aircraft>>,
     powerups: Query<Entity, With<PowerUp>>,
     time: Res<Time>,
-    mut spawn_timer: Local<f32>,
+    mut run_timers: ResMut<RunTimers>,
+    mut rng: ResMut<GameRng>,
+    terrain: Res<TerrainHeightField>,
 ) {
     let powerup_count = powerups.iter().count();
     let max_powerups = 3;
     
-    *spawn_timer += time.delta_secs();
+    run_timers.powerup_spawn += time.delta_secs();
     
-    if powerup_count < max_powerups && *spawn_timer > 10.0 {
-        *spawn_timer = 0.0;
+    if powerup_count < max_powerups && run_timers.powerup_spawn > 10.0 {
+        run_timers.powerup_spawn = 0.0;
         
         if let Ok(player_transform) = player_query.single() {
             // Spawn powerup at distance from player
-            let spawn_distance = 100.0 + fastrand::f32() * 150.0;
-            let angle = fastrand::f32() * std::f32::consts::TAU;
-            let height = 30.0 + fastrand::f32() * 80.0;
+            let spawn_distance = 100.0 + rng.spawns.f32() * 150.0;
+            let angle = rng.spawns.f32() * std::f32::consts::TAU;
+            let height = 30.0 + rng.spawns.f32() * 80.0;
             
-            let position = Vec3::new(
-                player_transform.translation.x + angle.cos() * spawn_distance,
-                height,
-                player_transform.translation.z + angle.sin() * spawn_distance,
-            );
+            let x = player_transform.translation.x + angle.cos() * spawn_distance;
+            let z = player_transform.translation.z + angle.sin() * spawn_distance;
+            // Height is measured from the ground below
+            let position = Vec3::new(x, terrain.height_at(x, z) + height, z);
             
             // Random powerup type
-            let power_type = match fastrand::u8(0..7) {
+            let power_type = match rng.spawns.u8(0..8) {
                 0 => PowerUpType::RapidFire,
                 1 => PowerUpType::Shield,
                 2 => PowerUpType::SpeedBoost,
                 3 => PowerUpType::HealthPack,
                 4 => PowerUpType::EnergyRecharge,
                 5 => PowerUpType::TripleShot,
-                _ => PowerUpType::HomingMissiles,
+                6 => PowerUpType::HomingMissiles,
+                _ => PowerUpType::AmmoCrate,
             };
             
             let (color, emissive_color) = match power_type {
@@ -92,6 +95,7 @@ pub fn spawn_powerups_system(
                 PowerUpType::EnergyRecharge => (Color::srgb(0.5, 0.0, 1.0), Color::srgb(0.5, 0.0, 1.0)),
                 PowerUpType::TripleShot => (Color::srgb(1.0, 0.0, 1.0), Color::srgb(1.0, 0.0, 1.0)),
                 PowerUpType::HomingMissiles => (Color::srgb(1.0, 0.0, 0.0), Color::srgb(1.0, 0.0, 0.0)),
+                PowerUpType::AmmoCrate => (Color::srgb(0.6, 0.6, 0.3), Color::srgb(0.5, 0.5, 0.2)),
             };
             
             // Spawn powerup entity
@@ -101,8 +105,9 @@ pub fn spawn_powerups_system(
                 PowerUp {
                     power_type,
                     lifetime: 30.0,
-                    bob_phase: fastrand::f32() * std::f32::consts::TAU,
+                    bob_phase: rng.spawns.f32() * std::f32::consts::TAU,
                 },
+                InterpolatedTransform::new(Transform::from_translation(position)),
                 GameEntity,
             )).id();
             
@@ -141,9 +146,10 @@ pub fn spawn_powerups_system(
 pub fn animate_powerups(
     mut powerups: Query<(&mut Transform, &PowerUp)>,
     time: Res<Time>,
+    run_clock: Res<RunClock>,
 ) {
     for (mut transform, powerup) in powerups.iter_mut() {
-        let elapsed = time.elapsed_secs();
+        let elapsed = run_clock.elapsed;
         
         // Rotation
         transform.rotate_y(time.delta_secs() * 2.0);
@@ -163,13 +169,18 @@ pub fn animate_powerups(
 pub fn collect_powerups_system(
     mut commands: Commands,
     mut player_query: Query<(&Transform, &mut crate::enemies::Health, Entity), With<crate::Aircraft>>,
-    powerups_query: Query<(Entity, &Transform, &PowerUp)>,
+    powerups_query: Query<(&Transform, &PowerUp)>,
     mut active_powerups: ResMut<ActivePowerUps>,
+    mut loadout: ResMut<Loadout>,
     mut game_stats: ResMut<GameStats>,
     mut camera_query: Query<&mut FlightCamera>,
+    spatial_index: Res<SpatialIndex>,
 ) {
     if let Ok((player_transform, mut player_health, player_entity)) = player_query.single_mut() {
-        for (powerup_entity, powerup_transform, powerup) in powerups_query.iter() {
+        for powerup_entity in spatial_index.powerups.query_sphere(player_transform.translation, 8.0) {
+            let Ok((powerup_transform, powerup)) = powerups_query.get(powerup_entity) else {
+                continue;
+            };
             let distance = player_transform.translation.distance(powerup_transform.translation);
             
             if distance < 8.0 {
@@ -183,6 +194,14 @@ pub fn collect_powerups_system(
                         // Energy recharge handled in aircraft systems
                         game_stats.score += 50;
                     }
+                    PowerUpType::HomingMissiles => {
+                        loadout.refill(Projectile::Missile, 3);
+                        game_stats.score += 50;
+                    }
+                    PowerUpType::AmmoCrate => {
+                        loadout.refill_all();
+                        game_stats.score += 50;
+                    }
                     _ => {
                         // Duration-based powerups
                         let duration = match powerup.power_type {
@@ -190,7 +209,6 @@ pub fn collect_powerups_system(
                             PowerUpType::Shield => 15.0,
                             PowerUpType::SpeedBoost => 8.0,
                             PowerUpType::TripleShot => 12.0,
-                            PowerUpType::HomingMissiles => 20.0,
                             _ => 10.0,
                         };
                         
@@ -202,13 +220,10 @@ pub fn collect_powerups_system(
                             },
                         ));
                         
-                        // Update active powerups
+                        // Update active powerups; weapon power-ups apply through their effect
                         match powerup.power_type {
-                            PowerUpType::RapidFire => active_powerups.rapid_fire = true,
                             PowerUpType::Shield => active_powerups.shield = true,
                             PowerUpType::SpeedBoost => active_powerups.speed_multiplier = 2.0,
-                            PowerUpType::TripleShot => active_powerups.triple_shot = true,
-                            PowerUpType::HomingMissiles => active_powerups.homing_missiles = true,
                             _ => {}
                         }
                         
@@ -241,11 +256,8 @@ pub fn update_powerup_effects(
         if effect.remaining <= 0.0 {
             // Remove expired effect
             match effect.effect_type {
-                PowerUpType::RapidFire => active_powerups.rapid_fire = false,
                 PowerUpType::Shield => active_powerups.shield = false,
                 PowerUpType::SpeedBoost => active_powerups.speed_multiplier = 1.0,
-                PowerUpType::TripleShot => active_powerups.triple_shot = false,
-                PowerUpType::HomingMissiles => active_powerups.homing_missiles = false,
                 _ => {}
             }
             
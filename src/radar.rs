@@ -0,0 +1,65 @@
+use bevy::prelude::*;
+
+// Height difference within which a contact counts as level with the player
+const RADAR_LEVEL_BAND: f32 = 25.0;
+
+// Zoom levels are ranges in metres, from the centre to the edge of the scope; R steps through them
+#[derive(Resource)]
+pub struct Radar {
+    pub ranges: Vec<f32>,
+    pub zoom: usize,
+}
+
+impl Default for Radar {
+    fn default() -> Self {
+        Self {
+            ranges: vec![250.0, 500.0, 1000.0],
+            zoom: 1,
+        }
+    }
+}
+
+impl Radar {
+    pub fn range(&self) -> f32 {
+        self.ranges[self.zoom.min(self.ranges.len() - 1)]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RadarAltitude {
+    Above,
+    Level,
+    Below,
+}
+
+impl RadarAltitude {
+    pub fn of(height_difference: f32) -> Self {
+        if height_difference > RADAR_LEVEL_BAND {
+            RadarAltitude::Above
+        } else if height_difference < -RADAR_LEVEL_BAND {
+            RadarAltitude::Below
+        } else {
+            RadarAltitude::Level
+        }
+    }
+}
+
+// Where `position` shows up on a heading-up scope around `player`, as a fraction of `range`:
+// x to the right, y straight ahead. None when it's out of range.
+pub fn radar_blip(player: &Transform, position: Vec3, range: f32) -> Option<Vec2> {
+    // Pointing straight up or down the nose says nothing about heading, but the belly does
+    let heading = (player.forward().with_y(0.0))
+        .try_normalize()
+        .or_else(|| (-player.up().with_y(0.0)).try_normalize())
+        .unwrap_or(Vec3::NEG_Z);
+    let right = heading.cross(Vec3::Y);
+    let offset = position - player.translation;
+    let blip = Vec2::new(offset.dot(right), offset.dot(heading)) / range;
+    (blip.length() <= 1.0).then_some(blip)
+}
+
+pub fn cycle_radar_zoom(keyboard_input: Res<ButtonInput<KeyCode>>, mut radar: ResMut<Radar>) {
+    if keyboard_input.just_pressed(KeyCode::KeyR) {
+        radar.zoom = (radar.zoom + 1) % radar.ranges.len();
+    }
+}
@@ -0,0 +1,488 @@
+use bevy::prelude::*;
+use bevy::input::mouse::MouseMotion;
+use bevy::time::TimeUpdateStrategy;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::game_state::{GameState, GameMode, CurrentGameMode, GameStats, ChallengeTimer, GameRng, SeedSettings, UpgradeData};
+use crate::save::SavedUpgrades;
+use crate::flight::FlightModel;
+use crate::archetypes::EnemyArchetypes;
+
+// Bump this whenever the binary layout below changes, or when the simulation changes so that
+// older inputs no longer reproduce their runs (2: gameplay moved to a fixed timestep,
+// 3: flight model added to the header, 4: arcade attitude reworked onto quaternions,
+// 5: terrain collision, 6: generated terrain, 7: homing missiles, 8: weapon loadout and
+// two-byte button bits, 9: target lock, 10: enemy archetypes, 11: enemy damage states and
+// crashes, 12: ground units, 13: Survival bosses, 14: enemy archetype fingerprint in the header)
+pub const REPLAY_VERSION: u16 = 14;
+
+const REPLAY_MAGIC: &[u8; 4] = b"SKYR";
+const LAST_REPLAY_FILE_NAME: &str = "last.replay";
+
+// Button bits stored per frame; HAS_MOUSE marks frames followed by a mouse delta
+const ROLL_LEFT: u16 = 1 << 0;
+const ROLL_RIGHT: u16 = 1 << 1;
+const THROTTLE_UP: u16 = 1 << 2;
+const THROTTLE_DOWN: u16 = 1 << 3;
+const BOOST: u16 = 1 << 4;
+const FIRE: u16 = 1 << 5;
+const FIRE_SECONDARY: u16 = 1 << 6;
+const CYCLE_SECONDARY: u16 = 1 << 7;
+const CYCLE_TARGET: u16 = 1 << 8;
+const HAS_MOUSE: u16 = 1 << 15;
+
+// Everything the player did this frame. Gameplay reads this rather than the raw input devices
+// so that a replay can stand in for the keyboard and mouse.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlayerInput {
+    pub mouse_delta: Vec2,
+    pub roll_left: bool,
+    pub roll_right: bool,
+    pub throttle_up: bool,
+    pub throttle_down: bool,
+    pub boost: bool,
+    pub fire: bool,
+    pub fire_secondary: bool,
+    pub cycle_secondary: bool,
+    pub cycle_target: bool,
+}
+
+impl PlayerInput {
+    fn button_bits(&self) -> u16 {
+        let mut bits = 0;
+        for (pressed, bit) in [
+            (self.roll_left, ROLL_LEFT),
+            (self.roll_right, ROLL_RIGHT),
+            (self.throttle_up, THROTTLE_UP),
+            (self.throttle_down, THROTTLE_DOWN),
+            (self.boost, BOOST),
+            (self.fire, FIRE),
+            (self.fire_secondary, FIRE_SECONDARY),
+            (self.cycle_secondary, CYCLE_SECONDARY),
+            (self.cycle_target, CYCLE_TARGET),
+        ] {
+            if pressed {
+                bits |= bit;
+            }
+        }
+        bits
+    }
+
+    fn from_bits(bits: u16, mouse_delta: Vec2) -> Self {
+        Self {
+            mouse_delta,
+            roll_left: bits & ROLL_LEFT != 0,
+            roll_right: bits & ROLL_RIGHT != 0,
+            throttle_up: bits & THROTTLE_UP != 0,
+            throttle_down: bits & THROTTLE_DOWN != 0,
+            boost: bits & BOOST != 0,
+            fire: bits & FIRE != 0,
+            fire_secondary: bits & FIRE_SECONDARY != 0,
+            cycle_secondary: bits & CYCLE_SECONDARY != 0,
+            cycle_target: bits & CYCLE_TARGET != 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayFrame {
+    pub delta: Duration,
+    pub input: PlayerInput,
+}
+
+// A run is fully described by its seed, mode, flight model, upgrade levels, the enemy
+// archetypes it was flown against and the input and frame time of every simulated frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub mode: GameMode,
+    pub flight_model: FlightModel,
+    pub upgrades: SavedUpgrades,
+    // `EnemyArchetypes::fingerprint` when it was recorded
+    pub archetypes: u64,
+    pub frames: Vec<ReplayFrame>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    Corrupt(String),
+    UnsupportedVersion(u16),
+    // The enemy archetype files have changed since it was recorded, so it would play out differently
+    ArchetypeMismatch,
+}
+
+impl std::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "i/o error: {err}"),
+            ReplayError::Corrupt(reason) => write!(f, "corrupt replay: {reason}"),
+            ReplayError::UnsupportedVersion(version) => {
+                write!(f, "replay version {version} is not supported (expected {REPLAY_VERSION})")
+            }
+            ReplayError::ArchetypeMismatch => write!(f, "replay was recorded with different enemy types"),
+        }
+    }
+}
+
+fn mode_to_byte(mode: GameMode) -> u8 {
+    match mode {
+        GameMode::FreePlay => 0,
+        GameMode::TimeAttack => 1,
+        GameMode::TargetHunt => 2,
+        GameMode::Survival => 3,
+        GameMode::RaceTheClock => 4,
+    }
+}
+
+fn mode_from_byte(byte: u8) -> Option<GameMode> {
+    match byte {
+        0 => Some(GameMode::FreePlay),
+        1 => Some(GameMode::TimeAttack),
+        2 => Some(GameMode::TargetHunt),
+        3 => Some(GameMode::Survival),
+        4 => Some(GameMode::RaceTheClock),
+        _ => None,
+    }
+}
+
+fn flight_model_to_byte(flight_model: FlightModel) -> u8 {
+    match flight_model {
+        FlightModel::Arcade => 0,
+        FlightModel::Simulation => 1,
+    }
+}
+
+fn flight_model_from_byte(byte: u8) -> Option<FlightModel> {
+    match byte {
+        0 => Some(FlightModel::Arcade),
+        1 => Some(FlightModel::Simulation),
+        _ => None,
+    }
+}
+
+// Layout (little endian):
+//   "SKYR" | version u16 | seed u64 | mode u8 | flight model u8 | 4 x upgrade level u32
+//   | archetype fingerprint u64 | frame count u32
+//   then per frame: frame time in nanoseconds u32 | button bits u16 | [mouse dx f32 | mouse dy f32]
+// The mouse delta is only written when HAS_MOUSE is set, so idle frames take six bytes.
+impl Replay {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(44 + self.frames.len() * 6);
+        bytes.extend_from_slice(REPLAY_MAGIC);
+        bytes.extend_from_slice(&REPLAY_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.push(mode_to_byte(self.mode));
+        bytes.push(flight_model_to_byte(self.flight_model));
+        for level in [
+            self.upgrades.speed_level,
+            self.upgrades.maneuverability_level,
+            self.upgrades.magnet_level,
+            self.upgrades.multiplier_level,
+        ] {
+            bytes.extend_from_slice(&level.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.archetypes.to_le_bytes());
+        bytes.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+
+        for frame in &self.frames {
+            let nanos = frame.delta.as_nanos().min(u32::MAX as u128) as u32;
+            bytes.extend_from_slice(&nanos.to_le_bytes());
+
+            let has_mouse = frame.input.mouse_delta != Vec2::ZERO;
+            let bits = frame.input.button_bits() | if has_mouse { HAS_MOUSE } else { 0 };
+            bytes.extend_from_slice(&bits.to_le_bytes());
+            if has_mouse {
+                bytes.extend_from_slice(&frame.input.mouse_delta.x.to_le_bytes());
+                bytes.extend_from_slice(&frame.input.mouse_delta.y.to_le_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let mut reader = ByteReader { bytes, position: 0 };
+
+        if &reader.take::<4>()? != REPLAY_MAGIC {
+            return Err(ReplayError::Corrupt("not a replay file".to_string()));
+        }
+        let version = u16::from_le_bytes(reader.take()?);
+        if version != REPLAY_VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+
+        let seed = u64::from_le_bytes(reader.take()?);
+        let mode_byte = reader.take::<1>()?[0];
+        let mode = mode_from_byte(mode_byte)
+            .ok_or_else(|| ReplayError::Corrupt(format!("unknown game mode {mode_byte}")))?;
+        let flight_model_byte = reader.take::<1>()?[0];
+        let flight_model = flight_model_from_byte(flight_model_byte)
+            .ok_or_else(|| ReplayError::Corrupt(format!("unknown flight model {flight_model_byte}")))?;
+        let upgrades = SavedUpgrades {
+            speed_level: u32::from_le_bytes(reader.take()?),
+            maneuverability_level: u32::from_le_bytes(reader.take()?),
+            magnet_level: u32::from_le_bytes(reader.take()?),
+            multiplier_level: u32::from_le_bytes(reader.take()?),
+        };
+        let archetypes = u64::from_le_bytes(reader.take()?);
+
+        let frame_count = u32::from_le_bytes(reader.take()?) as usize;
+        // Every frame is at least six bytes, so don't trust a count the data can't hold
+        let mut frames = Vec::with_capacity(frame_count.min(bytes.len() / 6));
+        for _ in 0..frame_count {
+            let delta = Duration::from_nanos(u32::from_le_bytes(reader.take()?) as u64);
+            let bits = u16::from_le_bytes(reader.take()?);
+            let mouse_delta = if bits & HAS_MOUSE != 0 {
+                Vec2::new(
+                    f32::from_le_bytes(reader.take()?),
+                    f32::from_le_bytes(reader.take()?),
+                )
+            } else {
+                Vec2::ZERO
+            };
+            frames.push(ReplayFrame {
+                delta,
+                input: PlayerInput::from_bits(bits, mouse_delta),
+            });
+        }
+
+        if reader.position != bytes.len() {
+            return Err(ReplayError::Corrupt("trailing data after last frame".to_string()));
+        }
+
+        Ok(Self { seed, mode, flight_model, upgrades, archetypes, frames })
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl ByteReader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], ReplayError> {
+        let end = self.position + N;
+        let slice = self.bytes.get(self.position..end)
+            .ok_or_else(|| ReplayError::Corrupt("unexpected end of file".to_string()))?;
+        self.position = end;
+        Ok(slice.try_into().expect("slice length matches N"))
+    }
+}
+
+pub fn load_replay(path: &Path) -> Result<Replay, ReplayError> {
+    let bytes = fs::read(path).map_err(ReplayError::Io)?;
+    Replay::decode(&bytes)
+}
+
+pub fn write_replay(path: &Path, replay: &Replay) -> Result<(), ReplayError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(ReplayError::Io)?;
+    }
+    fs::write(path, replay.encode()).map_err(ReplayError::Io)
+}
+
+// Where finished runs are written. `dir` is None when recording to disk is disabled.
+#[derive(Resource)]
+pub struct ReplayStore {
+    pub dir: Option<PathBuf>,
+}
+
+impl Default for ReplayStore {
+    fn default() -> Self {
+        Self {
+            dir: dirs::data_dir().map(|dir| dir.join("sky-hunter").join("replays")),
+        }
+    }
+}
+
+impl ReplayStore {
+    pub fn disabled() -> Self {
+        Self { dir: None }
+    }
+
+    pub fn last_replay_path(&self) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| dir.join(LAST_REPLAY_FILE_NAME))
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ReplayRecorder {
+    // The run in progress
+    pub current: Option<Replay>,
+    // The most recently finished run
+    pub last: Option<Replay>,
+}
+
+// Present while a replay is driving the game instead of the player
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    pub replay: Replay,
+    pub frame: usize,
+    saved_upgrades: SavedUpgrades,
+    saved_flight_model: FlightModel,
+    previous_time_strategy: Option<TimeUpdateStrategy>,
+}
+
+pub fn read_live_input(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut motion_events: EventReader<MouseMotion>,
+    mut input: ResMut<PlayerInput>,
+) {
+    *input = PlayerInput {
+        mouse_delta: motion_events.read().map(|event| event.delta).sum(),
+        roll_left: keyboard.pressed(KeyCode::KeyA),
+        roll_right: keyboard.pressed(KeyCode::KeyD),
+        throttle_up: keyboard.pressed(KeyCode::KeyW),
+        throttle_down: keyboard.pressed(KeyCode::KeyS),
+        boost: keyboard.pressed(KeyCode::Space),
+        // Left Mouse Button or F key for shooting (not Space)
+        fire: keyboard.pressed(KeyCode::KeyF) || mouse.pressed(MouseButton::Left),
+        fire_secondary: keyboard.pressed(KeyCode::KeyE) || mouse.pressed(MouseButton::Right),
+        cycle_secondary: keyboard.pressed(KeyCode::KeyQ),
+        cycle_target: keyboard.pressed(KeyCode::KeyT),
+    };
+}
+
+pub fn feed_replay_input(playback: Res<ReplayPlayback>, mut input: ResMut<PlayerInput>) {
+    *input = playback.replay.frames
+        .get(playback.frame)
+        .map(|frame| frame.input)
+        .unwrap_or_default();
+}
+
+// Runs after begin_run so the recorded seed is the one this run actually uses
+pub fn start_recording(world: &mut World) {
+    if world.contains_resource::<ReplayPlayback>() {
+        return;
+    }
+
+    let replay = Replay {
+        seed: world.resource::<GameRng>().seed,
+        mode: world.resource::<CurrentGameMode>().mode,
+        flight_model: *world.resource::<FlightModel>(),
+        upgrades: SavedUpgrades::capture(world.resource::<UpgradeData>()),
+        archetypes: world.resource::<EnemyArchetypes>().fingerprint(),
+        frames: Vec::new(),
+    };
+    world.resource_mut::<ReplayRecorder>().current = Some(replay);
+}
+
+pub fn record_frame(
+    time: Res<Time>,
+    input: Res<PlayerInput>,
+    mut recorder: ResMut<ReplayRecorder>,
+) {
+    if let Some(replay) = &mut recorder.current {
+        replay.frames.push(ReplayFrame {
+            delta: time.delta(),
+            input: *input,
+        });
+    }
+}
+
+pub fn finish_recording(mut recorder: ResMut<ReplayRecorder>, store: Res<ReplayStore>) {
+    let Some(replay) = recorder.current.take() else {
+        return;
+    };
+
+    if let Some(path) = store.last_replay_path() {
+        match write_replay(&path, &replay) {
+            Ok(()) => info!("Saved replay of seed {} to {}", replay.seed, path.display()),
+            Err(err) => error!("Failed to write replay {}: {err}", path.display()),
+        }
+    }
+
+    recorder.last = Some(replay);
+}
+
+// Sets up the world so the next transition into Playing re-runs `replay`. Frame times come from
+// the recording, so playback doesn't depend on how fast this machine renders. Replays recorded
+// against other enemy archetypes are refused, since their enemies would fly differently.
+pub fn start_playback(world: &mut World, replay: Replay) -> Result<(), ReplayError> {
+    if replay.archetypes != world.resource::<EnemyArchetypes>().fingerprint() {
+        return Err(ReplayError::ArchetypeMismatch);
+    }
+    let Some(first_frame) = replay.frames.first() else {
+        warn!("Replay of seed {} has no frames", replay.seed);
+        return Ok(());
+    };
+    let first_delta = first_frame.delta;
+
+    stop_playback(world);
+
+    world.insert_resource(CurrentGameMode { mode: replay.mode });
+    world.insert_resource(ChallengeTimer::for_mode(replay.mode));
+    world.resource_mut::<SeedSettings>().replay = Some(replay.seed);
+
+    let mut game_stats = world.resource_mut::<GameStats>();
+    game_stats.score = 0;
+    game_stats.combo = 0;
+    game_stats.targets_hit = 0;
+    game_stats.time_played = 0.0;
+    game_stats.bonus_coins = 0;
+
+    let mut upgrades = world.resource_mut::<UpgradeData>();
+    let saved_upgrades = SavedUpgrades::capture(&upgrades);
+    replay.upgrades.apply(&mut upgrades);
+
+    let saved_flight_model = *world.resource::<FlightModel>();
+    world.insert_resource(replay.flight_model);
+
+    let previous_time_strategy = world.remove_resource::<TimeUpdateStrategy>();
+    world.insert_resource(TimeUpdateStrategy::ManualDuration(first_delta));
+
+    world.insert_resource(ReplayPlayback {
+        replay,
+        frame: 0,
+        saved_upgrades,
+        saved_flight_model,
+        previous_time_strategy,
+    });
+    world.resource_mut::<NextState<GameState>>().set(GameState::Playing);
+    Ok(())
+}
+
+// Restart in the pause menu while watching a replay: plays it again from its first frame
+pub fn restart_playback(world: &mut World) {
+    let Some(replay) = world.get_resource::<ReplayPlayback>().map(|playback| playback.replay.clone()) else {
+        return;
+    };
+    // Leaving the pause menu only unpauses after this frame's time has been taken, which would
+    // spend the replay's first frame standing still
+    world.resource_mut::<Time<Virtual>>().unpause();
+    if let Err(err) = start_playback(world, replay) {
+        error!("Could not restart replay: {err}");
+    }
+}
+
+pub fn advance_playback(
+    mut playback: ResMut<ReplayPlayback>,
+    mut time_strategy: ResMut<TimeUpdateStrategy>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    playback.frame += 1;
+
+    if let Some(frame) = playback.replay.frames.get(playback.frame) {
+        *time_strategy = TimeUpdateStrategy::ManualDuration(frame.delta);
+    } else if matches!(*next_state, NextState::Unchanged) {
+        // The recording stopped without a game over (e.g. the player quit), so end it here
+        next_state.set(GameState::GameOver);
+    }
+}
+
+// Puts back the player's own upgrades, flight model and clock once a replay is over
+pub fn stop_playback(world: &mut World) {
+    let Some(playback) = world.remove_resource::<ReplayPlayback>() else {
+        return;
+    };
+
+    playback.saved_upgrades.apply(&mut world.resource_mut::<UpgradeData>());
+    world.insert_resource(playback.saved_flight_model);
+    match playback.previous_time_strategy {
+        Some(strategy) => world.insert_resource(strategy),
+        None => world.insert_resource(TimeUpdateStrategy::default()),
+    }
+}
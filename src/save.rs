@@ -0,0 +1,253 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::game_state::{GameStats, UpgradeData};
+
+// Bump this whenever the layout of SaveData changes and add a matching arm to `migrate`
+pub const SAVE_VERSION: u32 = 1;
+
+const SAVE_FILE_NAME: &str = "save.ron";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SaveData {
+    pub version: u32,
+    pub high_score: u32,
+    pub coins: u32,
+    pub upgrades: SavedUpgrades,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedUpgrades {
+    pub speed_level: u32,
+    pub maneuverability_level: u32,
+    pub magnet_level: u32,
+    pub multiplier_level: u32,
+}
+
+impl Default for SaveData {
+    fn default() -> Self {
+        Self {
+            version: SAVE_VERSION,
+            high_score: 0,
+            coins: 0,
+            upgrades: SavedUpgrades::default(),
+        }
+    }
+}
+
+impl Default for SavedUpgrades {
+    fn default() -> Self {
+        Self::capture(&UpgradeData::default())
+    }
+}
+
+impl SaveData {
+    pub fn capture(game_stats: &GameStats, upgrades: &UpgradeData) -> Self {
+        Self {
+            version: SAVE_VERSION,
+            high_score: game_stats.high_score,
+            coins: game_stats.coins,
+            upgrades: SavedUpgrades::capture(upgrades),
+        }
+    }
+
+    pub fn apply(&self, game_stats: &mut GameStats, upgrades: &mut UpgradeData) {
+        game_stats.high_score = self.high_score;
+        game_stats.coins = self.coins;
+        self.upgrades.apply(upgrades);
+    }
+}
+
+impl SavedUpgrades {
+    pub fn capture(upgrades: &UpgradeData) -> Self {
+        Self {
+            speed_level: upgrades.speed_level,
+            maneuverability_level: upgrades.maneuverability_level,
+            magnet_level: upgrades.magnet_level,
+            multiplier_level: upgrades.multiplier_level,
+        }
+    }
+
+    pub fn apply(&self, upgrades: &mut UpgradeData) {
+        upgrades.speed_level = self.speed_level;
+        upgrades.maneuverability_level = self.maneuverability_level;
+        upgrades.magnet_level = self.magnet_level;
+        upgrades.multiplier_level = self.multiplier_level;
+    }
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Corrupt(String),
+    UnsupportedVersion(u32),
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "i/o error: {err}"),
+            SaveError::Corrupt(reason) => write!(f, "corrupt save file: {reason}"),
+            SaveError::UnsupportedVersion(version) => {
+                write!(f, "save file version {version} is newer than supported version {SAVE_VERSION}")
+            }
+        }
+    }
+}
+
+// Where progression is stored. `path` is None when persistence is disabled (headless runs, tests),
+// and `writable` is cleared when the file on disk came from a newer build so we never clobber it.
+#[derive(Resource)]
+pub struct SaveFile {
+    pub path: Option<PathBuf>,
+    pub writable: bool,
+}
+
+impl Default for SaveFile {
+    fn default() -> Self {
+        Self {
+            path: dirs::data_dir().map(|dir| dir.join("sky-hunter").join(SAVE_FILE_NAME)),
+            writable: true,
+        }
+    }
+}
+
+impl SaveFile {
+    pub fn disabled() -> Self {
+        Self {
+            path: None,
+            writable: false,
+        }
+    }
+}
+
+// Only the version is read first so older layouts can be routed through `migrate`
+#[derive(Deserialize)]
+struct SaveHeader {
+    #[serde(default)]
+    version: u32,
+}
+
+pub fn parse_save(contents: &str) -> Result<SaveData, SaveError> {
+    let header: SaveHeader = ron::from_str(contents)
+        .map_err(|err| SaveError::Corrupt(err.to_string()))?;
+
+    if header.version > SAVE_VERSION {
+        return Err(SaveError::UnsupportedVersion(header.version));
+    }
+
+    let value: ron::Value = ron::from_str(contents)
+        .map_err(|err| SaveError::Corrupt(err.to_string()))?;
+    let migrated = migrate(value, header.version)?;
+
+    let mut data: SaveData = migrated
+        .into_rust()
+        .map_err(|err| SaveError::Corrupt(err.to_string()))?;
+    data.version = SAVE_VERSION;
+    Ok(data)
+}
+
+fn migrate(value: ron::Value, from_version: u32) -> Result<ron::Value, SaveError> {
+    // Version 0 is a file written without a version field; its layout matches version 1 and
+    // any fields it lacks are filled in by #[serde(default)].
+    match from_version {
+        0 | 1 => Ok(value),
+        other => Err(SaveError::UnsupportedVersion(other)),
+    }
+}
+
+pub fn load_save_data(path: &Path) -> Result<Option<SaveData>, SaveError> {
+    match fs::read_to_string(path) {
+        Ok(contents) => parse_save(&contents).map(Some),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(SaveError::Io(err)),
+    }
+}
+
+pub fn write_save_data(path: &Path, data: &SaveData) -> Result<(), SaveError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(SaveError::Io)?;
+    }
+
+    let contents = ron::ser::to_string_pretty(data, ron::ser::PrettyConfig::default())
+        .map_err(|err| SaveError::Corrupt(err.to_string()))?;
+
+    write_atomically(path, &contents).map_err(SaveError::Io)
+}
+
+// Writes to a temporary file next to `path` first so a crash mid-write can't corrupt the real file
+pub fn write_atomically(path: &Path, contents: &str) -> std::io::Result<()> {
+    let temp_path = path.with_extension("ron.tmp");
+    fs::write(&temp_path, contents)?;
+    fs::rename(&temp_path, path)
+}
+
+// Moves an unreadable save aside (save.ron -> save.ron.corrupt-<unix time>) and returns the new path
+pub fn backup_corrupt_save(path: &Path) -> std::io::Result<PathBuf> {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| SAVE_FILE_NAME.to_string());
+    let backup_path = path.with_file_name(format!("{file_name}.corrupt-{timestamp}"));
+    fs::rename(path, &backup_path)?;
+    Ok(backup_path)
+}
+
+pub fn load_progress(
+    mut save_file: ResMut<SaveFile>,
+    mut game_stats: ResMut<GameStats>,
+    mut upgrades: ResMut<UpgradeData>,
+) {
+    let Some(path) = save_file.path.clone() else {
+        return;
+    };
+
+    match load_save_data(&path) {
+        Ok(Some(data)) => {
+            data.apply(&mut game_stats, &mut upgrades);
+            info!("Loaded save file from {}", path.display());
+        }
+        Ok(None) => {
+            info!("No save file at {}, starting fresh", path.display());
+        }
+        Err(SaveError::UnsupportedVersion(version)) => {
+            // Written by a newer build - leave it alone rather than downgrading it
+            error!("Save file {} has version {version}, which this build cannot read; progress will not be saved", path.display());
+            save_file.writable = false;
+        }
+        Err(SaveError::Io(err)) => {
+            error!("Could not read save file {}: {err}; progress will not be saved", path.display());
+            save_file.writable = false;
+        }
+        Err(err @ SaveError::Corrupt(_)) => {
+            match backup_corrupt_save(&path) {
+                Ok(backup_path) => {
+                    warn!("{err}; moved it to {} and starting fresh", backup_path.display());
+                }
+                Err(backup_err) => {
+                    error!("{err}; could not back it up ({backup_err}), progress will not be saved");
+                    save_file.writable = false;
+                }
+            }
+        }
+    }
+}
+
+pub fn save_progress(save_file: &SaveFile, game_stats: &GameStats, upgrades: &UpgradeData) {
+    if !save_file.writable {
+        return;
+    }
+
+    if let Some(path) = &save_file.path {
+        if let Err(err) = write_save_data(path, &SaveData::capture(game_stats, upgrades)) {
+            error!("Failed to write save file {}: {err}", path.display());
+        }
+    }
+}
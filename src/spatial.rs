@@ -0,0 +1,109 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use crate::enemies::Enemy;
+use crate::ground::GroundUnit;
+use crate::powerups::PowerUp;
+use crate::targets::Collectible;
+
+// Roughly the largest hit radius, so most queries only touch a handful of cells
+pub const SPATIAL_CELL_SIZE: f32 = 25.0;
+
+// The index is rebuilt at the start of each fixed step, before enemies and balloons move.
+// Queries are padded by this much so anything that moved since is still found; callers
+// always do the exact check against the entity's current Transform.
+const STEP_SLACK: f32 = 5.0;
+
+// Uniform grid of entities bucketed by the cell their position falls in
+pub struct SpatialGrid {
+    pub cell_size: f32,
+    cells: HashMap<IVec3, Vec<Entity>>,
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    pub fn insert(&mut self, entity: Entity, position: Vec3) {
+        self.cells.entry(self.cell_of(position)).or_default().push(entity);
+    }
+
+    fn cell_of(&self, position: Vec3) -> IVec3 {
+        (position / self.cell_size).floor().as_ivec3()
+    }
+
+    // Everything that may be within `radius` of the path from `start` to `end`. Cells are visited
+    // in a fixed order so the results come back the same way on every run.
+    pub fn query_segment(&self, start: Vec3, end: Vec3, radius: f32) -> impl Iterator<Item = Entity> + '_ {
+        let reach = Vec3::splat(radius + STEP_SLACK);
+        let min = self.cell_of(start.min(end) - reach);
+        let max = self.cell_of(start.max(end) + reach);
+
+        (min.x..=max.x)
+            .flat_map(move |x| {
+                (min.y..=max.y).flat_map(move |y| (min.z..=max.z).map(move |z| IVec3::new(x, y, z)))
+            })
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+    }
+
+    pub fn query_sphere(&self, center: Vec3, radius: f32) -> impl Iterator<Item = Entity> + '_ {
+        self.query_segment(center, center, radius)
+    }
+}
+
+// Shared by every collision check in the fixed step
+#[derive(Resource)]
+pub struct SpatialIndex {
+    pub enemies: SpatialGrid,
+    pub targets: SpatialGrid,
+    pub powerups: SpatialGrid,
+    pub ground_units: SpatialGrid,
+}
+
+impl Default for SpatialIndex {
+    fn default() -> Self {
+        Self {
+            enemies: SpatialGrid::new(SPATIAL_CELL_SIZE),
+            targets: SpatialGrid::new(SPATIAL_CELL_SIZE),
+            powerups: SpatialGrid::new(SPATIAL_CELL_SIZE),
+            ground_units: SpatialGrid::new(SPATIAL_CELL_SIZE),
+        }
+    }
+}
+
+pub fn rebuild_spatial_index(
+    mut index: ResMut<SpatialIndex>,
+    enemies: Query<(Entity, &Transform), With<Enemy>>,
+    targets: Query<(Entity, &Transform), With<Collectible>>,
+    powerups: Query<(Entity, &Transform), With<PowerUp>>,
+    ground_units: Query<(Entity, &Transform, &GroundUnit)>,
+) {
+    index.enemies.clear();
+    for (entity, transform) in enemies.iter() {
+        index.enemies.insert(entity, transform.translation);
+    }
+
+    index.targets.clear();
+    for (entity, transform) in targets.iter() {
+        index.targets.insert(entity, transform.translation);
+    }
+
+    index.powerups.clear();
+    for (entity, transform) in powerups.iter() {
+        index.powerups.insert(entity, transform.translation);
+    }
+
+    index.ground_units.clear();
+    for (entity, transform, unit) in ground_units.iter() {
+        index.ground_units.insert(entity, unit.hit_center(transform));
+    }
+}
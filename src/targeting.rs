@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+use crate::{Aircraft, replay::PlayerInput};
+use crate::enemies::{Enemy, lead_position};
+use crate::boss::Boss;
+use crate::physics::InterpolatedTransform;
+use crate::weapons::Loadout;
+
+// Half-angle of the view cone targets are picked from, automatically or by cycling
+pub const TARGET_CONE: f32 = 0.6;
+pub const TARGET_RANGE: f32 = 800.0;
+// A locked target is kept wherever it goes, until it gets this far away
+const TARGET_LOST_RANGE: f32 = 1000.0;
+
+// The enemy or boss the player has selected, and where to aim the primary weapon to hit it
+#[derive(Resource, Default)]
+pub struct TargetLock {
+    pub target: Option<Entity>,
+    pub lead_point: Option<Vec3>,
+    // Whether the cycle button was down last step, so holding it only cycles once
+    cycle_held: bool,
+}
+
+// Enemies within `cone` radians of `forward` and `range` of `position`, nearest first
+pub fn enemies_in_cone<'a>(
+    position: Vec3,
+    forward: Vec3,
+    cone: f32,
+    range: f32,
+    enemies: impl Iterator<Item = (Entity, &'a Transform)>,
+) -> Vec<(Entity, f32)> {
+    let min_alignment = cone.cos();
+    let mut in_cone: Vec<(Entity, f32)> = enemies
+        .filter_map(|(entity, transform)| {
+            let offset = transform.translation - position;
+            let distance = offset.length();
+            let in_cone = distance > 0.0 && offset.dot(forward) / distance >= min_alignment;
+            (in_cone && distance <= range).then_some((entity, distance))
+        })
+        .collect();
+    in_cone.sort_by(|a, b| a.1.total_cmp(&b.1));
+    in_cone
+}
+
+// Keeps the lock on a live target in range, picks the nearest enemy in view when there isn't one,
+// and moves on to the next enemy in view, further away, each time the cycle button is pressed
+pub fn update_target_lock(
+    mut lock: ResMut<TargetLock>,
+    input: Res<PlayerInput>,
+    player_query: Query<&Transform, With<Aircraft>>,
+    enemy_query: Query<(Entity, &Transform, &InterpolatedTransform), Or<(With<Enemy>, With<Boss>)>>,
+    loadout: Res<Loadout>,
+    time: Res<Time>,
+) {
+    let cycle_pressed = input.cycle_target && !lock.cycle_held;
+    lock.cycle_held = input.cycle_target;
+
+    let Ok(player_transform) = player_query.single() else {
+        lock.target = None;
+        lock.lead_point = None;
+        return;
+    };
+    let position = player_transform.translation;
+
+    let current = lock.target.and_then(|target| enemy_query.get(target).ok());
+    if current.is_none_or(|(_, transform, _)| transform.translation.distance(position) > TARGET_LOST_RANGE) {
+        lock.target = None;
+    }
+
+    if lock.target.is_none() || cycle_pressed {
+        let in_view = enemies_in_cone(
+            position,
+            *player_transform.forward(),
+            TARGET_CONE,
+            TARGET_RANGE,
+            enemy_query.iter().map(|(entity, transform, _)| (entity, transform)),
+        );
+        let next = match lock.target.and_then(|target| in_view.iter().position(|(entity, _)| *entity == target)) {
+            Some(index) if cycle_pressed => (index + 1) % in_view.len(),
+            _ => 0,
+        };
+        if let Some((entity, _)) = in_view.get(next) {
+            lock.target = Some(*entity);
+        }
+    }
+
+    lock.lead_point = lock.target.and_then(|target| enemy_query.get(target).ok()).map(|(_, transform, interpolated)| {
+        // How far the target moved over the last step
+        let velocity = (interpolated.current.translation - interpolated.previous.translation) / time.delta_secs();
+        lead_position(position, transform.translation, velocity, loadout.primary.weapon.projectile_speed, 1.0)
+    });
+}
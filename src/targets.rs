@@ -1,6 +1,10 @@
 use bevy::prelude::*;
-use crate::game_state::{GameStats, GameMode, CurrentGameMode, ChallengeTimer, get_score_multiplier, get_magnet_range, UpgradeData};
-use crate::Aircraft;
+use crate::game_state::{GameStats, GameMode, CurrentGameMode, ChallengeTimer, GameRng, RunClock, RunTimers, get_score_multiplier, get_magnet_range, UpgradeData};
+use crate::physics::InterpolatedTransform;
+use crate::spatial::SpatialIndex;
+use crate::terrain::TerrainHeightField;
+use crate::particles::{ParticleBurst, ParticlePreset};
+use crate::{Aircraft, GameEntity};
 
 #[derive(Component)]
 pub struct Target {
@@ -27,14 +31,20 @@ pub enum TargetType {
     Combo,
 }
 
-#[derive(Component)]
-pub struct Collectible;
+impl TargetType {
+    pub fn color(&self) -> Color {
+        match self {
+            TargetType::Golden => Color::srgb(1.0, 0.85, 0.0),
+            TargetType::Speed => Color::srgb(0.8, 0.2, 0.8),
+            TargetType::Time => Color::srgb(0.2, 0.8, 0.8),
+            TargetType::Combo => Color::srgb(0.8, 0.8, 0.2),
+            TargetType::Normal => Color::srgb(0.2, 0.8, 0.2),
+        }
+    }
+}
 
 #[derive(Component)]
-pub struct ParticleEffect {
-    pub lifetime: f32,
-    pub velocity: Vec3,
-}
+pub struct Collectible;
 
 #[derive(Event)]
 pub struct TargetHitEvent {
@@ -50,6 +60,8 @@ pub fn spawn_targets_system(
     targets: Query<Entity, With<Target>>,
     game_mode: Res<CurrentGameMode>,
     _time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+    terrain: Res<TerrainHeightField>,
 ) {
     let target_count = targets.iter().count();
     let max_targets = match game_mode.mode {
@@ -61,42 +73,41 @@ pub fn spawn_targets_system(
     if target_count < max_targets {
         if let Ok(aircraft_transform) = query.single() {
             // Spawn targets around the player
-            let spawn_distance = 100.0 + fastrand::f32() * 200.0;
-            let angle = fastrand::f32() * std::f32::consts::TAU;
-            let height = 20.0 + fastrand::f32() * 100.0;
+            let spawn_distance = 100.0 + rng.spawns.f32() * 200.0;
+            let angle = rng.spawns.f32() * std::f32::consts::TAU;
+            let height = 20.0 + rng.spawns.f32() * 100.0;
             
-            let position = Vec3::new(
-                aircraft_transform.translation.x + angle.cos() * spawn_distance,
-                height,
-                aircraft_transform.translation.z + angle.sin() * spawn_distance,
-            );
+            let x = aircraft_transform.translation.x + angle.cos() * spawn_distance;
+            let z = aircraft_transform.translation.z + angle.sin() * spawn_distance;
+            // Height is measured from the ground below
+            let position = Vec3::new(x, terrain.height_at(x, z) + height, z);
             
             // Determine target type
-            let (target_type, color, points, scale) = match game_mode.mode {
+            let (target_type, points, scale) = match game_mode.mode {
                 GameMode::TargetHunt => {
-                    if fastrand::f32() < 0.1 {
-                        (TargetType::Golden, Color::srgb(1.0, 0.85, 0.0), 500, 2.0)
+                    if rng.spawns.f32() < 0.1 {
+                        (TargetType::Golden, 500, 2.0)
                     } else {
-                        (TargetType::Normal, Color::srgb(0.2, 0.8, 0.2), 100, 1.0)
+                        (TargetType::Normal, 100, 1.0)
                     }
                 }
                 GameMode::Survival => {
-                    if fastrand::f32() < 0.2 {
-                        (TargetType::Time, Color::srgb(0.2, 0.8, 0.8), 50, 1.5)
+                    if rng.spawns.f32() < 0.2 {
+                        (TargetType::Time, 50, 1.5)
                     } else {
-                        (TargetType::Normal, Color::srgb(0.2, 0.8, 0.2), 100, 1.0)
+                        (TargetType::Normal, 100, 1.0)
                     }
                 }
                 _ => {
-                    let rand = fastrand::f32();
+                    let rand = rng.spawns.f32();
                     if rand < 0.05 {
-                        (TargetType::Golden, Color::srgb(1.0, 0.85, 0.0), 500, 2.0)
+                        (TargetType::Golden, 500, 2.0)
                     } else if rand < 0.15 {
-                        (TargetType::Speed, Color::srgb(0.8, 0.2, 0.8), 200, 1.3)
+                        (TargetType::Speed, 200, 1.3)
                     } else if rand < 0.25 {
-                        (TargetType::Combo, Color::srgb(0.8, 0.8, 0.2), 150, 1.2)
+                        (TargetType::Combo, 150, 1.2)
                     } else {
-                        (TargetType::Normal, Color::srgb(0.2, 0.8, 0.2), 100, 1.0)
+                        (TargetType::Normal, 100, 1.0)
                     }
                 }
             };
@@ -112,12 +123,15 @@ pub fn spawn_targets_system(
                 Collectible,
                 Balloon {
                     base_height: position.y,
-                    float_phase: fastrand::f32() * std::f32::consts::TAU,
-                    sway_phase: fastrand::f32() * std::f32::consts::TAU,
+                    float_phase: rng.spawns.f32() * std::f32::consts::TAU,
+                    sway_phase: rng.spawns.f32() * std::f32::consts::TAU,
                 },
+                InterpolatedTransform::new(Transform::from_translation(position)),
+                GameEntity,
             )).id();
             
-            // Balloon body with detailed mesh
+            // Balloon body with detailed mesh, coloured like its radar blip
+            let color = target_type.color();
             let balloon_body = commands.spawn((
                 Mesh3d(meshes.add(crate::models::create_detailed_balloon_mesh(&target_type))),
                 MeshMaterial3d(materials.add(StandardMaterial {
@@ -175,18 +189,22 @@ pub fn spawn_targets_system(
 pub fn collision_detection_system(
     mut commands: Commands,
     mut aircraft_query: Query<(&Transform, &mut crate::enemies::Health), With<Aircraft>>,
-    targets_query: Query<(Entity, &Transform, &Target, &Children), With<Collectible>>,
+    targets_query: Query<(&Transform, &Target), With<Collectible>>,
     mut game_stats: ResMut<GameStats>,
     mut challenge_timer: ResMut<ChallengeTimer>,
     _game_mode: Res<CurrentGameMode>,
     upgrades: Res<UpgradeData>,
     mut hit_events: EventWriter<TargetHitEvent>,
+    spatial_index: Res<SpatialIndex>,
 ) {
     if let Ok((aircraft_transform, mut aircraft_health)) = aircraft_query.single_mut() {
         let magnet_range = get_magnet_range(upgrades.magnet_level);
         let collection_range = 5.0 + magnet_range;
         
-        for (entity, target_transform, target, _children) in targets_query.iter() {
+        for entity in spatial_index.targets.query_sphere(aircraft_transform.translation, collection_range) {
+            let Ok((target_transform, target)) = targets_query.get(entity) else {
+                continue;
+            };
             let distance = aircraft_transform.translation.distance(target_transform.translation);
             
             if distance < collection_range {
@@ -268,103 +286,44 @@ pub fn magnet_effect_system(
     }
 }
 
-pub fn particle_system(
-    mut commands: Commands,
-    mut particles: Query<(Entity, &mut Transform, &mut ParticleEffect)>,
-    time: Res<Time>,
-) {
-    for (entity, mut transform, mut particle) in particles.iter_mut() {
-        particle.lifetime -= time.delta_secs();
-        
-        if particle.lifetime <= 0.0 {
-            commands.entity(entity).despawn();
-        } else {
-            transform.translation += particle.velocity * time.delta_secs();
-            particle.velocity.y -= 9.8 * time.delta_secs(); // Gravity
-            
-            // Fade out
-            transform.scale = Vec3::splat(particle.lifetime / 1.0);
-        }
-    }
-}
-
 pub fn spawn_hit_particles(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
     mut hit_events: EventReader<TargetHitEvent>,
+    mut bursts: EventWriter<ParticleBurst>,
 ) {
     for event in hit_events.read() {
-        // Spawn multiple particles
-        for i in 0..15 {
-            let velocity = Vec3::new(
-                (fastrand::f32() - 0.5) * 20.0,
-                fastrand::f32() * 15.0 + 5.0,
-                (fastrand::f32() - 0.5) * 20.0,
-            );
-            
-            let color = match event.target_type {
-                TargetType::Golden => Color::srgb(1.0, 0.85, 0.0),
-                TargetType::Speed => Color::srgb(0.8, 0.2, 0.8),
-                TargetType::Time => Color::srgb(0.2, 0.8, 0.8),
-                TargetType::Combo => Color::srgb(0.8, 0.8, 0.2),
-                TargetType::Normal => Color::srgb(0.2, 0.8, 0.2),
-            };
-            
-            commands.spawn((
-                Mesh3d(meshes.add(Sphere::new(0.3))),
-                MeshMaterial3d(materials.add(StandardMaterial {
-                    base_color: color,
-                    emissive: color.to_linear() * 2.0,
-                    ..default()
-                })),
-                Transform::from_translation(event.position),
-                ParticleEffect {
-                    lifetime: 1.0,
-                    velocity,
-                },
-            ));
-            
-            // Add green healing particles
-            if i < 5 {
-                let heal_velocity = Vec3::new(
-                    (fastrand::f32() - 0.5) * 10.0,
-                    fastrand::f32() * 5.0 + 10.0,
-                    (fastrand::f32() - 0.5) * 10.0,
-                );
-                
-                commands.spawn((
-                    Mesh3d(meshes.add(Sphere::new(0.2))),
-                    MeshMaterial3d(materials.add(StandardMaterial {
-                        base_color: Color::srgb(0.2, 1.0, 0.2),
-                        emissive: Color::srgb(0.2, 1.0, 0.2).into(),
-                        ..default()
-                    })),
-                    Transform::from_translation(event.position),
-                    ParticleEffect {
-                        lifetime: 1.5,
-                        velocity: heal_velocity,
-                    },
-                ));
-            }
-        }
+        bursts.write(ParticleBurst {
+            preset: ParticlePreset::BalloonPop,
+            position: event.position,
+            velocity: Vec3::Y * 10.0,
+            count: 15,
+            color: Some(event.target_type.color()),
+        });
+        
+        // Green healing sparks floating up
+        bursts.write(ParticleBurst {
+            preset: ParticlePreset::BalloonPop,
+            position: event.position,
+            velocity: Vec3::Y * 12.0,
+            count: 5,
+            color: Some(Color::srgb(0.2, 1.0, 0.2)),
+        });
     }
 }
 
 pub fn combo_timeout_system(
     mut game_stats: ResMut<GameStats>,
     time: Res<Time>,
-    mut combo_timer: Local<f32>,
+    mut run_timers: ResMut<RunTimers>,
 ) {
     if game_stats.combo > 0 {
-        *combo_timer += time.delta_secs();
+        run_timers.combo += time.delta_secs();
         
-        if *combo_timer > 3.0 {
+        if run_timers.combo > 3.0 {
             game_stats.combo = 0;
-            *combo_timer = 0.0;
+            run_timers.combo = 0.0;
         }
     } else {
-        *combo_timer = 0.0;
+        run_timers.combo = 0.0;
     }
 }
 
@@ -372,9 +331,10 @@ pub fn animate_targets(
     mut balloons: Query<(&mut Transform, &Balloon, &Target), With<Collectible>>,
     mut strings: Query<&mut Transform, (With<BalloonString>, Without<Balloon>, Without<Collectible>)>,
     time: Res<Time>,
+    run_clock: Res<RunClock>,
 ) {
     for (mut transform, balloon, target) in balloons.iter_mut() {
-        let elapsed = time.elapsed_secs();
+        let elapsed = run_clock.elapsed;
         
         // Floating motion - gentle up and down
         let float_amount = match target.target_type {
@@ -410,7 +370,7 @@ pub fn animate_targets(
     // Animate strings with physics-like motion
     for mut string_transform in strings.iter_mut() {
         // Add slight sway to strings
-        let sway = (time.elapsed_secs() * 2.0).sin() * 0.02;
+        let sway = (run_clock.elapsed * 2.0).sin() * 0.02;
         string_transform.rotation = Quat::from_rotation_z(sway);
     }
 }
\ No newline at end of file
@@ -0,0 +1,623 @@
+use bevy::prelude::*;
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
+use std::collections::HashMap;
+use crate::enemies::{Enemy, Health};
+use crate::game_state::GameState;
+use crate::lod::DistanceLod;
+use crate::models::{create_low_poly_trunk_mesh, create_terrain_chunk, create_tree_mesh};
+use crate::noise::{fbm, hash, hash01, ridged};
+use crate::powerups::ActivePowerUps;
+use crate::{Aircraft, FlightCamera, GameEntity};
+
+pub const TERRAIN_CHUNK_SIZE: f32 = 400.0;
+// Grid resolution of a chunk by how many chunks it is from the aircraft's; the last one is
+// used for everything further out
+pub const TERRAIN_LOD_RESOLUTIONS: [u32; 3] = [32, 16, 8];
+// How far each chunk's edges hang down, hiding the cracks where a finer chunk meets a coarser one
+const TERRAIN_SKIRT_DEPTH: f32 = 60.0;
+// Chunks within this many chunks of the aircraft are kept loaded
+pub const TERRAIN_VIEW_RADIUS: i32 = 2;
+// Finished meshes kept around for flying back over recently seen ground
+const MAX_CACHED_CHUNKS: usize = 150;
+// Trees further than this from the camera are drawn with low-poly meshes
+const TREE_LOD_DISTANCE: f32 = 350.0;
+
+// How far the aircraft's centre sits above its belly
+pub const AIRCRAFT_CLEARANCE: f32 = 2.0;
+const AIRCRAFT_RADIUS: f32 = 3.0;
+// Speed into the surface below which the aircraft just scrapes along it
+const SAFE_IMPACT_SPEED: f32 = 4.0;
+// Damage per unit of speed into the surface, so a grazing touch hurts far less than a dive
+const CRASH_DAMAGE_PER_SPEED: f32 = 1.5;
+// Enemies never fly lower than this above the ground
+const ENEMY_MIN_ALTITUDE: f32 = 15.0;
+
+// Water fills everything below this height
+pub const SEA_LEVEL: f32 = 0.0;
+// Above this (give or take the local climate) mountains are snow-capped
+const SNOW_LINE: f32 = 110.0;
+// Trees are placed at most one per square cell of this size; it divides TERRAIN_CHUNK_SIZE
+const TREE_CELL_SIZE: f32 = 40.0;
+// Ground steeper than this (the normal's vertical part) is bare rock
+const STEEPEST_TREE_SLOPE: f32 = 0.85;
+
+// Offsets that give each noise layer its own pattern from the one world seed
+const CONTINENT_LAYER: u32 = 0x0000_1000;
+const HILL_LAYER: u32 = 0x0000_2000;
+const MOUNTAIN_MASK_LAYER: u32 = 0x0000_3000;
+const RIDGE_LAYER: u32 = 0x0000_4000;
+const MOISTURE_LAYER: u32 = 0x0000_5000;
+const CLIMATE_LAYER: u32 = 0x0000_6000;
+const TREE_LAYER: u32 = 0x0000_7000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Biome {
+    Grassland,
+    Desert,
+    Snow,
+    Water,
+}
+
+impl Biome {
+    // Chance that a tree cell in this biome has a tree
+    fn tree_density(&self) -> f32 {
+        match self {
+            Biome::Grassland => 0.45,
+            Biome::Desert => 0.05,
+            Biome::Snow => 0.15,
+            Biome::Water => 0.0,
+        }
+    }
+
+    fn color(&self) -> Vec3 {
+        match self {
+            Biome::Grassland => Vec3::new(0.22, 0.45, 0.18),
+            Biome::Desert => Vec3::new(0.82, 0.7, 0.45),
+            Biome::Snow => Vec3::new(0.92, 0.94, 0.97),
+            Biome::Water => Vec3::new(0.12, 0.32, 0.6),
+        }
+    }
+}
+
+// The ground's shape everywhere in the world, generated from a seed: broad lowlands whose
+// lowest basins hold lakes and seas, rolling hills, and ridged mountain ranges with canyons
+// between them. Terrain meshes are built from it, so anything that needs to stay above the
+// ground or hit it uses the same shape the player sees.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq)]
+pub struct TerrainHeightField {
+    pub seed: u32,
+}
+
+impl TerrainHeightField {
+    pub fn from_seed(seed: u32) -> Self {
+        Self { seed }
+    }
+
+    // Height of the land itself, which may be under water
+    fn land_height(&self, x: f32, z: f32) -> f32 {
+        let continent = fbm(self.seed ^ CONTINENT_LAYER, x * 0.0009, z * 0.0009, 4);
+        let hills = fbm(self.seed ^ HILL_LAYER, x * 0.006, z * 0.006, 3);
+        let mountain_mask = fbm(self.seed ^ MOUNTAIN_MASK_LAYER, x * 0.0007, z * 0.0007, 3);
+        let mountain_mask = (mountain_mask / 0.35).clamp(0.0, 1.0);
+        let ridges = ridged(self.seed ^ RIDGE_LAYER, x * 0.0025, z * 0.0025, 5);
+
+        8.0 + continent * 45.0 + hills * 10.0 + ridges * ridges * 230.0 * mountain_mask
+    }
+
+    // Height of the surface: the land, or the water over it
+    pub fn height_at(&self, x: f32, z: f32) -> f32 {
+        self.land_height(x, z).max(SEA_LEVEL)
+    }
+
+    pub fn normal_at(&self, x: f32, z: f32) -> Vec3 {
+        let step = 1.0;
+        let dx = self.height_at(x + step, z) - self.height_at(x - step, z);
+        let dz = self.height_at(x, z + step) - self.height_at(x, z - step);
+        Vec3::new(-dx, 2.0 * step, -dz).normalize()
+    }
+
+    pub fn biome_at(&self, x: f32, z: f32) -> Biome {
+        let height = self.land_height(x, z);
+        if height < SEA_LEVEL {
+            return Biome::Water;
+        }
+
+        // The snow line wanders up and down with the climate
+        let climate = fbm(self.seed ^ CLIMATE_LAYER, x * 0.0015, z * 0.0015, 2);
+        if height > SNOW_LINE + climate * 40.0 {
+            return Biome::Snow;
+        }
+
+        let moisture = fbm(self.seed ^ MOISTURE_LAYER, x * 0.001, z * 0.001, 3);
+        if moisture < -0.2 {
+            Biome::Desert
+        } else {
+            Biome::Grassland
+        }
+    }
+
+    // Vertex colour: the biome's colour, turning to rock on steep slopes
+    pub fn color_at(&self, x: f32, z: f32) -> [f32; 4] {
+        let biome = self.biome_at(x, z);
+        let rock = Vec3::new(0.42, 0.4, 0.38);
+        let steepness = if biome == Biome::Water {
+            0.0
+        } else {
+            ((0.9 - self.normal_at(x, z).y) / 0.2).clamp(0.0, 1.0)
+        };
+        // A little per-vertex variation so flat ground doesn't look painted
+        let shade = 0.92 + hash01(self.seed, x as i32, z as i32) * 0.16;
+        let color = biome.color().lerp(rock, steepness) * shade;
+        [color.x, color.y, color.z, 1.0]
+    }
+
+    // The tree in a TREE_CELL_SIZE cell, if it has one
+    pub fn tree_in_cell(&self, cell: IVec2) -> Option<Tree> {
+        let seed = self.seed ^ TREE_LAYER;
+        let roll = hash01(seed, cell.x, cell.y);
+        let x = (cell.x as f32 + 0.1 + hash01(seed.wrapping_add(1), cell.x, cell.y) * 0.8) * TREE_CELL_SIZE;
+        let z = (cell.y as f32 + 0.1 + hash01(seed.wrapping_add(2), cell.x, cell.y) * 0.8) * TREE_CELL_SIZE;
+
+        let biome = self.biome_at(x, z);
+        if roll >= biome.tree_density() || self.normal_at(x, z).y < STEEPEST_TREE_SLOPE {
+            return None;
+        }
+
+        let base = Vec3::new(x, self.height_at(x, z), z);
+        let variant = hash(seed.wrapping_add(3), cell.x, cell.y) % TREE_VARIANTS;
+        let kind = match biome {
+            Biome::Desert => TreeKind::Cactus,
+            Biome::Snow => TreeKind::Pine,
+            _ if base.y > SNOW_LINE * 0.5 => TreeKind::Pine,
+            _ => TreeKind::Broadleaf,
+        };
+        Some(Tree::new(kind, variant, base))
+    }
+
+    // Every tree whose cell overlaps the square of `reach` around `position`
+    pub fn trees_near(&self, position: Vec3, reach: f32) -> impl Iterator<Item = Tree> + '_ {
+        let min = ((Vec2::new(position.x, position.z) - reach) / TREE_CELL_SIZE).floor().as_ivec2();
+        let max = ((Vec2::new(position.x, position.z) + reach) / TREE_CELL_SIZE).floor().as_ivec2();
+        (min.x..=max.x)
+            .flat_map(move |x| (min.y..=max.y).map(move |z| IVec2::new(x, z)))
+            .filter_map(|cell| self.tree_in_cell(cell))
+    }
+
+    pub fn trees_in_chunk(&self, coord: IVec2) -> impl Iterator<Item = Tree> + '_ {
+        let cells_per_chunk = (TERRAIN_CHUNK_SIZE / TREE_CELL_SIZE) as i32;
+        let first = coord * cells_per_chunk - cells_per_chunk / 2;
+        (first.x..first.x + cells_per_chunk)
+            .flat_map(move |x| (first.y..first.y + cells_per_chunk).map(move |z| IVec2::new(x, z)))
+            .filter_map(|cell| self.tree_in_cell(cell))
+    }
+}
+
+pub const TREE_VARIANTS: u32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeKind {
+    Broadleaf,
+    Pine,
+    Cactus,
+}
+
+// A tree's solid parts: a trunk standing on `base` and, except for cacti, a round canopy on top
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tree {
+    pub kind: TreeKind,
+    pub variant: u32,
+    pub base: Vec3,
+    pub trunk_radius: f32,
+    pub trunk_height: f32,
+    pub canopy_radius: f32,
+}
+
+impl Tree {
+    pub fn new(kind: TreeKind, variant: u32, base: Vec3) -> Self {
+        let v = variant as f32;
+        // Broadleaf and pine trunks match the sizes create_tree_mesh gives the same variant
+        let (trunk_radius, trunk_height, canopy_radius) = match kind {
+            TreeKind::Broadleaf => (1.0 + v * 0.1, 15.0 + v, 8.0 + v * 0.8),
+            TreeKind::Pine => (1.0 + v * 0.1, 15.0 + v, 5.0 + v * 0.5),
+            TreeKind::Cactus => (1.2, 6.0 + v * 1.5, 0.0),
+        };
+        Self {
+            kind,
+            variant,
+            base,
+            trunk_radius,
+            trunk_height,
+            canopy_radius,
+        }
+    }
+
+    pub fn canopy_center(&self) -> Vec3 {
+        self.base + Vec3::Y * (self.trunk_height + self.canopy_radius * 0.5)
+    }
+}
+
+// Damage from hitting a surface with this outward normal. Only the speed into the surface
+// counts, which is the impact speed scaled by how steeply the aircraft hit.
+pub fn impact_damage(velocity: Vec3, normal: Vec3) -> f32 {
+    let speed_into = -velocity.dot(normal);
+    if speed_into > SAFE_IMPACT_SPEED {
+        speed_into * CRASH_DAMAGE_PER_SPEED
+    } else {
+        0.0
+    }
+}
+
+// Pushes the aircraft back out of a surface and turns it to fly along it, so it doesn't hit
+// the same surface again on the next step
+fn deflect_off_surface(transform: &mut Transform, aircraft: &mut Aircraft, normal: Vec3, depth: f32) {
+    transform.translation += normal * depth;
+
+    let speed_into = -aircraft.velocity.dot(normal);
+    if speed_into > 0.0 {
+        aircraft.velocity += normal * speed_into;
+    }
+
+    let forward = *transform.forward();
+    if forward.dot(normal) < 0.0 {
+        let along = (forward - normal * forward.dot(normal)).normalize_or_zero();
+        if along != Vec3::ZERO {
+            transform.rotation = Quat::from_rotation_arc(forward, along) * transform.rotation;
+        }
+    }
+}
+
+pub fn terrain_collision_system(
+    terrain: Res<TerrainHeightField>,
+    mut player_query: Query<(&mut Transform, &mut Aircraft, &mut Health)>,
+    mut camera_query: Query<&mut FlightCamera>,
+    mut game_state: ResMut<NextState<GameState>>,
+    active_powerups: Res<ActivePowerUps>,
+) {
+    let Ok((mut transform, mut aircraft, mut health)) = player_query.single_mut() else {
+        return;
+    };
+
+    // Each contact is the surface's outward normal and how far the aircraft is inside it
+    let mut contacts = Vec::new();
+
+    let position = transform.translation;
+    let ground = terrain.height_at(position.x, position.z) + AIRCRAFT_CLEARANCE;
+    if position.y < ground {
+        let normal = terrain.normal_at(position.x, position.z);
+        contacts.push((normal, (ground - position.y) * normal.y));
+    }
+
+    for tree in terrain.trees_near(position, TREE_CELL_SIZE) {
+        let base = tree.base;
+
+        if tree.canopy_radius > 0.0 {
+            let canopy_offset = position - tree.canopy_center();
+            let canopy_reach = tree.canopy_radius + AIRCRAFT_RADIUS;
+            if canopy_offset.length_squared() < canopy_reach * canopy_reach {
+                let normal = canopy_offset.try_normalize().unwrap_or(Vec3::Y);
+                contacts.push((normal, canopy_reach - canopy_offset.length()));
+                continue;
+            }
+        }
+
+        let trunk_offset = Vec3::new(position.x - base.x, 0.0, position.z - base.z);
+        let trunk_reach = tree.trunk_radius + AIRCRAFT_RADIUS;
+        let beside_trunk = position.y > base.y && position.y < base.y + tree.trunk_height;
+        if beside_trunk && trunk_offset.length_squared() < trunk_reach * trunk_reach {
+            let normal = trunk_offset.try_normalize().unwrap_or(Vec3::X);
+            contacts.push((normal, trunk_reach - trunk_offset.length()));
+        }
+    }
+
+    for (normal, depth) in contacts {
+        let mut damage = impact_damage(aircraft.velocity, normal);
+        if active_powerups.shield {
+            damage *= 0.2; // Shield absorbs 80% of the impact
+        }
+        deflect_off_surface(&mut transform, &mut aircraft, normal, depth);
+
+        if damage > 0.0 {
+            health.current = (health.current - damage).max(0.0);
+            if let Ok(mut camera) = camera_query.single_mut() {
+                camera.shake_amount = (damage * 0.1).clamp(2.0, 8.0);
+                camera.shake_timer = 0.4;
+            }
+            if health.current <= 0.0 {
+                game_state.set(GameState::GameOver);
+            }
+        }
+    }
+}
+
+pub fn keep_enemies_above_terrain(
+    terrain: Res<TerrainHeightField>,
+    mut enemies: Query<&mut Transform, With<Enemy>>,
+) {
+    for mut transform in enemies.iter_mut() {
+        let floor = terrain.height_at(transform.translation.x, transform.translation.z) + ENEMY_MIN_ALTITUDE;
+        transform.translation.y = transform.translation.y.max(floor);
+    }
+}
+
+#[derive(Component)]
+pub struct TerrainChunk;
+
+// Chunk (x, y) is centred on (x, y) * TERRAIN_CHUNK_SIZE in world x and z
+pub fn chunk_coord(position: Vec3) -> IVec2 {
+    IVec2::new(
+        ((position.x + TERRAIN_CHUNK_SIZE * 0.5) / TERRAIN_CHUNK_SIZE).floor() as i32,
+        ((position.z + TERRAIN_CHUNK_SIZE * 0.5) / TERRAIN_CHUNK_SIZE).floor() as i32,
+    )
+}
+
+fn chunk_distance(a: IVec2, b: IVec2) -> i32 {
+    (a - b).abs().max_element()
+}
+
+// Meshes and materials shared by every chunk and tree
+struct TerrainAssets {
+    ground: Handle<StandardMaterial>,
+    // Near and far meshes for each tree variant
+    trunks: Vec<(Handle<Mesh>, Handle<Mesh>)>,
+    cacti: Vec<(Handle<Mesh>, Handle<Mesh>)>,
+    canopy: (Handle<Mesh>, Handle<Mesh>),
+    pine_canopy: (Handle<Mesh>, Handle<Mesh>),
+    bark: Handle<StandardMaterial>,
+    leaves: Handle<StandardMaterial>,
+    needles: Handle<StandardMaterial>,
+    cactus: Handle<StandardMaterial>,
+}
+
+impl TerrainAssets {
+    fn new(meshes: &mut Assets<Mesh>, materials: &mut Assets<StandardMaterial>) -> Self {
+        let material = |color: Color| StandardMaterial {
+            base_color: color,
+            perceptual_roughness: 0.9,
+            ..default()
+        };
+        Self {
+            // White so the vertex colours show through
+            ground: materials.add(StandardMaterial {
+                base_color: Color::WHITE,
+                metallic: 0.0,
+                perceptual_roughness: 0.9,
+                double_sided: true,
+                cull_mode: None,
+                ..default()
+            }),
+            trunks: (0..TREE_VARIANTS)
+                .map(|variant| {
+                    let tree = Tree::new(TreeKind::Broadleaf, variant, Vec3::ZERO);
+                    (
+                        meshes.add(create_tree_mesh(variant)),
+                        meshes.add(create_low_poly_trunk_mesh(tree.trunk_radius, tree.trunk_height)),
+                    )
+                })
+                .collect(),
+            cacti: (0..TREE_VARIANTS)
+                .map(|variant| {
+                    let tree = Tree::new(TreeKind::Cactus, variant, Vec3::ZERO);
+                    let cylinder = Cylinder::new(tree.trunk_radius, tree.trunk_height);
+                    (
+                        meshes.add(cylinder.mesh().resolution(12)),
+                        meshes.add(cylinder.mesh().resolution(4)),
+                    )
+                })
+                .collect(),
+            canopy: (
+                meshes.add(Sphere::new(1.0).mesh().ico(3).unwrap()),
+                meshes.add(Sphere::new(1.0).mesh().ico(0).unwrap()),
+            ),
+            pine_canopy: (
+                meshes.add(Cone { radius: 1.0, height: 2.5 }.mesh().resolution(16)),
+                meshes.add(Cone { radius: 1.0, height: 2.5 }.mesh().resolution(5)),
+            ),
+            bark: materials.add(material(Color::srgb(0.4, 0.3, 0.2))),
+            leaves: materials.add(material(Color::srgb(0.2, 0.6, 0.2))),
+            needles: materials.add(material(Color::srgb(0.12, 0.35, 0.2))),
+            cactus: materials.add(material(Color::srgb(0.3, 0.55, 0.25))),
+        }
+    }
+}
+
+pub struct LoadedChunk {
+    pub entity: Entity,
+    // Index into TERRAIN_LOD_RESOLUTIONS of the mesh it's showing
+    pub lod: usize,
+}
+
+// Keeps terrain loaded in a ring around the aircraft, finer close up and coarser further out.
+// Meshes are built on the async compute pool and cached by chunk coordinate and detail level,
+// so the world never runs out and revisited ground doesn't have to be rebuilt. Only what's
+// drawn is streamed: collisions ask the height field.
+#[derive(Resource, Default)]
+pub struct TerrainStreamer {
+    pub loaded: HashMap<IVec2, LoadedChunk>,
+    building: HashMap<(IVec2, usize), Task<Mesh>>,
+    cache: HashMap<(IVec2, usize), Handle<Mesh>>,
+    assets: Option<TerrainAssets>,
+    // The height field the cached meshes were built from
+    field: Option<TerrainHeightField>,
+}
+
+impl TerrainStreamer {
+    // Forgets the chunks of the last run, which have already been despawned
+    pub fn reset(&mut self, field: TerrainHeightField) {
+        self.loaded.clear();
+        self.building.clear();
+        if self.field != Some(field) {
+            self.cache.clear();
+            self.field = Some(field);
+        }
+    }
+
+    pub fn loaded_lods(&self) -> impl Iterator<Item = usize> + '_ {
+        self.loaded.values().map(|chunk| chunk.lod)
+    }
+
+    // A cached mesh for `coord` at `lod`, or failing that the closest level that is cached
+    fn best_cached(&self, coord: IVec2, lod: usize) -> Option<(usize, Handle<Mesh>)> {
+        let mut levels: Vec<usize> = (0..TERRAIN_LOD_RESOLUTIONS.len()).collect();
+        levels.sort_by_key(|level| level.abs_diff(lod));
+        levels
+            .into_iter()
+            .find_map(|level| self.cache.get(&(coord, level)).map(|mesh| (level, mesh.clone())))
+    }
+}
+
+// Terrain detail for a chunk this many chunks from the aircraft
+pub fn terrain_lod(distance: i32) -> usize {
+    (distance.max(0) as usize).min(TERRAIN_LOD_RESOLUTIONS.len() - 1)
+}
+
+fn spawn_chunk(
+    commands: &mut Commands,
+    assets: &TerrainAssets,
+    terrain: &TerrainHeightField,
+    coord: IVec2,
+    mesh: Handle<Mesh>,
+) -> Entity {
+    let origin = Vec3::new(coord.x as f32 * TERRAIN_CHUNK_SIZE, 0.0, coord.y as f32 * TERRAIN_CHUNK_SIZE);
+    commands.spawn((
+        Mesh3d(mesh),
+        MeshMaterial3d(assets.ground.clone()),
+        Transform::from_translation(origin),
+        Visibility::default(),
+        TerrainChunk,
+        GameEntity,
+    )).with_children(|chunk| {
+        for tree in terrain.trees_in_chunk(coord) {
+            let base = tree.base - origin;
+            let variant = tree.variant as usize;
+            let (trunk, material) = match tree.kind {
+                TreeKind::Cactus => (&assets.cacti[variant], &assets.cactus),
+                _ => (&assets.trunks[variant], &assets.bark),
+            };
+            // Cylinders are centred on their middle, the tree meshes stand on their base
+            let trunk_offset = if tree.kind == TreeKind::Cactus { tree.trunk_height * 0.5 } else { 0.0 };
+            chunk.spawn((
+                Mesh3d(trunk.0.clone()),
+                MeshMaterial3d(material.clone()),
+                Transform::from_translation(base + Vec3::Y * trunk_offset),
+                DistanceLod::new(trunk.0.clone(), trunk.1.clone(), TREE_LOD_DISTANCE),
+            ));
+
+            if tree.canopy_radius > 0.0 {
+                let (canopy, material) = if tree.kind == TreeKind::Pine {
+                    (&assets.pine_canopy, &assets.needles)
+                } else {
+                    (&assets.canopy, &assets.leaves)
+                };
+                chunk.spawn((
+                    Mesh3d(canopy.0.clone()),
+                    MeshMaterial3d(material.clone()),
+                    Transform::from_translation(tree.canopy_center() - origin)
+                        .with_scale(Vec3::splat(tree.canopy_radius)),
+                    DistanceLod::new(canopy.0.clone(), canopy.1.clone(), TREE_LOD_DISTANCE),
+                ));
+            }
+        }
+    }).id()
+}
+
+pub fn stream_terrain_chunks(
+    mut commands: Commands,
+    mut streamer: ResMut<TerrainStreamer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    terrain: Res<TerrainHeightField>,
+    player_query: Query<&Transform, With<Aircraft>>,
+) {
+    let Ok(player_transform) = player_query.single() else {
+        return;
+    };
+    let center = chunk_coord(player_transform.translation);
+    let streamer = &mut *streamer;
+
+    if streamer.assets.is_none() {
+        streamer.assets = Some(TerrainAssets::new(&mut meshes, &mut materials));
+    }
+
+    // Collect meshes that finished building
+    let mut finished = Vec::new();
+    streamer.building.retain(|key, task| match block_on(future::poll_once(task)) {
+        Some(mesh) => {
+            finished.push((*key, mesh));
+            false
+        }
+        None => true,
+    });
+    for (key, mesh) in finished {
+        streamer.cache.insert(key, meshes.add(mesh));
+    }
+
+    // Unload chunks a little past the ring, so flying along a chunk edge doesn't keep swapping them
+    streamer.loaded.retain(|coord, chunk| {
+        let keep = chunk_distance(*coord, center) <= TERRAIN_VIEW_RADIUS + 1;
+        if !keep {
+            commands.entity(chunk.entity).despawn();
+        }
+        keep
+    });
+
+    // Nearest chunks first, so the ground under the aircraft appears before the horizon
+    let mut wanted: Vec<IVec2> = (-TERRAIN_VIEW_RADIUS..=TERRAIN_VIEW_RADIUS)
+        .flat_map(|x| (-TERRAIN_VIEW_RADIUS..=TERRAIN_VIEW_RADIUS).map(move |z| center + IVec2::new(x, z)))
+        .collect();
+    wanted.sort_by_key(|coord| chunk_distance(*coord, center));
+
+    let task_pool = AsyncComputeTaskPool::get();
+    for coord in wanted {
+        let lod = terrain_lod(chunk_distance(coord, center));
+        if streamer.loaded.get(&coord).is_some_and(|chunk| chunk.lod == lod) {
+            continue;
+        }
+
+        if !streamer.cache.contains_key(&(coord, lod)) {
+            streamer.building.entry((coord, lod)).or_insert_with(|| {
+                let field = *terrain;
+                let offset = coord.as_vec2() * TERRAIN_CHUNK_SIZE;
+                task_pool.spawn(async move {
+                    create_terrain_chunk(
+                        TERRAIN_CHUNK_SIZE,
+                        TERRAIN_LOD_RESOLUTIONS[lod],
+                        TERRAIN_SKIRT_DEPTH,
+                        |x, z| field.height_at(x + offset.x, z + offset.y),
+                        |x, z| field.color_at(x + offset.x, z + offset.y),
+                    )
+                })
+            });
+        }
+
+        // Show the closest detail level that's ready; a loaded chunk keeps its mesh until the
+        // level it should have is built
+        let Some((ready_lod, mesh)) = streamer.best_cached(coord, lod) else {
+            continue;
+        };
+        match streamer.loaded.get_mut(&coord) {
+            Some(chunk) if ready_lod == lod => {
+                commands.entity(chunk.entity).insert(Mesh3d(mesh));
+                chunk.lod = lod;
+            }
+            Some(_) => {}
+            None => {
+                let Some(assets) = &streamer.assets else {
+                    return;
+                };
+                let entity = spawn_chunk(&mut commands, assets, &terrain, coord, mesh);
+                streamer.loaded.insert(coord, LoadedChunk { entity, lod: ready_lod });
+            }
+        }
+    }
+
+    // Drop the cached meshes farthest from the aircraft once there are too many
+    if streamer.cache.len() > MAX_CACHED_CHUNKS {
+        let mut cached: Vec<(IVec2, usize)> = streamer.cache.keys().copied().collect();
+        cached.sort_by_key(|(coord, _)| std::cmp::Reverse(chunk_distance(*coord, center)));
+        for key in cached.into_iter().take(streamer.cache.len() - MAX_CACHED_CHUNKS) {
+            if let Some(mesh) = streamer.cache.remove(&key) {
+                meshes.remove(&mesh);
+            }
+        }
+    }
+}
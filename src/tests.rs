@@ -1,6 +1,38 @@
 #[cfg(test)]
 mod tests {
     use crate::game_state::{GameMode, GameStats, UpgradeData, get_speed_bonus, get_maneuverability_bonus, get_magnet_range, get_score_multiplier, get_upgrade_cost};
+    use bevy::prelude::*;
+    use crate::{Aircraft, GameEntity};
+    use crate::enemies::{Crashing, DamageState, Enemy, EnemyBehaviorState, EnemyBullet, Health, PilotPersonality, PlayerBullet, damage_enemy, lead_position, spawn_formation_at};
+    use bevy::ecs::system::RunSystemOnce;
+    use crate::particles::{EmitterOf, ParticleBurst, ParticleEmitter, ParticleEmitters, ParticlePreset};
+    use bevy::render::primitives::Aabb;
+    use crate::assets::SharedAssets;
+    use crate::pool::{EntityPool, Overflow, PoolConfig};
+    use crate::missiles::{Missile, MissileLock, proportional_navigation};
+    use crate::powerups::{PowerUpEffect, PowerUpType};
+    use crate::weapons::{Loadout, Weapon, WeaponSlot};
+    use crate::targeting::TargetLock;
+    use crate::radar::{Radar, RadarAltitude, radar_blip};
+    use crate::archetypes::{EnemyArchetype, EnemyArchetypes, pick_weighted};
+    use crate::ground::{FlakShell, GroundSites, GroundUnit, GroundUnitKind, SamMissile, damage_ground_unit, ground_site_in_cell};
+    use crate::targets::Target;
+    use crate::boss::{Boss, BossHit, BossKind, BossPart, BossPartKind, BossPhase, BossWaves, boss_hit, damage_boss, damage_boss_part};
+    use crate::game_state::ChallengeTimer;
+    use crate::game_state::{GameRng, GameState, pause_run};
+    use bevy::input::mouse::MouseMotion;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+    use crate::headless::{HeadlessOptions, headless_app, simulate, simulate_replay, start_run};
+    use crate::replay::{PlayerInput, Replay, ReplayError, ReplayFrame, ReplayPlayback, ReplayRecorder, REPLAY_VERSION, restart_playback, start_playback};
+    use crate::ghost::{GhostAircraft, GhostRun, GhostSample, GhostState, GhostStore, GHOST_VERSION, load_ghost, write_ghost};
+    use crate::physics::{InterpolatedTransform, segment_hits_sphere};
+    use crate::spatial::SpatialGrid;
+    use crate::flight::{FlightModel, bank_angle, level_attitude, lift_coefficient, turn_in_body_frame};
+    use crate::terrain::{AIRCRAFT_CLEARANCE, SEA_LEVEL, TERRAIN_LOD_RESOLUTIONS, TERRAIN_VIEW_RADIUS, Biome, TerrainChunk, TerrainHeightField, TerrainStreamer, TreeKind, chunk_coord, impact_damage};
+    use crate::lod::{DebugOverlay, ENTITY_BUDGET, TRIANGLE_BUDGET, triangle_count};
+    use crate::models::{AircraftModel, AircraftModels, ControlSurface, body_rates, create_terrain_chunk};
+    use crate::save::{SaveData, SavedUpgrades, SaveError, SAVE_VERSION, backup_corrupt_save, load_save_data, parse_save, write_save_data};
 
     #[test]
     fn test_game_stats_default() {
@@ -103,4 +135,1899 @@ mod tests {
             }
         }
     }
-}
\ No newline at end of file
+    
+    fn temp_save_path(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("sky-hunter-test-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("save.ron")
+    }
+
+    #[test]
+    fn test_save_round_trip() {
+        let path = temp_save_path("round-trip");
+        let stats = GameStats {
+            coins: 1234,
+            high_score: 9000,
+            ..Default::default()
+        };
+        let upgrades = UpgradeData {
+            speed_level: 3,
+            magnet_level: 2,
+            ..Default::default()
+        };
+        
+        write_save_data(&path, &SaveData::capture(&stats, &upgrades)).unwrap();
+        let loaded = load_save_data(&path).unwrap().unwrap();
+        
+        let mut restored_stats = GameStats::default();
+        let mut restored_upgrades = UpgradeData::default();
+        loaded.apply(&mut restored_stats, &mut restored_upgrades);
+        assert_eq!(restored_stats.coins, 1234);
+        assert_eq!(restored_stats.high_score, 9000);
+        assert_eq!(restored_upgrades.speed_level, 3);
+        assert_eq!(restored_upgrades.magnet_level, 2);
+        assert_eq!(restored_upgrades.multiplier_level, 1);
+    }
+
+    #[test]
+    fn test_missing_save_is_not_an_error() {
+        let path = temp_save_path("missing");
+        assert!(load_save_data(&path).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_save_migration_fills_missing_fields() {
+        // An unversioned file with only some fields should load with defaults for the rest
+        let data = parse_save("(coins: 50)").unwrap();
+        assert_eq!(data.version, SAVE_VERSION);
+        assert_eq!(data.coins, 50);
+        assert_eq!(data.high_score, 0);
+        assert_eq!(data.upgrades.speed_level, 1);
+        assert_eq!(data.upgrades.magnet_level, 0);
+    }
+
+    #[test]
+    fn test_newer_save_version_is_rejected() {
+        let contents = format!("(version: {}, coins: 10)", SAVE_VERSION + 1);
+        assert!(matches!(parse_save(&contents), Err(SaveError::UnsupportedVersion(_))));
+    }
+
+    #[test]
+    fn test_corrupt_save_is_backed_up() {
+        let path = temp_save_path("corrupt");
+        std::fs::write(&path, "this is not ron {{{").unwrap();
+        assert!(matches!(load_save_data(&path), Err(SaveError::Corrupt(_))));
+        
+        let backup = backup_corrupt_save(&path).unwrap();
+        assert!(!path.exists());
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), "this is not ron {{{");
+    }
+    
+    #[test]
+    fn test_headless_simulation_runs() {
+        let tick_rate = 60.0;
+        let mut app = headless_app(tick_rate);
+        start_run(&mut app, GameMode::FreePlay, None);
+        let ticks = simulate(&mut app, 5.0, tick_rate);
+        
+        assert_eq!(ticks, 300);
+        assert_eq!(*app.world().resource::<State<GameState>>().get(), GameState::Playing);
+        let stats = app.world().resource::<GameStats>();
+        assert!((stats.time_played - 5.0).abs() < 0.1);
+        
+        let aircraft_count = app.world_mut().query::<&Aircraft>().iter(app.world()).count();
+        assert_eq!(aircraft_count, 1);
+    }
+
+    #[test]
+    fn test_headless_options_parse() {
+        let args: Vec<String> = ["sky-hunter", "--headless", "--mode", "time-attack", "--seconds", "30", "--seed", "1234"]
+            .iter().map(|arg| arg.to_string()).collect();
+        let options = HeadlessOptions::parse(&args).unwrap();
+        assert_eq!(options.mode, GameMode::TimeAttack);
+        assert_eq!(options.seconds, 30.0);
+        assert_eq!(options.seed, Some(1234));
+        
+        let bad: Vec<String> = ["sky-hunter", "--headless", "--mode", "dogfight"]
+            .iter().map(|arg| arg.to_string()).collect();
+        assert!(HeadlessOptions::parse(&bad).is_err());
+    }
+    
+    // The score plus the positions of the player, every target and every enemy
+    fn world_snapshot(app: &mut App) -> (u32, Vec<Vec3>) {
+        let world = app.world_mut();
+        let mut positions: Vec<Vec3> = world
+            .query_filtered::<&Transform, With<Aircraft>>()
+            .iter(world)
+            .map(|transform| transform.translation)
+            .collect();
+        positions.extend(
+            world
+                .query_filtered::<&Transform, With<Target>>()
+                .iter(world)
+                .map(|transform| transform.translation),
+        );
+        positions.extend(
+            world
+                .query_filtered::<&Transform, With<Enemy>>()
+                .iter(world)
+                .map(|transform| transform.translation),
+        );
+        (world.resource::<GameStats>().score, positions)
+    }
+    
+    fn seeded_run_snapshot(seed: u64) -> (u32, Vec<Vec3>) {
+        let tick_rate = 60.0;
+        let mut app = headless_app(tick_rate);
+        start_run(&mut app, GameMode::Survival, Some(seed));
+        simulate(&mut app, 8.0, tick_rate);
+        world_snapshot(&mut app)
+    }
+    
+    #[test]
+    fn test_same_seed_reproduces_run() {
+        let first = seeded_run_snapshot(42);
+        let second = seeded_run_snapshot(42);
+        assert!(!first.1.is_empty());
+        assert_eq!(first, second);
+        
+        let other = seeded_run_snapshot(43);
+        assert_ne!(first.1, other.1);
+    }
+    
+    fn test_replay() -> Replay {
+        let idle = PlayerInput::default();
+        let turning = PlayerInput {
+            mouse_delta: Vec2::new(12.5, -3.0),
+            roll_left: true,
+            fire: true,
+            cycle_secondary: true,
+            ..default()
+        };
+        Replay {
+            seed: 987654321,
+            mode: GameMode::TimeAttack,
+            flight_model: FlightModel::Simulation,
+            upgrades: SavedUpgrades {
+                speed_level: 3,
+                maneuverability_level: 2,
+                magnet_level: 1,
+                multiplier_level: 4,
+            },
+            archetypes: 0x5eed_f00d,
+            frames: vec![
+                ReplayFrame { delta: Duration::from_nanos(16_666_667), input: idle },
+                ReplayFrame { delta: Duration::from_nanos(33_333_333), input: turning },
+            ],
+        }
+    }
+    
+    #[test]
+    fn test_replay_encode_round_trip() {
+        let replay = test_replay();
+        let bytes = replay.encode();
+        assert_eq!(Replay::decode(&bytes).unwrap(), replay);
+        
+        // Header, one idle frame and one frame carrying a mouse delta
+        assert_eq!(bytes.len(), 44 + 6 + 14);
+        
+        assert!(matches!(Replay::decode(&bytes[..bytes.len() - 1]), Err(ReplayError::Corrupt(_))));
+        assert!(matches!(Replay::decode(b"not a replay"), Err(ReplayError::Corrupt(_))));
+        
+        let mut future = bytes.clone();
+        future[4..6].copy_from_slice(&(REPLAY_VERSION + 1).to_le_bytes());
+        assert!(matches!(Replay::decode(&future), Err(ReplayError::UnsupportedVersion(_))));
+    }
+    
+    #[test]
+    fn test_replay_playback_matches_recording() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::FreePlay, Some(7));
+        
+        // Fly with changing inputs and an uneven frame rate
+        for tick in 0..480u32 {
+            let world = app.world_mut();
+            let frame_time = if tick % 3 == 0 { 1.0 / 30.0 } else { 1.0 / 90.0 };
+            world.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(frame_time)));
+            
+            let mut keyboard = world.resource_mut::<ButtonInput<KeyCode>>();
+            keyboard.release_all();
+            keyboard.press(KeyCode::KeyF);
+            if (tick / 60) % 2 == 0 {
+                keyboard.press(KeyCode::KeyA);
+            } else {
+                keyboard.press(KeyCode::KeyW);
+            }
+            if tick % 10 == 0 {
+                world.send_event(MouseMotion { delta: Vec2::new(8.0, (tick % 7) as f32 - 3.0) });
+            }
+            
+            app.update();
+        }
+        assert_eq!(*app.world().resource::<State<GameState>>().get(), GameState::Playing);
+        let recorded = world_snapshot(&mut app);
+        let replay = app.world().resource::<ReplayRecorder>().current.clone().unwrap();
+        assert_eq!(replay.frames.len(), 480);
+        
+        // Play it back through the file format in an app ticking at a different rate
+        let replay = Replay::decode(&replay.encode()).unwrap();
+        let mut playback_app = headless_app(144.0);
+        start_playback(playback_app.world_mut(), replay).unwrap();
+        let ticks = simulate_replay(&mut playback_app);
+        
+        assert_eq!(ticks, 480);
+        assert_eq!(world_snapshot(&mut playback_app), recorded);
+    }
+    
+    #[test]
+    fn test_replay_of_a_paused_run_matches_recording() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::TargetHunt, Some(11));
+        let fly = |app: &mut App, ticks: u32| {
+            for tick in 0..ticks {
+                let world = app.world_mut();
+                let frame_time = if tick % 4 == 0 { 1.0 / 40.0 } else { 1.0 / 75.0 };
+                world.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(frame_time)));
+                let mut keyboard = world.resource_mut::<ButtonInput<KeyCode>>();
+                keyboard.release_all();
+                keyboard.press(KeyCode::KeyF);
+                if tick % 90 < 30 {
+                    keyboard.press(KeyCode::KeyD);
+                }
+                app.update();
+            }
+        };
+        fly(&mut app, 200);
+        
+        // Sit in the pause menu for a while at a frame rate the run never used, then resume
+        app.world_mut().run_system_once(|mut next_state: ResMut<NextState<GameState>>, mut virtual_time: ResMut<Time<Virtual>>| {
+            pause_run(&mut next_state, &mut virtual_time);
+        }).unwrap();
+        app.world_mut().insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(1.0 / 23.0)));
+        for _ in 0..37 {
+            app.update();
+        }
+        assert_eq!(*app.world().resource::<State<GameState>>().get(), GameState::Paused);
+        app.world_mut().resource_mut::<NextState<GameState>>().set(GameState::Playing);
+        fly(&mut app, 240);
+        
+        assert_eq!(*app.world().resource::<State<GameState>>().get(), GameState::Playing);
+        let recorded = world_snapshot(&mut app);
+        let replay = app.world().resource::<ReplayRecorder>().current.clone().unwrap();
+        let frames = replay.frames.len() as u32;
+        
+        let mut playback_app = headless_app(60.0);
+        start_playback(playback_app.world_mut(), replay).unwrap();
+        assert_eq!(simulate_replay(&mut playback_app), frames);
+        assert_eq!(world_snapshot(&mut playback_app), recorded);
+    }
+    
+    #[test]
+    fn test_restarting_a_replay_plays_it_from_the_start() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::TargetHunt, Some(13));
+        for tick in 0..300 {
+            let mut keyboard = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+            keyboard.release_all();
+            keyboard.press(KeyCode::KeyF);
+            if tick % 80 < 25 {
+                keyboard.press(KeyCode::KeyA);
+            }
+            app.update();
+        }
+        let recorded = world_snapshot(&mut app);
+        let replay = app.world().resource::<ReplayRecorder>().current.clone().unwrap();
+        let frames = replay.frames.len() as u32;
+        
+        let mut playback_app = headless_app(60.0);
+        start_playback(playback_app.world_mut(), replay).unwrap();
+        for _ in 0..120 {
+            playback_app.update();
+        }
+        playback_app.world_mut().run_system_once(|mut next_state: ResMut<NextState<GameState>>, mut virtual_time: ResMut<Time<Virtual>>| {
+            pause_run(&mut next_state, &mut virtual_time);
+        }).unwrap();
+        playback_app.update();
+        
+        // What Restart in the pause menu does while a replay is being watched
+        playback_app.world_mut().run_system_once(|mut commands: Commands, game_entities: Query<Entity, With<GameEntity>>| {
+            for entity in game_entities.iter() {
+                commands.entity(entity).despawn();
+            }
+            commands.queue(restart_playback);
+        }).unwrap();
+        playback_app.update();
+        
+        assert_eq!(playback_app.world().resource::<ReplayPlayback>().frame, 1);
+        assert_eq!(playback_app.world().resource::<GameRng>().seed, 13);
+        assert_eq!(simulate_replay(&mut playback_app) + 1, frames);
+        assert_eq!(world_snapshot(&mut playback_app), recorded);
+    }
+    
+    #[test]
+    fn test_replays_refuse_other_enemy_archetypes() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::FreePlay, Some(5));
+        for _ in 0..60 {
+            app.update();
+        }
+        let replay = app.world().resource::<ReplayRecorder>().current.clone().unwrap();
+        
+        // A fresh load of the same files plays it
+        let mut playback_app = headless_app(60.0);
+        assert_eq!(replay.archetypes, playback_app.world().resource::<EnemyArchetypes>().fingerprint());
+        start_playback(playback_app.world_mut(), replay.clone()).unwrap();
+        assert!(playback_app.world().get_resource::<ReplayPlayback>().is_some());
+        
+        // Tuning any value in an archetype, as saving its file would, makes it refuse to start
+        let mut edited_app = headless_app(60.0);
+        let mut archetypes = edited_app.world_mut().resource_mut::<Assets<EnemyArchetype>>();
+        let id = archetypes.ids().next().unwrap();
+        archetypes.get_mut(id).unwrap().speed += 1.0;
+        edited_app.update();
+        assert_ne!(replay.archetypes, edited_app.world().resource::<EnemyArchetypes>().fingerprint());
+        assert!(matches!(start_playback(edited_app.world_mut(), replay), Err(ReplayError::ArchetypeMismatch)));
+        assert!(edited_app.world().get_resource::<ReplayPlayback>().is_none());
+        edited_app.update();
+        assert_eq!(*edited_app.world().resource::<State<GameState>>().get(), GameState::MainMenu);
+    }
+    
+    #[test]
+    fn test_archetype_edits_wait_for_the_run_to_end() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::FreePlay, Some(5));
+        for _ in 0..30 {
+            app.update();
+        }
+        let fingerprint = app.world().resource::<EnemyArchetypes>().fingerprint();
+        
+        let mut archetypes = app.world_mut().resource_mut::<Assets<EnemyArchetype>>();
+        let id = archetypes.ids().next().unwrap();
+        archetypes.get_mut(id).unwrap().speed += 1.0;
+        for _ in 0..30 {
+            app.update();
+        }
+        assert_eq!(app.world().resource::<EnemyArchetypes>().fingerprint(), fingerprint);
+        assert_eq!(app.world().resource::<ReplayRecorder>().current.as_ref().unwrap().archetypes, fingerprint);
+        
+        app.world_mut().resource_mut::<NextState<GameState>>().set(GameState::MainMenu);
+        app.update();
+        app.update();
+        assert_ne!(app.world().resource::<EnemyArchetypes>().fingerprint(), fingerprint);
+    }
+    
+    fn test_ghost() -> GhostRun {
+        let sample = |time: f32, x: f32, score: u32| GhostSample {
+            time,
+            translation: [x, 50.0, 0.0],
+            rotation: Quat::IDENTITY.to_array(),
+            score,
+        };
+        GhostRun {
+            score: 300,
+            samples: vec![sample(0.0, 0.0, 0), sample(1.0, 10.0, 100), sample(2.0, 20.0, 300)],
+        }
+    }
+    
+    #[test]
+    fn test_ghost_playback_and_comparison() {
+        let ghost = test_ghost();
+        
+        let pose = ghost.pose_at(0.5).unwrap();
+        assert!((pose.translation.x - 5.0).abs() < 1e-5);
+        assert!(ghost.pose_at(2.5).is_none());
+        
+        assert_eq!(ghost.score_at(1.5), 100);
+        assert_eq!(ghost.time_to_reach(200), Some(2.0));
+        assert_eq!(ghost.time_to_reach(400), None);
+        
+        let state = GhostState {
+            best: Some(ghost),
+            ..default()
+        };
+        let comparison = state.compare(0, 1.5).unwrap();
+        assert_eq!(comparison.time_delta, Some(0.0));
+        assert_eq!(comparison.points_delta, -100);
+        assert!(GhostState::default().compare(0, 1.5).is_none());
+    }
+    
+    #[test]
+    fn test_ghost_file_round_trip() {
+        let path = temp_save_path("ghost").with_file_name("time-attack-1.ron");
+        
+        let ghost = test_ghost();
+        write_ghost(&path, &ghost).unwrap();
+        assert_eq!(load_ghost(&path), Some(ghost));
+        assert_eq!(load_ghost(&path.with_file_name("missing.ron")), None);
+    }
+    
+    #[test]
+    fn test_ghost_files_carry_a_version() {
+        let path = temp_save_path("ghost-version").with_file_name("time-attack-2.ron");
+        
+        write_ghost(&path, &test_ghost()).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.contains(&format!("version:{GHOST_VERSION}")));
+        assert!(!path.with_extension("ron.tmp").exists());
+        
+        // Ghosts from an older layout or a newer build are ignored rather than misread
+        let run = ron::to_string(&test_ghost()).unwrap();
+        std::fs::write(&path, &run).unwrap();
+        assert_eq!(load_ghost(&path), None);
+        std::fs::write(&path, format!("(version:{},run:{run})", GHOST_VERSION + 1)).unwrap();
+        assert_eq!(load_ghost(&path), None);
+    }
+    
+    #[test]
+    fn test_time_attack_run_leaves_a_ghost() {
+        let dir = temp_save_path("ghost-run").with_file_name("ghosts");
+        let tick_rate = 20.0;
+        
+        let mut app = headless_app(tick_rate);
+        app.insert_resource(GhostStore { dir: Some(dir.clone()) });
+        start_run(&mut app, GameMode::TimeAttack, Some(5));
+        simulate(&mut app, 70.0, tick_rate);
+        assert_eq!(*app.world().resource::<State<GameState>>().get(), GameState::GameOver);
+        
+        let ghost = load_ghost(&dir.join("time-attack-5.ron")).unwrap();
+        assert!(ghost.samples.len() > 100);
+        
+        // The next run on the same seed races against it
+        let mut app = headless_app(tick_rate);
+        app.insert_resource(GhostStore { dir: Some(dir) });
+        start_run(&mut app, GameMode::TimeAttack, Some(5));
+        simulate(&mut app, 1.0, tick_rate);
+        let ghosts = app.world_mut().query::<&GhostAircraft>().iter(app.world()).count();
+        assert_eq!(ghosts, 1);
+        assert!(app.world().resource::<GhostState>().best.is_some());
+    }
+    
+    #[test]
+    fn test_segment_hits_sphere() {
+        let center = Vec3::new(0.0, 0.0, 0.0);
+        // Both ends well outside the sphere, but the path goes straight through it
+        assert!(segment_hits_sphere(Vec3::new(-40.0, 0.0, 0.0), Vec3::new(40.0, 0.0, 0.0), center, 5.0));
+        // Passes beside it
+        assert!(!segment_hits_sphere(Vec3::new(-40.0, 6.0, 0.0), Vec3::new(40.0, 6.0, 0.0), center, 5.0));
+        // Stops short of it
+        assert!(!segment_hits_sphere(Vec3::new(-40.0, 0.0, 0.0), Vec3::new(-10.0, 0.0, 0.0), center, 5.0));
+        // A bullet that hasn't moved is a point check
+        assert!(segment_hits_sphere(Vec3::new(3.0, 0.0, 0.0), Vec3::new(3.0, 0.0, 0.0), center, 5.0));
+    }
+    
+    #[test]
+    fn test_fast_bullet_does_not_tunnel_through_player() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::FreePlay, Some(1));
+        app.update();
+        
+        // 50 units per step: it starts 20 units ahead of the player and ends up 30 behind
+        let world = app.world_mut();
+        let player = *world.query_filtered::<&Transform, With<Aircraft>>().single(world).unwrap();
+        world.spawn((
+            Transform::from_translation(player.translation + player.forward() * 20.0),
+            EnemyBullet {
+                velocity: player.back() * 3000.0,
+                damage: 10.0,
+                lifetime: 3.0,
+            },
+        ));
+        app.update();
+        
+        let world = app.world_mut();
+        let health = world.query_filtered::<&Health, With<Aircraft>>().single(world).unwrap();
+        assert_eq!(health.current, 90.0);
+        assert_eq!(world.query::<&EnemyBullet>().iter(world).count(), 0);
+    }
+    
+    // The simulated (not interpolated) poses of the player and every enemy after `frames` frames
+    fn simulated_snapshot(tick_rate: f32, frames: u32) -> (u32, Vec<Vec3>) {
+        let mut app = headless_app(tick_rate);
+        start_run(&mut app, GameMode::Survival, Some(7));
+        for _ in 0..frames {
+            app.update();
+        }
+        
+        let world = app.world_mut();
+        let positions = world
+            .query_filtered::<&InterpolatedTransform, Or<(With<Aircraft>, With<Enemy>)>>()
+            .iter(world)
+            .map(|interpolated| interpolated.current.translation)
+            .collect();
+        (world.resource::<GameStats>().score, positions)
+    }
+    
+    #[test]
+    fn test_simulation_does_not_depend_on_frame_rate() {
+        // 6.04 seconds either way, which doesn't end exactly on a fixed step
+        let slow = simulated_snapshot(25.0, 151);
+        let fast = simulated_snapshot(50.0, 302);
+        assert!(slow.1.len() > 1);
+        assert_eq!(slow, fast);
+    }
+    
+    #[test]
+    fn test_spatial_grid_queries() {
+        let mut grid = SpatialGrid::new(25.0);
+        let near = Entity::from_raw(1);
+        let across_cell = Entity::from_raw(2);
+        let far = Entity::from_raw(3);
+        grid.insert(near, Vec3::new(1.0, 1.0, 1.0));
+        grid.insert(across_cell, Vec3::new(-2.0, 0.0, 0.0));
+        grid.insert(far, Vec3::new(300.0, 0.0, -300.0));
+        
+        let found: Vec<Entity> = grid.query_sphere(Vec3::ZERO, 5.0).collect();
+        assert!(found.contains(&near) && found.contains(&across_cell));
+        assert!(!found.contains(&far));
+        
+        // A long path finds everything along it
+        let found: Vec<Entity> = grid.query_segment(Vec3::ZERO, Vec3::new(300.0, 0.0, -300.0), 5.0).collect();
+        assert_eq!(found.len(), 3);
+        
+        grid.clear();
+        assert_eq!(grid.query_sphere(Vec3::ZERO, 5.0).count(), 0);
+    }
+    
+    // Not run by default; compare the two in a release build with
+    // `cargo test --release -- --ignored --nocapture bench_`
+    #[test]
+    #[ignore]
+    fn bench_bullet_hits_grid_vs_brute_force() {
+        const BULLETS: usize = 2000;
+        const ENEMIES: usize = 300;
+        const ROUNDS: u32 = 50;
+        
+        let mut rng = fastrand::Rng::with_seed(1);
+        let mut random_point = |extent: f32| Vec3::new(
+            (rng.f32() - 0.5) * extent,
+            rng.f32() * 200.0,
+            (rng.f32() - 0.5) * extent,
+        );
+        let enemies: Vec<(Entity, Vec3)> = (0..ENEMIES)
+            .map(|index| (Entity::from_raw(index as u32), random_point(1000.0)))
+            .collect();
+        let bullets: Vec<(Vec3, Vec3)> = (0..BULLETS)
+            .map(|_| {
+                let start = random_point(1000.0);
+                (start, start + Vec3::new(0.0, 0.0, -250.0 / 60.0))
+            })
+            .collect();
+        
+        let brute_force_start = std::time::Instant::now();
+        let mut brute_force_hits = 0;
+        for _ in 0..ROUNDS {
+            for &(start, end) in &bullets {
+                brute_force_hits += enemies
+                    .iter()
+                    .filter(|(_, position)| segment_hits_sphere(start, end, *position, 5.0))
+                    .count();
+            }
+        }
+        let brute_force_time = brute_force_start.elapsed();
+        
+        // The grid is rebuilt every round, as it is every fixed step in the game
+        let grid_start = std::time::Instant::now();
+        let mut grid = SpatialGrid::new(25.0);
+        let mut grid_hits = 0;
+        for _ in 0..ROUNDS {
+            grid.clear();
+            for &(entity, position) in &enemies {
+                grid.insert(entity, position);
+            }
+            for &(start, end) in &bullets {
+                grid_hits += grid
+                    .query_segment(start, end, 5.0)
+                    .filter(|entity| segment_hits_sphere(start, end, enemies[entity.index() as usize].1, 5.0))
+                    .count();
+            }
+        }
+        let grid_time = grid_start.elapsed();
+        
+        println!("{BULLETS} bullets vs {ENEMIES} enemies, {ROUNDS} steps:");
+        println!("  brute force: {brute_force_time:?}");
+        println!("  grid:        {grid_time:?} ({:.1}x faster)", brute_force_time.as_secs_f64() / grid_time.as_secs_f64());
+        assert_eq!(grid_hits, brute_force_hits);
+        assert!(grid_time < brute_force_time);
+    }
+    
+    #[test]
+    fn test_lift_coefficient_stalls() {
+        assert_eq!(lift_coefficient(0.0), 0.0);
+        assert!(lift_coefficient(0.2) > lift_coefficient(0.1));
+        assert_eq!(lift_coefficient(-0.2), -lift_coefficient(0.2));
+        
+        // Just past the stall the wing has lost about half its lift
+        let peak = lift_coefficient(0.28);
+        assert!(lift_coefficient(0.3) < peak * 0.6);
+        assert!(lift_coefficient(1.5) < lift_coefficient(0.3));
+    }
+    
+    #[test]
+    fn test_simulation_model_can_loop() {
+        let mut app = headless_app(60.0);
+        app.insert_resource(FlightModel::Simulation);
+        start_run(&mut app, GameMode::FreePlay, Some(3));
+        
+        // Full throttle and a steady pull on the stick
+        let mut lowest_up = 1.0_f32;
+        for _ in 0..360 {
+            let world = app.world_mut();
+            world.resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::KeyW);
+            world.send_event(MouseMotion { delta: Vec2::new(0.0, -12.0) });
+            app.update();
+            
+            let world = app.world_mut();
+            let transform = *world.query_filtered::<&Transform, With<Aircraft>>().single(world).unwrap();
+            lowest_up = lowest_up.min(transform.up().y);
+        }
+        // Went over the top of a loop
+        assert!(lowest_up < -0.5, "never flew inverted (lowest up.y {lowest_up})");
+        
+        // Pulling hard the whole way round bleeds off speed
+        let world = app.world_mut();
+        let aircraft = world.query::<&Aircraft>().single(world).unwrap();
+        assert!(aircraft.current_speed > 5.0 && aircraft.current_speed < 80.0);
+    }
+    
+    #[test]
+    fn test_simulation_model_holds_altitude_hands_off() {
+        let mut app = headless_app(60.0);
+        app.insert_resource(FlightModel::Simulation);
+        start_run(&mut app, GameMode::FreePlay, Some(3));
+        simulate(&mut app, 10.0, 60.0);
+        
+        let world = app.world_mut();
+        let transform = *world.query_filtered::<&Transform, With<Aircraft>>().single(world).unwrap();
+        // Started at 50; trimmed flight drifts a little but neither dives in nor balloons away
+        assert!(transform.translation.y > 20.0 && transform.translation.y < 120.0, "altitude {}", transform.translation.y);
+    }
+    
+    #[test]
+    fn test_body_frame_turns_stay_stable() {
+        let dt = 1.0 / 60.0;
+        
+        // A steady pull for one full turn is a loop: back where it started, upside down half way
+        let mut rotation = Quat::IDENTITY;
+        for step in 0..600 {
+            rotation = turn_in_body_frame(rotation, Vec3::new(std::f32::consts::TAU / 600.0, 0.0, 0.0));
+            if step == 299 {
+                assert!((rotation * Vec3::Y).y < -0.999);
+                assert!(bank_angle(rotation).abs() > 3.1);
+            }
+        }
+        assert!(rotation.angle_between(Quat::IDENTITY) < 1e-3);
+        
+        // Half loop then half roll is an Immelmann: heading reversed, wings level, right way up
+        let mut rotation = Quat::IDENTITY;
+        for _ in 0..100 {
+            rotation = turn_in_body_frame(rotation, Vec3::new(std::f32::consts::PI / 100.0, 0.0, 0.0));
+        }
+        for _ in 0..100 {
+            rotation = turn_in_body_frame(rotation, Vec3::new(0.0, 0.0, std::f32::consts::PI / 100.0));
+        }
+        assert!((rotation * Vec3::NEG_Z).distance(Vec3::Z) < 1e-3);
+        assert!(bank_angle(rotation).abs() < 1e-3);
+        
+        // An hour of tumbling through every axis at once never lets the attitude drift off unit length
+        let mut rotation = Quat::IDENTITY;
+        for step in 0..216_000 {
+            let t = step as f32 * dt;
+            let rates = Vec3::new(2.0 * (t * 0.7).sin(), 1.3 * (t * 0.3).cos(), 3.0);
+            rotation = turn_in_body_frame(rotation, rates * dt);
+            assert!((rotation.length() - 1.0).abs() < 1e-5);
+        }
+        let (right, up, forward) = (rotation * Vec3::X, rotation * Vec3::Y, rotation * Vec3::NEG_Z);
+        assert!(right.dot(up).abs() < 1e-5 && up.dot(forward).abs() < 1e-5);
+        
+        // Leveling keeps the heading, even from straight up
+        let climbing = Quat::from_rotation_y(1.0) * Quat::from_rotation_x(std::f32::consts::FRAC_PI_2);
+        let level = level_attitude(climbing);
+        assert!((level * Vec3::NEG_Z).y.abs() < 1e-5 && bank_angle(level).abs() < 1e-5);
+        assert!(level.is_finite());
+    }
+    
+    #[test]
+    fn test_arcade_model_can_loop() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::FreePlay, Some(3));
+        
+        let mut lowest_up = 1.0_f32;
+        for _ in 0..240 {
+            app.world_mut().send_event(MouseMotion { delta: Vec2::new(0.0, -20.0) });
+            app.update();
+            
+            let world = app.world_mut();
+            let transform = *world.query_filtered::<&Transform, With<Aircraft>>().single(world).unwrap();
+            lowest_up = lowest_up.min(transform.up().y);
+            assert!(transform.rotation.is_normalized());
+        }
+        // The old pitch clamp stopped the nose at about 45 degrees up
+        assert!(lowest_up < -0.5, "never flew inverted (lowest up.y {lowest_up})");
+        
+        // Let go and the wings roll back to level
+        simulate(&mut app, 6.0, 60.0);
+        let world = app.world_mut();
+        let transform = *world.query_filtered::<&Transform, With<Aircraft>>().single(world).unwrap();
+        assert!(bank_angle(transform.rotation).abs() < 0.2, "bank {}", bank_angle(transform.rotation));
+    }
+    
+    #[test]
+    fn test_impact_damage_scales_with_speed_and_angle() {
+        let ground = Vec3::Y;
+        let dive = Vec3::new(0.0, -1.0, -1.0).normalize();
+        let graze = Vec3::new(0.0, -0.05, -1.0).normalize();
+        
+        // Twice as fast, twice the damage
+        assert!((impact_damage(dive * 100.0, ground) - 2.0 * impact_damage(dive * 50.0, ground)).abs() < 1e-3);
+        // Steeper hurts more at the same speed, and a graze or climbing away doesn't hurt at all
+        assert!(impact_damage(Vec3::NEG_Y * 50.0, ground) > impact_damage(dive * 50.0, ground));
+        assert_eq!(impact_damage(graze * 50.0, ground), 0.0);
+        assert_eq!(impact_damage(Vec3::new(0.0, 5.0, -50.0), ground), 0.0);
+    }
+    
+    // Moves the player, making sure interpolation doesn't put back the old pose
+    fn place_player(app: &mut App, transform: Transform, velocity: Vec3) {
+        let world = app.world_mut();
+        let (mut current, mut interpolated, mut aircraft) = world
+            .query::<(&mut Transform, &mut InterpolatedTransform, &mut Aircraft)>()
+            .single_mut(world)
+            .unwrap();
+        *current = transform;
+        *interpolated = InterpolatedTransform::new(transform);
+        aircraft.velocity = velocity;
+    }
+    
+    #[test]
+    fn test_diving_into_terrain_damages_aircraft() {
+        let mut app = headless_app(60.0);
+        app.insert_resource(FlightModel::Simulation);
+        start_run(&mut app, GameMode::FreePlay, Some(4));
+        app.update();
+        
+        let terrain = *app.world().resource::<TerrainHeightField>();
+        let start = Vec3::new(120.0, terrain.height_at(120.0, -40.0) + 15.0, -40.0);
+        let nose_down = Transform::from_translation(start).looking_to(Vec3::new(0.0, -1.0, -1.0), Vec3::Y);
+        place_player(&mut app, nose_down, nose_down.forward() * 60.0);
+        
+        for _ in 0..30 {
+            app.update();
+            let world = app.world_mut();
+            let transform = world.query_filtered::<&Transform, With<Aircraft>>().single(world).unwrap();
+            let ground = terrain.height_at(transform.translation.x, transform.translation.z);
+            assert!(transform.translation.y > ground, "aircraft went underground");
+        }
+        
+        let world = app.world_mut();
+        let health = world.query_filtered::<&Health, With<Aircraft>>().single(world).unwrap();
+        assert!(health.current < 60.0 && health.current > 0.0, "health {}", health.current);
+    }
+    
+    #[test]
+    fn test_flying_into_a_tree_damages_aircraft() {
+        let mut app = headless_app(60.0);
+        app.insert_resource(FlightModel::Simulation);
+        start_run(&mut app, GameMode::FreePlay, Some(4));
+        app.update();
+        
+        // Line up level with the middle of a nearby trunk, with open ground in front of it
+        let terrain = *app.world().resource::<TerrainHeightField>();
+        let (start, tree) = terrain
+            .trees_near(Vec3::ZERO, 2000.0)
+            .filter(|tree| tree.kind == TreeKind::Broadleaf)
+            .find_map(|tree| {
+                let start = tree.base + Vec3::new(-20.0, tree.trunk_height * 0.5, 0.0);
+                let clear = (0..=4).all(|step| terrain.height_at(start.x + step as f32 * 4.0, start.z) < start.y - 4.0);
+                clear.then_some((start, tree))
+            })
+            .unwrap();
+        let approach = Transform::from_translation(start).looking_to(Vec3::X, Vec3::Y);
+        place_player(&mut app, approach, Vec3::X * 50.0);
+        simulate(&mut app, 1.0, 60.0);
+        
+        let world = app.world_mut();
+        let health = world.query_filtered::<&Health, With<Aircraft>>().single(world).unwrap();
+        assert!(health.current < 100.0, "flew through the tree at {}", tree.base);
+    }
+    
+    #[test]
+    fn test_enemies_and_balloons_stay_above_terrain() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::Survival, Some(5));
+        simulate(&mut app, 20.0, 60.0);
+        
+        let terrain = *app.world().resource::<TerrainHeightField>();
+        let world = app.world_mut();
+        let enemies: Vec<Vec3> = world
+            .query_filtered::<&InterpolatedTransform, With<Enemy>>()
+            .iter(world)
+            .map(|interpolated| interpolated.current.translation)
+            .collect();
+        assert!(!enemies.is_empty());
+        for position in enemies {
+            assert!(position.y >= terrain.height_at(position.x, position.z) + 14.9);
+        }
+        
+        let balloons: Vec<Vec3> = world
+            .query_filtered::<&Transform, With<Target>>()
+            .iter(world)
+            .map(|transform| transform.translation)
+            .collect();
+        assert!(!balloons.is_empty());
+        for position in balloons {
+            assert!(position.y > terrain.height_at(position.x, position.z) + AIRCRAFT_CLEARANCE);
+        }
+    }
+    
+    // Steps until every chunk around the aircraft has been built and spawned
+    fn loaded_chunks(app: &mut App) -> Vec<IVec2> {
+        let wanted = ((2 * TERRAIN_VIEW_RADIUS + 1) * (2 * TERRAIN_VIEW_RADIUS + 1)) as usize;
+        for _ in 0..600 {
+            app.update();
+            if app.world().resource::<TerrainStreamer>().loaded.len() >= wanted {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        app.world().resource::<TerrainStreamer>().loaded.keys().copied().collect()
+    }
+    
+    #[test]
+    fn test_terrain_streams_around_the_aircraft() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::FreePlay, Some(2));
+        
+        let chunks = loaded_chunks(&mut app);
+        assert_eq!(chunks.len(), 25);
+        assert!(chunks.contains(&IVec2::ZERO));
+        
+        // Far past where the old fixed terrain ended
+        let far_away = Transform::from_xyz(20_000.0, 80.0, -7_000.0);
+        place_player(&mut app, far_away, far_away.forward() * 50.0);
+        let chunks = loaded_chunks(&mut app);
+        
+        let world = app.world_mut();
+        let player = world.query_filtered::<&Transform, With<Aircraft>>().single(world).unwrap();
+        let center = chunk_coord(player.translation);
+        assert!(chunks.contains(&center));
+        // The chunks around the origin are gone, and nothing is left loaded out of range
+        assert!(!chunks.contains(&IVec2::ZERO));
+        assert!(chunks.iter().all(|coord| (*coord - center).abs().max_element() <= TERRAIN_VIEW_RADIUS + 1));
+        assert_eq!(world.query::<&TerrainChunk>().iter(world).count(), chunks.len());
+        
+        // Flying back finds the meshes already built, so the whole ring returns in one frame
+        let home = Transform::from_xyz(0.0, 80.0, 0.0);
+        place_player(&mut app, home, home.forward() * 50.0);
+        app.update();
+        let loaded = &app.world().resource::<TerrainStreamer>().loaded;
+        assert_eq!(loaded.len(), 25);
+        assert!(loaded.contains_key(&IVec2::ZERO));
+    }
+    
+    #[test]
+    fn test_effects_share_assets() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::FreePlay, Some(9));
+        app.update();
+        
+        let asset_counts = |app: &App| (
+            app.world().resource::<Assets<Mesh>>().len(),
+            app.world().resource::<Assets<StandardMaterial>>().len(),
+        );
+        // Hold the trigger and the afterburner for a minute and a half, kept high above one spot
+        // so terrain streaming doesn't add meshes of its own
+        let mut settled = (0, 0);
+        let mut entities = 0;
+        for tick in 0..5400u32 {
+            if tick % 60 == 0 {
+                let high = Transform::from_xyz(0.0, 500.0, 0.0);
+                place_player(&mut app, high, high.forward() * 60.0);
+                let world = app.world_mut();
+                let mut health = world.query_filtered::<&mut Health, With<Aircraft>>().single_mut(world).unwrap();
+                health.current = health.max;
+                // Keep the cannon from overheating so it fires the whole time
+                let mut loadout = world.resource_mut::<Loadout>();
+                loadout.primary.heat = 0.0;
+            }
+            let mut keyboard = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+            keyboard.press(KeyCode::KeyF);
+            keyboard.press(KeyCode::Space);
+            app.update();
+            if tick == 1800 {
+                settled = asset_counts(&app);
+                entities = app.world_mut().query::<Entity>().iter(app.world()).count();
+            }
+        }
+        
+        // Thousands of bullets and exhaust particles later, only the odd enemy or balloon has added assets
+        let (meshes, materials) = asset_counts(&app);
+        assert!(meshes <= settled.0 + 20, "{} meshes, {} after 30s", meshes, settled.0);
+        assert!(materials <= settled.1 + 20, "{} materials, {} after 30s", materials, settled.1);
+        let world = app.world_mut();
+        let now = world.query::<Entity>().iter(world).count();
+        assert!(now <= entities + 50, "{} entities, {} after 30s", now, entities);
+        
+        let sphere = world.resource::<SharedAssets>().sphere.clone();
+        let mut bullets = world.query_filtered::<&Mesh3d, With<PlayerBullet>>();
+        let bullet_meshes: Vec<&Mesh3d> = bullets.iter(world).collect();
+        assert!(bullet_meshes.len() > 20);
+        assert!(bullet_meshes.iter().all(|mesh| mesh.0 == sphere));
+        
+        // Exhaust lives in an emitter the player's aircraft owns rather than as entities of its own
+        let mut players = world.query_filtered::<&ParticleEmitters, With<Aircraft>>();
+        let owned: Vec<Entity> = players.single(world).unwrap().iter().collect();
+        let exhaust = owned
+            .iter()
+            .filter_map(|&entity| world.get::<ParticleEmitter>(entity))
+            .find(|emitter| emitter.preset == ParticlePreset::EngineExhaust)
+            .unwrap();
+        assert!(!exhaust.is_empty());
+        assert_eq!(owned.len(), 1);
+    }
+    
+    #[test]
+    fn test_particle_bursts_get_an_emitter_of_their_own() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::FreePlay, Some(9));
+        app.update();
+        
+        let explosions = |app: &mut App| {
+            let world = app.world_mut();
+            let mut emitters = world.query::<(&ParticleEmitter, &Aabb, Has<EmitterOf>, Has<Mesh3d>)>();
+            emitters
+                .iter(world)
+                .filter(|(emitter, ..)| emitter.preset == ParticlePreset::Explosion)
+                .map(|(emitter, aabb, owned, mesh)| {
+                    // Drawn by instancing rather than through a mesh of their own, and culled by
+                    // bounds that cover every particle
+                    assert!(!owned && !mesh);
+                    assert!(emitter.instances().all(|(position, _, _)| (Vec3::from(aabb.center) - position).abs().cmple(aabb.half_extents.into()).all()));
+                    emitter.len()
+                })
+                .collect::<Vec<usize>>()
+        };
+        
+        let position = Vec3::new(0.0, 900.0, 0.0);
+        for count in [30, 50] {
+            app.world_mut().send_event(ParticleBurst {
+                preset: ParticlePreset::Explosion,
+                position,
+                velocity: Vec3::ZERO,
+                count,
+                color: None,
+            });
+        }
+        app.update();
+        let mut lens = explosions(&mut app);
+        lens.sort();
+        assert_eq!(lens, [30, 50]);
+        
+        // Every particle has burnt out a couple of seconds later, and the emitters with them
+        for _ in 0..120 {
+            app.update();
+        }
+        assert!(explosions(&mut app).is_empty());
+        
+        // An emitter never holds more than its cap, however much is asked of it
+        app.world_mut().send_event(ParticleBurst {
+            preset: ParticlePreset::Explosion,
+            position,
+            velocity: Vec3::ZERO,
+            count: 100_000,
+            color: None,
+        });
+        app.update();
+        let lens = explosions(&mut app);
+        assert!(lens.len() == 1 && lens[0] > 0 && lens[0] < 1000, "{:?} particles", lens);
+    }
+    
+    #[test]
+    fn test_particle_trails_outlive_their_source() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::FreePlay, Some(9));
+        app.update();
+        
+        // A badly damaged aircraft owns its smoke and fire
+        let aircraft = app.world_mut().spawn((Transform::from_xyz(0.0, 900.0, 0.0), Health { current: 5.0, max: 100.0 })).id();
+        for _ in 0..30 {
+            app.update();
+        }
+        let owned: Vec<Entity> = app.world().get::<ParticleEmitters>(aircraft).unwrap().iter().collect();
+        assert_eq!(owned.len(), 2);
+        
+        // Once it's gone its trail stays until the last particle burns out, then goes too
+        app.world_mut().despawn(aircraft);
+        app.update();
+        assert!(owned.iter().all(|&emitter| app.world().get::<ParticleEmitter>(emitter).is_some_and(|emitter| !emitter.is_empty())));
+        assert!(owned.iter().all(|&emitter| app.world().get::<EmitterOf>(emitter).is_none()));
+        for _ in 0..180 {
+            app.update();
+        }
+        assert!(owned.iter().all(|&emitter| app.world().get_entity(emitter).is_err()));
+    }
+    
+    #[test]
+    fn test_engine_exhaust_follows_the_player_input() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::FreePlay, Some(9));
+        app.update();
+        
+        // Nothing is held on the keyboard, as during a replay: the afterburner comes from the input alone
+        let exhaust_after = |app: &mut App, boost: bool| {
+            *app.world_mut().resource_mut::<PlayerInput>() = PlayerInput { boost, ..default() };
+            for _ in 0..60 {
+                app.world_mut().run_system_once(crate::particles::emit_engine_exhaust).unwrap();
+            }
+            let world = app.world_mut();
+            let mut emitters = world.query::<&ParticleEmitter>();
+            emitters.iter(world).find(|emitter| emitter.preset == ParticlePreset::EngineExhaust).unwrap().len()
+        };
+        let cruising = exhaust_after(&mut app, false);
+        let boosting = exhaust_after(&mut app, true) - cruising;
+        assert!(boosting > cruising * 2, "{} particles boosting, {} cruising", boosting, cruising);
+    }
+    
+    #[test]
+    fn test_proportional_navigation_leads_crossing_target() {
+        // Steers towards a target off to the side, and not at all at one dead ahead
+        let ahead = Vec3::new(0.0, 0.0, -100.0);
+        let steer = proportional_navigation(Vec3::ZERO, Vec3::NEG_Z * 100.0, ahead + Vec3::X * 30.0, Vec3::ZERO, 10.0);
+        assert!(steer.x > 0.0);
+        assert!(proportional_navigation(Vec3::ZERO, Vec3::NEG_Z * 100.0, ahead, Vec3::ZERO, 10.0).length() < 1e-3);
+        
+        // Never turns faster than the limit
+        let hard = proportional_navigation(Vec3::ZERO, Vec3::NEG_Z * 100.0, Vec3::X * 5.0, Vec3::ZERO, 2.0);
+        assert!(hard.length() <= 200.0 + 1e-3);
+        
+        // Flown step by step, it intercepts a target crossing its nose instead of chasing its tail
+        let (mut missile, mut missile_velocity) = (Vec3::ZERO, Vec3::NEG_Z * 200.0);
+        let (mut target, target_velocity) = (Vec3::new(-150.0, 0.0, -300.0), Vec3::X * 70.0);
+        let delta = 1.0 / 60.0;
+        let mut closest = f32::MAX;
+        for _ in 0..240 {
+            missile_velocity += proportional_navigation(missile, missile_velocity, target, target_velocity, 2.5) * delta;
+            missile_velocity = missile_velocity.normalize() * 200.0;
+            missile += missile_velocity * delta;
+            target += target_velocity * delta;
+            closest = closest.min(missile.distance(target));
+        }
+        assert!(closest < 6.0, "missed by {}", closest);
+    }
+    
+    // Runs a free flight until the first enemy shows up
+    fn wait_for_enemy(app: &mut App) -> (Entity, Transform) {
+        for _ in 0..600 {
+            app.update();
+            let world = app.world_mut();
+            if let Some((entity, transform)) = world.query_filtered::<(Entity, &Transform), With<Enemy>>().iter(world).next() {
+                return (entity, *transform);
+            }
+        }
+        panic!("no enemy spawned");
+    }
+    
+    #[test]
+    fn test_aircraft_use_shared_models_with_moving_control_surfaces() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::FreePlay, Some(3));
+        wait_for_enemy(&mut app);
+        
+        // Every aircraft has one hull, sized for its model, and the same type shares one mesh
+        let world = app.world_mut();
+        let hulls: Vec<(Entity, Handle<Mesh>, Vec3)> = world
+            .query_filtered::<(&ChildOf, &Mesh3d, &Transform), Without<ControlSurface>>()
+            .iter(world)
+            .map(|(child_of, mesh, transform)| (child_of.parent(), mesh.0.clone(), transform.scale))
+            .collect();
+        let hull_of = |aircraft: Entity| {
+            let found: Vec<_> = hulls.iter().filter(|(parent, _, _)| *parent == aircraft).collect();
+            assert_eq!(found.len(), 1);
+            (found[0].1.clone(), found[0].2)
+        };
+        let player = world.query_filtered::<Entity, With<Aircraft>>().single(world).unwrap();
+        assert_eq!(hull_of(player).1, Vec3::ONE);
+        let enemies: Vec<(Entity, AircraftModel)> = world.query::<(Entity, &Enemy)>().iter(world).map(|(entity, enemy)| (entity, enemy.archetype.model)).collect();
+        for (entity, model) in &enemies {
+            assert_eq!(hull_of(*entity).1, Vec3::splat(model.scale()));
+            for (other, other_model) in &enemies {
+                if other_model == model {
+                    assert_eq!(hull_of(*entity).0, hull_of(*other).0);
+                }
+            }
+            assert_ne!(hull_of(*entity).0, hull_of(player).0);
+        }
+        
+        // Rolling right turns the airframe clockwise about its nose, and moves the ailerons
+        let rates = body_rates(Quat::IDENTITY, Quat::from_rotation_z(-0.05), 0.1);
+        assert!(rates.distance(Vec3::new(0.0, 0.0, -0.5)) < 1e-4);
+        for _ in 0..20 {
+            app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::KeyD);
+            app.update();
+        }
+        let world = app.world_mut();
+        let deflections: Vec<f32> = world.query::<&ControlSurface>().iter(world).map(|surface| surface.deflection).collect();
+        assert!(deflections.iter().any(|deflection| deflection.abs() > 0.02), "{deflections:?}");
+    }
+    
+    #[test]
+    fn test_missiles_lock_on_the_enemy_ahead() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::FreePlay, Some(3));
+        let (enemy, enemy_transform) = wait_for_enemy(&mut app);
+        
+        let behind = Transform::from_translation(enemy_transform.translation + Vec3::new(0.0, 0.0, 120.0))
+            .looking_at(enemy_transform.translation, Vec3::Y);
+        let hold = |app: &mut App, keys: &[KeyCode]| {
+            place_player(app, behind, behind.forward() * 60.0);
+            let mut keyboard = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+            keyboard.release_all();
+            for key in keys {
+                keyboard.press(*key);
+            }
+            app.update();
+        };
+        
+        // Rockets don't lock on; cycling once from them comes back round to missiles
+        hold(&mut app, &[KeyCode::KeyQ]);
+        assert_eq!(app.world().resource::<Loadout>().selected, 1);
+        assert_eq!(app.world().resource::<MissileLock>().target, None);
+        hold(&mut app, &[]);
+        hold(&mut app, &[KeyCode::KeyQ]);
+        assert_eq!(app.world().resource::<Loadout>().selected, 0);
+        
+        hold(&mut app, &[KeyCode::KeyE]);
+        assert_eq!(app.world().resource::<MissileLock>().target, Some(enemy));
+        
+        // Holding the secondary trigger launches one missile a second, each at the locked enemy
+        let world = app.world_mut();
+        let missiles: Vec<Option<Entity>> = world.query::<&Missile>().iter(world).map(|missile| missile.target).collect();
+        assert_eq!(missiles, vec![Some(enemy)]);
+        for _ in 0..30 {
+            hold(&mut app, &[KeyCode::KeyE]);
+        }
+        let world = app.world_mut();
+        assert_eq!(world.query::<&Missile>().iter(world).count(), 1);
+        let capacity = Weapon::HOMING_MISSILES.ammo.unwrap();
+        assert_eq!(world.resource::<Loadout>().secondaries[0].ammo, Some(capacity - 1));
+    }
+    
+    #[test]
+    fn test_lead_position_meets_target() {
+        let shooter = Vec3::ZERO;
+        let target = Vec3::new(0.0, 0.0, -200.0);
+        let velocity = Vec3::X * 50.0;
+        assert_eq!(lead_position(shooter, target, Vec3::ZERO, 250.0, 1.0), target);
+        
+        // A shot at the lead point arrives where the target has got to by then
+        let lead = lead_position(shooter, target, velocity, 250.0, 1.0);
+        let flight_time = 200.0 / 250.0;
+        assert!(lead.distance(target + velocity * flight_time) < 1e-3);
+        // Enemies only lead by half
+        let half = lead_position(shooter, target, velocity, 250.0, 0.5);
+        assert!(half.distance(target + velocity * flight_time * 0.5) < 1e-3);
+    }
+    
+    #[test]
+    fn test_target_lock_picks_and_cycles_enemies() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::FreePlay, Some(3));
+        let mut enemies = Vec::new();
+        for _ in 0..1200 {
+            app.update();
+            let world = app.world_mut();
+            enemies = world.query_filtered::<(Entity, &Transform), With<Enemy>>().iter(world).map(|(entity, transform)| (entity, transform.translation)).collect();
+            if enemies.len() >= 2 {
+                break;
+            }
+        }
+        assert!(enemies.len() >= 2, "fewer than two enemies spawned");
+        
+        // Far enough back that every enemy is in view
+        let centre = enemies.iter().map(|(_, position)| *position).sum::<Vec3>() / enemies.len() as f32;
+        let view = Transform::from_translation(centre + Vec3::new(0.0, 0.0, 500.0)).looking_at(centre, Vec3::Y);
+        let hold = |app: &mut App, keys: &[KeyCode]| {
+            place_player(app, view, view.forward() * 60.0);
+            let mut keyboard = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+            keyboard.release_all();
+            for key in keys {
+                keyboard.press(*key);
+            }
+            app.update();
+            app.world().resource::<TargetLock>().target
+        };
+        
+        // A lost target is replaced with the nearest enemy in view
+        app.world_mut().resource_mut::<TargetLock>().target = None;
+        let nearest = hold(&mut app, &[]).unwrap();
+        let world = app.world_mut();
+        let distances: Vec<(Entity, f32)> = world.query_filtered::<(Entity, &Transform), With<Enemy>>()
+            .iter(world)
+            .map(|(entity, transform)| (entity, transform.translation.distance(view.translation)))
+            .collect();
+        let closest = distances.iter().min_by(|a, b| a.1.total_cmp(&b.1)).unwrap().0;
+        assert_eq!(nearest, closest);
+        assert!(app.world().resource::<TargetLock>().lead_point.is_some());
+        
+        // Each press moves on to the next enemy; holding the key doesn't keep cycling
+        let next = hold(&mut app, &[KeyCode::KeyT]).unwrap();
+        assert_ne!(next, nearest);
+        assert_eq!(hold(&mut app, &[KeyCode::KeyT]), Some(next));
+        assert_eq!(hold(&mut app, &[]), Some(next));
+        
+        // And once it's destroyed the lock moves to another
+        app.world_mut().despawn(next);
+        let after = hold(&mut app, &[]);
+        assert!(after.is_some() && after != Some(next));
+    }
+    
+    #[test]
+    fn test_cannon_overheats_and_cools() {
+        let mut cannon = WeaponSlot::new(Weapon::CANNON);
+        let mut shots = 0;
+        while cannon.ready() {
+            cannon.fire();
+            cannon.cool(Weapon::CANNON.cooldown);
+            shots += 1;
+            assert!(shots < 100, "never overheated");
+        }
+        assert!(cannon.overheated);
+        assert!(shots > 10, "overheated after {} shots", shots);
+        
+        // Locked out until it has cooled most of the way down
+        cannon.cool(1.0);
+        assert!(!cannon.ready());
+        cannon.cool(2.0);
+        assert!(cannon.ready());
+        
+        // Secondaries run dry, and pickups top them up no further than they carry
+        let mut rockets = WeaponSlot::new(Weapon::ROCKETS);
+        let capacity = Weapon::ROCKETS.ammo.unwrap();
+        for _ in 0..capacity {
+            rockets.fire();
+            rockets.cool(1.0);
+        }
+        assert!(!rockets.ready());
+        rockets.refill(5);
+        assert_eq!(rockets.ammo, Some(5));
+        rockets.refill(1000);
+        assert_eq!(rockets.ammo, Some(capacity));
+    }
+    
+    #[test]
+    fn test_powerups_change_weapon_stats() {
+        let rapid = Weapon::CANNON.boosted(PowerUpType::RapidFire);
+        assert!(rapid.cooldown < Weapon::CANNON.cooldown);
+        let both = rapid.boosted(PowerUpType::TripleShot);
+        assert_eq!(both.projectile_count, Weapon::CANNON.projectile_count + 1);
+        assert_eq!(both.cooldown, rapid.cooldown);
+        
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::FreePlay, Some(5));
+        app.update();
+        let world = app.world_mut();
+        let player = world.query_filtered::<Entity, With<Aircraft>>().single(world).unwrap();
+        world.entity_mut(player).with_child(PowerUpEffect { effect_type: PowerUpType::TripleShot, remaining: 0.5 });
+        
+        // Three bullets a shot while it lasts, then back to two
+        let mut keyboard = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+        keyboard.press(KeyCode::KeyF);
+        app.update();
+        let world = app.world_mut();
+        assert_eq!(world.resource::<Loadout>().primary.weapon.projectile_count, 3);
+        assert_eq!(world.query::<&PlayerBullet>().iter(world).count(), 3);
+        for _ in 0..60 {
+            app.update();
+        }
+        assert_eq!(app.world().resource::<Loadout>().primary.weapon, Weapon::CANNON);
+    }
+    
+    #[test]
+    fn test_missile_homes_onto_enemy() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::FreePlay, Some(3));
+        let (enemy, enemy_transform) = wait_for_enemy(&mut app);
+        
+        // Fired at 45° off the enemy, so it has to turn to reach it
+        let start = Transform::from_translation(enemy_transform.translation + Vec3::new(60.0, 0.0, 60.0));
+        app.world_mut().spawn((
+            start,
+            InterpolatedTransform::new(start),
+            Missile {
+                target: Some(enemy),
+                velocity: Vec3::NEG_Z * 100.0,
+                fuel: 3.0,
+                lifetime: 6.0,
+                max_speed: Weapon::HOMING_MISSILES.projectile_speed,
+                damage: Weapon::HOMING_MISSILES.damage,
+            },
+        ));
+        
+        let before = app.world().resource::<GameStats>().enemies_destroyed;
+        let mut hit = false;
+        for _ in 0..180 {
+            app.update();
+            let world = app.world_mut();
+            if world.query::<&Missile>().iter(world).count() == 0 {
+                hit = world.get::<Health>(enemy).is_none_or(|health| health.current < health.max);
+                break;
+            }
+        }
+        assert!(hit, "missile didn't reach the enemy");
+        // A fighter is destroyed by one missile; anything tougher is at least damaged
+        let world = app.world_mut();
+        if world.get::<Enemy>(enemy).is_none() {
+            assert_eq!(world.resource::<GameStats>().enemies_destroyed, before + 1);
+        }
+    }
+    
+    #[test]
+    fn test_entity_pool_recycles() {
+        let mut world = World::new();
+        let bullet = |lifetime| (Transform::default(), PlayerBullet { velocity: Vec3::ZERO, damage: 10.0, lifetime });
+        
+        let mut pool = EntityPool::<PlayerBullet>::new(PoolConfig { size: 2, overflow: Overflow::RecycleOldest });
+        pool.reset(&mut world.commands());
+        let first = pool.spawn(&mut world.commands(), bullet(1.0)).unwrap();
+        let second = pool.spawn(&mut world.commands(), bullet(2.0)).unwrap();
+        world.flush();
+        assert_ne!(first, second);
+        assert_eq!((pool.active(), pool.free()), (2, 0));
+        
+        // Full, so the oldest is taken back
+        let third = pool.spawn(&mut world.commands(), bullet(3.0)).unwrap();
+        world.flush();
+        assert_eq!(third, first);
+        assert_eq!(world.get::<PlayerBullet>(first).unwrap().lifetime, 3.0);
+        
+        // Released entities are hidden and lose their kind; releasing twice frees them once
+        pool.release(&mut world.commands(), second);
+        pool.release(&mut world.commands(), second);
+        world.flush();
+        assert!(world.get::<PlayerBullet>(second).is_none());
+        assert_eq!(world.get::<Visibility>(second), Some(&Visibility::Hidden));
+        assert_eq!(pool.free(), 1);
+        
+        assert_eq!(pool.spawn(&mut world.commands(), bullet(4.0)), Some(second));
+        world.flush();
+        assert_eq!(world.get::<Visibility>(second), Some(&Visibility::Inherited));
+        assert_eq!(world.query::<&PlayerBullet>().iter(&world).count(), 2);
+        
+        let mut skipping = EntityPool::<PlayerBullet>::new(PoolConfig { size: 1, overflow: Overflow::Skip });
+        skipping.reset(&mut world.commands());
+        assert!(skipping.spawn(&mut world.commands(), bullet(1.0)).is_some());
+        assert!(skipping.spawn(&mut world.commands(), bullet(1.0)).is_none());
+        
+        let mut growing = EntityPool::<PlayerBullet>::new(PoolConfig { size: 1, overflow: Overflow::Grow });
+        growing.reset(&mut world.commands());
+        for _ in 0..3 {
+            growing.spawn(&mut world.commands(), bullet(1.0));
+        }
+        world.flush();
+        assert_eq!(growing.active(), 3);
+        assert_eq!(world.query::<&PlayerBullet>().iter(&world).count(), 2 + 1 + 3);
+    }
+    
+    #[test]
+    fn test_terrain_chunks_have_skirts() {
+        let flat = create_terrain_chunk(100.0, 4, 0.0, |_, _| 5.0, |_, _| [1.0; 4]);
+        let skirted = create_terrain_chunk(100.0, 4, 20.0, |_, _| 5.0, |_, _| [1.0; 4]);
+        
+        // A 5×5 grid of vertices, plus a copy of each edge's 5 vertices hanging below it
+        assert_eq!(flat.count_vertices(), 25);
+        assert_eq!(skirted.count_vertices(), 25 + 4 * 5);
+        assert_eq!(triangle_count(&skirted), triangle_count(&flat) + 4 * 4 * 2);
+        
+        let Some(bevy::render::mesh::VertexAttributeValues::Float32x3(positions)) =
+            skirted.attribute(Mesh::ATTRIBUTE_POSITION) else {
+            panic!("terrain chunk has no positions");
+        };
+        let lowest = positions.iter().map(|position| position[1]).fold(f32::MAX, f32::min);
+        assert_eq!(lowest, -15.0);
+    }
+    
+    #[test]
+    fn test_terrain_detail_falls_off_with_distance() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::FreePlay, Some(2));
+        loaded_chunks(&mut app);
+        
+        let streamer = app.world().resource::<TerrainStreamer>();
+        let mut counts = [0; TERRAIN_LOD_RESOLUTIONS.len()];
+        for lod in streamer.loaded_lods() {
+            counts[lod] += 1;
+        }
+        // The chunk under the aircraft, the ring around it, then the outer ring
+        assert_eq!(counts, [1, 8, 16]);
+        assert_eq!(streamer.loaded[&IVec2::ZERO].lod, 0);
+    }
+    
+    #[test]
+    fn test_debug_overlay_stays_within_budget() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::FreePlay, Some(2));
+        app.world_mut().resource_mut::<DebugOverlay>().visible = true;
+        loaded_chunks(&mut app);
+        app.update();
+        
+        let stats = &app.world().resource::<DebugOverlay>().stats;
+        assert_eq!(stats.terrain_chunks, [1, 8, 16]);
+        assert!(stats.triangles > 0 && stats.triangles < TRIANGLE_BUDGET, "{} triangles", stats.triangles);
+        assert!(stats.entities < ENTITY_BUDGET, "{} entities", stats.entities);
+        // Trees and clouds near the aircraft keep their detail, the distant ones don't
+        assert!(stats.near_models > 0);
+        assert!(stats.far_models > 0);
+        // Bullets, trails and particles are spawned hidden at the start of the run
+        assert!(stats.pooled_free > 0);
+    }
+    
+    #[test]
+    fn test_terrain_generation_is_seeded_and_varied() {
+        let terrain = TerrainHeightField::from_seed(11);
+        let samples: Vec<(f32, f32)> = (-60..60)
+            .flat_map(|i| (-60..60).map(move |j| (i as f32 * 70.0, j as f32 * 70.0)))
+            .collect();
+        
+        // The same seed always builds the same world, and another seed a different one
+        let again = TerrainHeightField::from_seed(11);
+        let other = TerrainHeightField::from_seed(12);
+        assert!(samples.iter().all(|&(x, z)| terrain.height_at(x, z) == again.height_at(x, z)));
+        assert!(samples.iter().any(|&(x, z)| terrain.height_at(x, z) != other.height_at(x, z)));
+        
+        // Mountains tall enough to fly between, and flat water at sea level
+        let heights: Vec<f32> = samples.iter().map(|&(x, z)| terrain.height_at(x, z)).collect();
+        assert!(heights.iter().all(|height| *height >= SEA_LEVEL));
+        assert!(heights.iter().any(|height| *height > 150.0));
+        for &(x, z) in &samples {
+            if terrain.biome_at(x, z) == Biome::Water {
+                assert_eq!(terrain.height_at(x, z), SEA_LEVEL);
+            }
+        }
+        
+        // Every biome turns up, and each grows its own kind of tree
+        for biome in [Biome::Grassland, Biome::Desert, Biome::Snow, Biome::Water] {
+            assert!(samples.iter().any(|&(x, z)| terrain.biome_at(x, z) == biome), "no {biome:?}");
+        }
+        let trees: Vec<_> = terrain.trees_near(Vec3::ZERO, 3000.0).collect();
+        for tree in &trees {
+            let biome = terrain.biome_at(tree.base.x, tree.base.z);
+            assert_ne!(biome, Biome::Water);
+            assert_eq!(tree.kind == TreeKind::Cactus, biome == Biome::Desert);
+            assert_eq!(tree.base.y, terrain.height_at(tree.base.x, tree.base.z));
+        }
+        for kind in [TreeKind::Broadleaf, TreeKind::Pine, TreeKind::Cactus] {
+            assert!(trees.iter().any(|tree| tree.kind == kind), "no {kind:?} trees");
+        }
+        // Forests are much denser than deserts
+        let count_in = |biome| trees.iter().filter(|tree| terrain.biome_at(tree.base.x, tree.base.z) == biome).count() as f32;
+        let area_of = |biome| samples.iter().filter(|&&(x, z)| terrain.biome_at(x, z) == biome).count() as f32;
+        assert!(count_in(Biome::Grassland) / area_of(Biome::Grassland) > 3.0 * count_in(Biome::Desert) / area_of(Biome::Desert));
+    }
+    
+    #[test]
+    fn test_radar_is_heading_up() {
+        // Flying east: east is straight up the scope and north is to the left
+        let player = Transform::from_xyz(100.0, 50.0, 0.0).looking_to(Vec3::X, Vec3::Y);
+        let ahead = radar_blip(&player, Vec3::new(300.0, 50.0, 0.0), 400.0).unwrap();
+        assert!(ahead.distance(Vec2::new(0.0, 0.5)) < 1e-4);
+        let north = radar_blip(&player, Vec3::new(100.0, 50.0, -200.0), 400.0).unwrap();
+        assert!(north.distance(Vec2::new(-0.5, 0.0)) < 1e-4);
+        assert_eq!(radar_blip(&player, Vec3::new(100.0, 50.0, 500.0), 400.0), None);
+        
+        // Height doesn't move a blip, and nosing up doesn't turn the scope
+        let climbing = player.looking_to(Vec3::new(1.0, 1.0, 0.0), Vec3::Y);
+        let above = radar_blip(&climbing, Vec3::new(300.0, 250.0, 0.0), 400.0).unwrap();
+        assert!(above.distance(Vec2::new(0.0, 0.5)) < 1e-4);
+        let vertical = Transform::from_xyz(100.0, 50.0, 0.0).looking_to(Vec3::Y, Vec3::NEG_X);
+        assert!(radar_blip(&vertical, Vec3::new(300.0, 50.0, 0.0), 400.0).unwrap().distance(Vec2::new(0.0, 0.5)) < 1e-4);
+        
+        assert_eq!(RadarAltitude::of(200.0), RadarAltitude::Above);
+        assert_eq!(RadarAltitude::of(-10.0), RadarAltitude::Level);
+        assert_eq!(RadarAltitude::of(-200.0), RadarAltitude::Below);
+        
+        let mut radar = Radar::default();
+        let ranges: Vec<f32> = (0..radar.ranges.len()).map(|zoom| {
+            radar.zoom = zoom;
+            radar.range()
+        }).collect();
+        assert!(ranges.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+    
+    #[test]
+    fn test_enemy_archetypes_load_from_asset_files() {
+        let app = headless_app(60.0);
+        let archetypes = app.world().resource::<EnemyArchetypes>();
+        assert!(archetypes.is_ready());
+        let loaded: Vec<(&str, AircraftModel, u32)> = archetypes.all().iter()
+            .map(|archetype| (archetype.name.as_str(), archetype.model, archetype.score))
+            .collect();
+        assert_eq!(loaded, vec![
+            ("Ace", AircraftModel::Ace, 200),
+            ("Bomber", AircraftModel::Bomber, 100),
+            ("Fighter", AircraftModel::Fighter, 50),
+        ]);
+        assert_eq!(archetypes.largest_ramming_radius(), 8.0);
+        
+        // Aces get likelier as the difficulty rises, and never fly in formation
+        let ace = &archetypes.all()[0];
+        assert!(ace.spawn_weight(3.0) > ace.spawn_weight(1.0));
+        let mut rng = fastrand::Rng::with_seed(4);
+        assert!((0..200).all(|_| archetypes.pick_formation(&mut rng).unwrap().name != "Ace"));
+        
+        // Picks follow the weights and the seed, and nothing comes of no weight at all
+        let pick = |seed| {
+            let mut rng = fastrand::Rng::with_seed(seed);
+            (0..100).map(|_| pick_weighted(&mut rng, [('a', 1.0), ('b', 0.0), ('c', 3.0)].into_iter()).unwrap()).collect::<String>()
+        };
+        assert_eq!(pick(9), pick(9));
+        assert!(!pick(9).contains('b'));
+        assert!(pick(9).matches('c').count() > pick(9).matches('a').count());
+        assert_eq!(pick_weighted(&mut rng, [('a', 0.0)].into_iter()), None);
+        
+        // A file that doesn't parse is reported rather than spawning a broken enemy
+        let broken = ron::de::from_str::<EnemyArchetype>("(name: \"Drone\", speed: fast)");
+        assert!(broken.is_err());
+    }
+    
+    #[test]
+    fn test_formation_pilots_come_from_their_archetype() {
+        let mut app = headless_app(60.0);
+        let bomber = app.world().resource::<EnemyArchetypes>().all()[1].clone();
+        assert_eq!(bomber.name, "Bomber");
+        for _ in 0..5 {
+            let archetype = bomber.clone();
+            app.world_mut().run_system_once(move |mut commands: Commands, models: Res<AircraftModels>, mut rng: ResMut<GameRng>| {
+                spawn_formation_at(&mut commands, &models, &mut rng.spawns, archetype.clone(), Vec3::new(0.0, 300.0, 0.0), Vec3::ZERO);
+            }).unwrap();
+        }
+        
+        // Bombers are middling pilots and never aggressive, in a formation or not
+        let world = app.world_mut();
+        let pilots: Vec<(PilotPersonality, f32, bool)> = world
+            .query::<&Enemy>()
+            .iter(world)
+            .map(|enemy| (enemy.personality, enemy.maneuver_skill, enemy.formation_role.is_some()))
+            .collect();
+        assert_eq!(pilots.len(), 15);
+        for (personality, skill, in_formation) in pilots {
+            assert!(in_formation);
+            assert!(matches!(personality, PilotPersonality::Defensive | PilotPersonality::Tactical), "{:?}", personality);
+            assert!((0.2..=0.4).contains(&skill), "skill {}", skill);
+        }
+    }
+    
+    #[test]
+    fn test_damaged_enemies_limp_and_shot_down_ones_crash() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::FreePlay, Some(3));
+        let (enemy, _) = wait_for_enemy(&mut app);
+        // Nothing else is allowed to end the run while the wreck falls
+        let world = app.world_mut();
+        world.query_filtered::<&mut Health, With<Aircraft>>().single_mut(world).unwrap().current = 1.0e6;
+        
+        // Takes a share of the enemy's full health off it
+        let hit = |app: &mut App, share: f32| {
+            app.world_mut().run_system_once(move |mut commands: Commands, mut game_stats: ResMut<GameStats>, mut enemies: Query<(&Transform, &mut Health, &mut Enemy)>| {
+                let (transform, mut health, mut target) = enemies.get_mut(enemy).unwrap();
+                let damage = health.max * share;
+                damage_enemy(&mut commands, &mut game_stats, enemy, transform, &mut health, &mut target, damage).is_some()
+            }).unwrap()
+        };
+        let (base_speed, base_skill) = {
+            let enemy = app.world().get::<Enemy>(enemy).unwrap();
+            (enemy.archetype.speed, enemy.maneuver_skill)
+        };
+        
+        assert!(!hit(&mut app, 0.6));
+        let damaged = app.world().get::<Enemy>(enemy).unwrap();
+        assert_eq!(damaged.damage_state, DamageState::Heavy);
+        assert_eq!(damaged.speed, base_speed * 0.75);
+        assert!((damaged.maneuver_skill - base_skill * 0.65).abs() < 1e-5);
+        
+        assert!(!hit(&mut app, 0.2));
+        let damaged = app.world().get::<Enemy>(enemy).unwrap();
+        assert_eq!(damaged.damage_state, DamageState::Critical);
+        assert!(matches!(damaged.behavior_state, EnemyBehaviorState::Retreating));
+        assert!((damaged.maneuver_skill - base_skill * 0.4).abs() < 1e-5);
+        
+        // Shot down, it's scored at once but stays in the world as a wreck until it hits the ground
+        let destroyed = app.world().resource::<GameStats>().enemies_destroyed;
+        assert!(hit(&mut app, 0.5));
+        assert!(app.world().get::<Enemy>(enemy).is_none());
+        assert!(app.world().get::<Crashing>(enemy).is_some());
+        assert_eq!(app.world().resource::<GameStats>().enemies_destroyed, destroyed + 1);
+        
+        let mut heights = Vec::new();
+        for _ in 0..900 {
+            app.update();
+            match app.world().get::<Transform>(enemy) {
+                Some(transform) => heights.push(transform.translation.y),
+                None => break,
+            }
+        }
+        assert!(app.world().get_entity(enemy).is_err(), "the wreck never hit the ground");
+        assert!(heights.len() > 30, "the wreck vanished instead of falling");
+        assert!(heights.windows(2).skip(30).all(|pair| pair[1] < pair[0]));
+    }
+    
+    #[test]
+    fn test_enemies_shot_down_in_the_same_step_cannot_be_rammed() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::FreePlay, Some(3));
+        let (enemy, _) = wait_for_enemy(&mut app);
+        let world = app.world_mut();
+        world.query_filtered::<&mut Health, With<Aircraft>>().single_mut(world).unwrap().current = 1.0e6;
+        let player = *world.query_filtered::<&Transform, With<Aircraft>>().single(world).unwrap();
+        let ram = |app: &mut App, health: f32| {
+            let world = app.world_mut();
+            world.get_mut::<Health>(enemy).unwrap().current = health;
+            *world.get_mut::<Transform>(enemy).unwrap() = player;
+            *world.get_mut::<InterpolatedTransform>(enemy).unwrap() = InterpolatedTransform::new(player);
+            let score = world.resource::<GameStats>().score;
+            app.update();
+            app.world().resource::<GameStats>().score - score
+        };
+        
+        // Already brought down by a bullet or missile this step, with its crash not yet applied
+        assert_eq!(ram(&mut app, 0.0), 0);
+        assert!(app.world().get::<Crashing>(enemy).is_none());
+        
+        let points = app.world().get::<Enemy>(enemy).unwrap().archetype.ramming.points;
+        assert_eq!(ram(&mut app, 10.0), points);
+        assert!(app.world().get::<Crashing>(enemy).is_some());
+    }
+    
+    #[test]
+    fn test_ground_sites_come_from_the_terrain_seed() {
+        let sites = |seed| {
+            let height_field = TerrainHeightField::from_seed(seed);
+            (-12..12)
+                .flat_map(|x| (-12..12).map(move |z| IVec2::new(x, z)))
+                .filter_map(|cell| ground_site_in_cell(&height_field, cell))
+                .collect::<Vec<_>>()
+        };
+        let found = sites(7);
+        assert_eq!(found, sites(7));
+        assert_ne!(found, sites(8));
+        
+        let height_field = TerrainHeightField::from_seed(7);
+        for kind in GroundUnitKind::ALL {
+            assert!(found.iter().any(|(found_kind, _)| *found_kind == kind), "no {kind:?}");
+        }
+        for (_, base) in &found {
+            assert!(Vec2::new(base.x, base.z).length() > 600.0, "a site is too close to the start");
+            assert!(base.y > SEA_LEVEL);
+            assert_eq!(base.y, height_field.height_at(base.x, base.z));
+        }
+    }
+    
+    // The nearest site of `kind` to where runs start, in the current run's terrain
+    fn nearest_ground_site(app: &App, kind: GroundUnitKind) -> Vec3 {
+        let height_field = app.world().resource::<TerrainHeightField>();
+        (-6..6)
+            .flat_map(|x| (-6..6).map(move |z| IVec2::new(x, z)))
+            .filter_map(|cell| ground_site_in_cell(height_field, cell))
+            .filter(|(found_kind, _)| *found_kind == kind)
+            .map(|(_, base)| base)
+            .min_by(|a, b| a.length().total_cmp(&b.length()))
+            .expect("no site nearby")
+    }
+    
+    fn ground_unit_at(app: &mut App, base: Vec3) -> Option<Entity> {
+        let world = app.world_mut();
+        world.query_filtered::<(Entity, &Transform), With<GroundUnit>>().iter(world)
+            .find(|(_, transform)| transform.translation.distance(base) < 0.01)
+            .map(|(entity, _)| entity)
+    }
+    
+    #[test]
+    fn test_anti_aircraft_guns_fire_flak_and_stay_destroyed() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::FreePlay, Some(3));
+        app.update();
+        let base = nearest_ground_site(&app, GroundUnitKind::AaGun);
+        let world = app.world_mut();
+        world.query_filtered::<&mut Health, With<Aircraft>>().single_mut(world).unwrap().current = 1.0e6;
+        
+        // Flying past the gun, well inside its range
+        let pass = Transform::from_translation(base + Vec3::new(-200.0, 150.0, 200.0)).looking_to(Vec3::X, Vec3::Y);
+        place_player(&mut app, pass, Vec3::X * 50.0);
+        let mut shells = 0;
+        for _ in 0..120 {
+            app.update();
+            let world = app.world_mut();
+            shells = shells.max(world.query::<&FlakShell>().iter(world).count());
+        }
+        assert!(shells > 0, "the gun never fired");
+        let gun = ground_unit_at(&mut app, base).expect("the gun wasn't put down");
+        
+        let score = app.world().resource::<GameStats>().score;
+        let destroyed = app.world_mut().run_system_once(move |mut commands: Commands, mut game_stats: ResMut<GameStats>, mut units: Query<(&Transform, &mut Health, &GroundUnit)>| {
+            let (transform, mut health, unit) = units.get_mut(gun).unwrap();
+            damage_ground_unit(&mut commands, &mut game_stats, gun, transform, &mut health, unit, 1000.0).is_some()
+        }).unwrap();
+        assert!(destroyed);
+        assert_eq!(app.world().resource::<GameStats>().score, score + GroundUnitKind::AaGun.score());
+        
+        for _ in 0..30 {
+            app.update();
+        }
+        assert_eq!(app.world().resource::<GroundSites>().destroyed.len(), 1);
+        assert!(ground_unit_at(&mut app, base).is_none(), "the gun came back");
+    }
+    
+    #[test]
+    fn test_radar_stations_alert_patrols_unless_flown_under() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::FreePlay, Some(3));
+        let (enemy, _) = wait_for_enemy(&mut app);
+        let base = nearest_ground_site(&app, GroundUnitKind::RadarStation);
+        let height_field = *app.world().resource::<TerrainHeightField>();
+        let tracking = |app: &mut App| {
+            let world = app.world_mut();
+            world.query::<&GroundUnit>().iter(world).any(|unit| unit.tracking)
+        };
+        
+        // Low over the ground the station can't see the player
+        let approach = base + Vec3::new(300.0, 0.0, 0.0);
+        let low = Transform::from_translation(approach.with_y(height_field.height_at(approach.x, approach.z) + 10.0));
+        place_player(&mut app, low, Vec3::ZERO);
+        app.update();
+        app.update();
+        assert!(ground_unit_at(&mut app, base).is_some());
+        assert!(!tracking(&mut app));
+        
+        // Higher up it can, and patrols near the station are sent after the player
+        let high = Transform::from_translation(approach.with_y(height_field.height_at(approach.x, approach.z) + 150.0));
+        place_player(&mut app, high, Vec3::ZERO);
+        let patrol = Transform::from_translation(base + Vec3::new(-500.0, 120.0, 0.0));
+        let world = app.world_mut();
+        *world.get_mut::<Transform>(enemy).unwrap() = patrol;
+        *world.get_mut::<InterpolatedTransform>(enemy).unwrap() = InterpolatedTransform::new(patrol);
+        app.update();
+        app.update();
+        assert!(tracking(&mut app));
+        assert!(app.world().get::<Enemy>(enemy).unwrap().alert_timer > 0.0);
+    }
+    
+    #[test]
+    fn test_sams_chase_down_the_player() {
+        let mut app = headless_app(60.0);
+        start_run(&mut app, GameMode::FreePlay, Some(3));
+        app.update();
+        let world = app.world_mut();
+        let player = world.query_filtered::<&Transform, With<Aircraft>>().single(world).unwrap().translation;
+        let health = world.query_filtered::<&Health, With<Aircraft>>().single(world).unwrap().current;
+        
+        // Off to one side of the player's path and below it, climbing away from them
+        let launch = Transform::from_translation(player + Vec3::new(150.0, -20.0, -250.0));
+        world.spawn((
+            launch,
+            InterpolatedTransform::new(launch),
+            SamMissile { velocity: Vec3::new(0.0, 30.0, -30.0), fuel: 5.0, lifetime: 9.0 },
+        ));
+        let mut detonated = false;
+        for _ in 0..540 {
+            app.update();
+            let world = app.world_mut();
+            if world.query::<&SamMissile>().iter(world).count() == 0 {
+                detonated = true;
+                break;
+            }
+        }
+        assert!(detonated);
+        let world = app.world_mut();
+        let left = world.query_filtered::<&Health, With<Aircraft>>().single(world).unwrap().current;
+        assert!(left <= health - 35.0, "the missile missed: {health} -> {left}");
+    }
+    
+    // Starts Survival with the first boss wave due straight away, and returns the boss once it's arrived
+    fn survival_boss(app: &mut App) -> Entity {
+        start_run(app, GameMode::Survival, Some(3));
+        app.update();
+        let world = app.world_mut();
+        world.query_filtered::<&mut Health, With<Aircraft>>().single_mut(world).unwrap().current = 1.0e6;
+        world.resource_mut::<BossWaves>().countdown = 0.0;
+        for _ in 0..10 {
+            app.update();
+            let world = app.world_mut();
+            if let Ok(boss) = world.query_filtered::<Entity, With<Boss>>().single(world) {
+                return boss;
+            }
+        }
+        panic!("no boss turned up");
+    }
+    
+    #[test]
+    fn test_survival_bosses_stop_the_clock_and_change_phase() {
+        let mut app = headless_app(60.0);
+        let boss_entity = survival_boss(&mut app);
+        let boss = app.world().get::<Boss>(boss_entity).unwrap().clone();
+        assert_eq!(boss.kind, BossKind::for_wave(1));
+        assert_eq!(boss.phase, BossPhase::Approach);
+        assert_eq!(boss.parts.len(), boss.kind.parts().len());
+        assert_eq!((boss.turrets_left, boss.engines_left), (4, 4));
+        let world = app.world_mut();
+        assert_eq!(world.query_filtered::<(), (With<BossPart>, With<Health>)>().iter(world).count(), 8);
+        
+        let clock = app.world().resource::<ChallengeTimer>().time_remaining;
+        for _ in 0..30 {
+            app.update();
+        }
+        assert_eq!(app.world().resource::<ChallengeTimer>().time_remaining, clock);
+        
+        // Half its health gone brings in the next phase and another escort
+        let world = app.world_mut();
+        let escorts = world.query::<&Enemy>().iter(world).count();
+        let mut health = world.get_mut::<Health>(boss_entity).unwrap();
+        health.current = health.max * 0.5;
+        app.update();
+        app.update();
+        assert_eq!(app.world().get::<Boss>(boss_entity).unwrap().phase, BossPhase::Reinforced);
+        let world = app.world_mut();
+        assert!(world.query::<&Enemy>().iter(world).count() >= escorts + 3);
+    }
+    
+    #[test]
+    fn test_boss_hits_find_parts_before_the_hull() {
+        let transform = Transform::from_xyz(100.0, 200.0, -300.0).looking_to(Vec3::X, Vec3::Y);
+        let kind = BossKind::Gunship;
+        let (offset, part_kind) = kind.parts().iter().map(|(part_kind, offset)| (*offset, *part_kind)).next().unwrap();
+        let part = BossPart { boss: Entity::PLACEHOLDER, kind: part_kind, offset, cooldown: 0.0 };
+        let turret = Entity::from_raw(7);
+        
+        // Straight down through the first turret, then through the middle of the fuselage
+        let center = transform.transform_point(offset);
+        let hit = boss_hit(&transform, kind, [(turret, &part)].into_iter(), center + Vec3::Y * 20.0, center - Vec3::Y * 20.0, 0.0);
+        assert!(matches!(hit, Some((BossHit::Part(entity), _)) if entity == turret));
+        let hit = boss_hit(&transform, kind, std::iter::empty(), center + Vec3::Y * 20.0, center - Vec3::Y * 20.0, 0.0);
+        assert!(matches!(hit, Some((BossHit::Hull, _))));
+        let above = transform.translation + Vec3::Y * 60.0;
+        assert!(boss_hit(&transform, kind, [(turret, &part)].into_iter(), above, above + Vec3::X * 50.0, 0.0).is_none());
+    }
+    
+    #[test]
+    fn test_boss_parts_and_kills_pay_out() {
+        let mut app = headless_app(60.0);
+        let boss_entity = survival_boss(&mut app);
+        let (turret, kind) = {
+            let world = app.world_mut();
+            let boss = world.get::<Boss>(boss_entity).unwrap().clone();
+            let turret = boss.parts.iter().copied()
+                .find(|part| world.get::<BossPart>(*part).unwrap().kind == BossPartKind::Turret)
+                .unwrap();
+            (turret, boss.kind)
+        };
+        
+        // A turret going down scores, stops it firing and costs the hull a share of its health
+        let score = app.world().resource::<GameStats>().score;
+        let destroyed = app.world_mut().run_system_once(move |mut commands: Commands, mut game_stats: ResMut<GameStats>, mut bosses: Query<(&Transform, &mut Health, &mut Boss)>, mut parts: Query<(&BossPart, &mut Health), Without<Boss>>| {
+            let (transform, mut health, mut boss) = bosses.get_mut(boss_entity).unwrap();
+            let (part, mut part_health) = parts.get_mut(turret).unwrap();
+            damage_boss_part(&mut commands, &mut game_stats, turret, part, &mut part_health, transform, &mut health, &mut boss, 1000.0).is_some()
+        }).unwrap();
+        assert!(destroyed);
+        assert_eq!(app.world().resource::<GameStats>().score, score + BossPartKind::Turret.score());
+        assert!(app.world().get::<Health>(turret).is_none());
+        let boss = app.world().get::<Boss>(boss_entity).unwrap();
+        assert_eq!((boss.turrets_left, boss.engines_left), (3, 4));
+        let health = app.world().get::<Health>(boss_entity).unwrap();
+        assert!((health.current - health.max * 0.92).abs() < 0.01);
+        
+        // Bringing it down pays its score and coins on top of the ones for points
+        let score = app.world().resource::<GameStats>().score;
+        let destroyed = app.world_mut().run_system_once(move |mut commands: Commands, mut game_stats: ResMut<GameStats>, mut bosses: Query<(&Transform, &mut Health, &Boss)>| {
+            let (transform, mut health, boss) = bosses.get_mut(boss_entity).unwrap();
+            damage_boss(&mut commands, &mut game_stats, boss_entity, transform, &mut health, boss, 1.0e6).is_some()
+        }).unwrap();
+        assert!(destroyed);
+        let game_stats = app.world().resource::<GameStats>();
+        assert_eq!(game_stats.score, score + kind.score());
+        assert_eq!(game_stats.bonus_coins, kind.coins());
+        assert_eq!(game_stats.coins_earned(), game_stats.score / 100 + kind.coins());
+        app.update();
+        assert!(app.world().get_entity(boss_entity).is_err());
+        assert!(app.world().get_entity(turret).is_err());
+        let world = app.world_mut();
+        assert_eq!(world.query::<&BossPart>().iter(world).count(), 0);
+    }
+}
@@ -1,6 +1,18 @@
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts};
-use crate::game_state::{GameState, GameMode, CurrentGameMode, GameStats, ChallengeTimer, UpgradeData, get_upgrade_cost};
+use crate::ghost::GhostState;
+use crate::flight::FlightModel;
+use crate::lod::{DebugOverlay, ENTITY_BUDGET, TRIANGLE_BUDGET};
+use crate::missiles::{Missile, MissileLock};
+use crate::weapons::{Loadout, WeaponSlot};
+use crate::targeting::TargetLock;
+use crate::radar::{Radar, RadarAltitude, radar_blip};
+use crate::archetypes::{ArchetypeStatus, EnemyArchetypes};
+use crate::ground::{GroundUnit, SamMissile};
+use crate::boss::{Boss, BossPhase};
+use crate::terrain::TERRAIN_LOD_RESOLUTIONS;
+use crate::replay::{ReplayPlayback, ReplayStore, load_replay, restart_playback, start_playback};
+use crate::game_state::{GameState, GameMode, CurrentGameMode, GameStats, ChallengeTimer, UpgradeData, GameRng, SeedSettings, RunClock, get_upgrade_cost};
 
 pub fn main_menu_ui(
     mut contexts: EguiContexts,
@@ -8,6 +20,12 @@ pub fn main_menu_ui(
     mut game_mode: ResMut<CurrentGameMode>,
     mut game_stats: ResMut<GameStats>,
     mut challenge_timer: ResMut<ChallengeTimer>,
+    mut seed_settings: ResMut<SeedSettings>,
+    mut seed_text: Local<String>,
+    replay_store: Res<ReplayStore>,
+    mut flight_model: ResMut<FlightModel>,
+    archetypes: Res<EnemyArchetypes>,
+    mut commands: Commands,
 ) {
     let ctx = contexts.ctx_mut();
     
@@ -16,6 +34,23 @@ pub fn main_menu_ui(
                 ui.heading(egui::RichText::new("✈️ SKY HUNTER").size(64.0).color(egui::Color32::from_rgb(255, 100, 100)));
                 ui.add_space(20.0);
                 
+                // Runs can't start until the enemy types have loaded
+                match &archetypes.status {
+                    ArchetypeStatus::Ready => {}
+                    ArchetypeStatus::Loading => {
+                        ui.label(egui::RichText::new("Loading enemy types...").size(24.0));
+                        return;
+                    }
+                    ArchetypeStatus::Failed(err) => {
+                        ui.label(egui::RichText::new(format!("Could not load enemy types: {err}")).size(18.0).color(egui::Color32::LIGHT_RED));
+                        ui.add_space(20.0);
+                        if ui.add_sized([200.0, 40.0], egui::Button::new(egui::RichText::new("🚪 Quit Game").size(18.0))).clicked() {
+                            std::process::exit(0);
+                        }
+                        return;
+                    }
+                }
+                
                 ui.label(egui::RichText::new("Choose Your Challenge").size(24.0));
                 ui.add_space(30.0);
                 
@@ -23,6 +58,7 @@ pub fn main_menu_ui(
                     game_mode.mode = GameMode::FreePlay;
                     game_stats.score = 0;
                     game_stats.combo = 0;
+                    *challenge_timer = ChallengeTimer::for_mode(game_mode.mode);
                     game_state.set(GameState::Playing);
                 }
                 ui.label("Fly freely and collect targets");
@@ -33,8 +69,7 @@ pub fn main_menu_ui(
                     game_mode.mode = GameMode::TimeAttack;
                     game_stats.score = 0;
                     game_stats.combo = 0;
-                    challenge_timer.time_remaining = 60.0;
-                    challenge_timer.total_time = 60.0;
+                    *challenge_timer = ChallengeTimer::for_mode(game_mode.mode);
                     game_state.set(GameState::Playing);
                 }
                 ui.label("Score as much as possible in 60 seconds");
@@ -45,6 +80,7 @@ pub fn main_menu_ui(
                     game_mode.mode = GameMode::TargetHunt;
                     game_stats.score = 0;
                     game_stats.combo = 0;
+                    *challenge_timer = ChallengeTimer::for_mode(game_mode.mode);
                     game_state.set(GameState::Playing);
                 }
                 ui.label("Find and destroy special targets");
@@ -55,8 +91,7 @@ pub fn main_menu_ui(
                     game_mode.mode = GameMode::Survival;
                     game_stats.score = 0;
                     game_stats.combo = 0;
-                    challenge_timer.time_remaining = 10.0;
-                    challenge_timer.total_time = 10.0;
+                    *challenge_timer = ChallengeTimer::for_mode(game_mode.mode);
                     game_state.set(GameState::Playing);
                 }
                 ui.label("Hit targets to gain time, miss and lose time");
@@ -67,8 +102,7 @@ pub fn main_menu_ui(
                     game_mode.mode = GameMode::RaceTheClock;
                     game_stats.score = 0;
                     game_stats.combo = 0;
-                    challenge_timer.time_remaining = 120.0;
-                    challenge_timer.total_time = 120.0;
+                    *challenge_timer = ChallengeTimer::for_mode(game_mode.mode);
                     game_state.set(GameState::Playing);
                 }
                 ui.label("Complete objectives before time runs out");
@@ -80,7 +114,53 @@ pub fn main_menu_ui(
                 }
                 ui.label("Upgrade your aircraft");
                 
-                ui.add_space(40.0);
+                ui.add_space(15.0);
+                
+                if let Some(path) = replay_store.last_replay_path() {
+                    if ui.add_sized([300.0, 50.0], egui::Button::new(egui::RichText::new("📼 Watch Last Run").size(20.0))).clicked() {
+                        match load_replay(&path) {
+                            Ok(replay) => {
+                                commands.queue(move |world: &mut World| {
+                                    if let Err(err) = start_playback(world, replay) {
+                                        error!("Could not play replay {}: {err}", path.display());
+                                    }
+                                });
+                            }
+                            Err(err) => error!("Could not load replay {}: {err}", path.display()),
+                        }
+                    }
+                    ui.label("Replay your most recent run");
+                }
+                
+                ui.add_space(30.0);
+                
+                // Seed - leave empty for a random run
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new("🌱 Seed:").size(16.0));
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut *seed_text)
+                            .hint_text("random")
+                            .desired_width(160.0),
+                    );
+                    if response.changed() {
+                        seed_settings.requested = seed_text.trim().parse().ok();
+                    }
+                });
+                if !seed_text.trim().is_empty() && seed_settings.requested.is_none() {
+                    ui.label(egui::RichText::new("Seed must be a whole number").size(14.0).color(egui::Color32::LIGHT_RED));
+                }
+                
+                ui.add_space(15.0);
+                
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new("🛩️ Flight Model:").size(16.0));
+                    for model in [FlightModel::Arcade, FlightModel::Simulation] {
+                        ui.selectable_value(&mut *flight_model, model, egui::RichText::new(model.label()).size(16.0));
+                    }
+                });
+                ui.label(flight_model.description());
+                
+                ui.add_space(30.0);
                 
                 // Stats
                 ui.separator();
@@ -89,6 +169,8 @@ pub fn main_menu_ui(
                     ui.add_space(40.0);
                     ui.label(egui::RichText::new(format!("💰 Coins: {}", game_stats.coins)).size(16.0));
                 });
+                let enemy_names: Vec<&str> = archetypes.all().iter().map(|archetype| archetype.name.as_str()).collect();
+                ui.label(egui::RichText::new(format!("Enemy types: {}", enemy_names.join(", "))).size(14.0).color(egui::Color32::GRAY));
                 
                 ui.add_space(40.0);
                 
@@ -106,12 +188,18 @@ pub fn game_hud(
     challenge_timer: Res<ChallengeTimer>,
     game_mode: Res<CurrentGameMode>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
-    player_query: Query<&crate::enemies::Health, With<crate::Aircraft>>,
-    _active_powerups: Res<crate::powerups::ActivePowerUps>,
+    player_query: Query<(&crate::enemies::Health, &Transform), With<crate::Aircraft>>,
+    target_lock: Res<TargetLock>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<crate::FlightCamera>>,
+    // Enemies and bosses, which can both be locked; bosses also get a health bar of their own
+    enemy_query: Query<(&GlobalTransform, &crate::enemies::Health, Option<&Boss>), Or<(With<crate::enemies::Enemy>, With<Boss>)>>,
     powerup_effects: Query<&crate::powerups::PowerUpEffect>,
     mut radio_chatter_events: EventReader<crate::enemies::RadioChatterEvent>,
-    mut chatter_display: Local<Vec<(String, f32, crate::enemies::EnemyType)>>,
+    mut chatter_display: Local<Vec<(String, f32, Color)>>,
     time: Res<Time>,
+    playback: Option<Res<ReplayPlayback>>,
+    ghost_state: Res<GhostState>,
+    run_clock: Res<RunClock>,
 ) {
     let ctx = contexts.ctx_mut();
     
@@ -119,12 +207,34 @@ pub fn game_hud(
     egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
         ui.horizontal(|ui| {
             ui.label(egui::RichText::new(format!("Score: {}", game_stats.score)).size(24.0).color(egui::Color32::WHITE));
+            
+            if let Some(playback) = &playback {
+                ui.add_space(20.0);
+                ui.label(egui::RichText::new(format!("📼 REPLAY  {}/{}", playback.frame, playback.replay.frames.len()))
+                    .size(20.0)
+                    .color(egui::Color32::from_rgb(255, 80, 80)));
+            }
             ui.add_space(20.0);
             
             if game_stats.combo > 1 {
                 ui.label(egui::RichText::new(format!("Combo x{}", game_stats.combo)).size(20.0).color(egui::Color32::YELLOW));
             }
             
+            // Personal best ghost comparison, e.g. "+2.3s / -450 pts"
+            if let Some(comparison) = ghost_state.compare(game_stats.score, run_clock.elapsed) {
+                ui.add_space(20.0);
+                let text = match comparison.time_delta {
+                    Some(time_delta) => format!("👻 {time_delta:+.1}s / {:+} pts", comparison.points_delta),
+                    None => format!("👻 {:+} pts", comparison.points_delta),
+                };
+                let color = if comparison.points_delta >= 0 {
+                    egui::Color32::from_rgb(100, 255, 100)
+                } else {
+                    egui::Color32::from_rgb(255, 120, 120)
+                };
+                ui.label(egui::RichText::new(text).size(20.0).color(color));
+            }
+            
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                 match game_mode.mode {
                     GameMode::TimeAttack | GameMode::Survival | GameMode::RaceTheClock => {
@@ -192,7 +302,7 @@ pub fn game_hud(
         });
     
     // Health bar
-    if let Ok(health) = player_query.single() {
+    if let Ok((health, _)) = player_query.single() {
         egui::Area::new(egui::Id::new("health_bar"))
             .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -40.0])
             .show(ctx, |ui| {
@@ -217,6 +327,35 @@ pub fn game_hud(
             });
     }
     
+    // Boss health bar, with its phase and how many of its turrets and engines are still working
+    if let Some((health, boss)) = enemy_query.iter().find_map(|(_, health, boss)| boss.map(|boss| (health, boss))) {
+        egui::Area::new(egui::Id::new("boss_health_bar"))
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 50.0])
+            .show(ctx, |ui| {
+                ui.vertical_centered(|ui| {
+                    let phase_color = match boss.phase {
+                        BossPhase::Approach => egui::Color32::WHITE,
+                        BossPhase::Reinforced => egui::Color32::YELLOW,
+                        BossPhase::Desperate => egui::Color32::from_rgb(255, 80, 80),
+                    };
+                    ui.label(egui::RichText::new(format!("☠ {} — WAVE {}", boss.kind.name().to_uppercase(), boss.wave))
+                        .size(20.0)
+                        .strong()
+                        .color(egui::Color32::from_rgb(255, 100, 80)));
+                    ui.add(egui::ProgressBar::new((health.current / health.max).clamp(0.0, 1.0))
+                        .desired_width(420.0)
+                        .fill(egui::Color32::from_rgb(180, 30, 30))
+                        .text(format!("{:.0}/{:.0}", health.current.max(0.0), health.max)));
+                    ui.label(egui::RichText::new(format!(
+                        "{}  ·  Turrets {}  ·  Engines {}",
+                        boss.phase.name(),
+                        boss.turrets_left,
+                        boss.engines_left,
+                    )).size(14.0).color(phase_color));
+                });
+            });
+    }
+    
     // Controls hint
     egui::Area::new(egui::Id::new("controls_hint"))
         .anchor(egui::Align2::LEFT_BOTTOM, [10.0, -10.0])
@@ -228,7 +367,12 @@ pub fn game_hud(
                 ui.label(egui::RichText::new("W/S - Speed Up/Down").size(14.0).color(egui::Color32::GRAY));
                 ui.label(egui::RichText::new("Space - Boost").size(14.0).color(egui::Color32::GRAY));
                 ui.label(egui::RichText::new("Left Click/F - Shoot").size(14.0).color(egui::Color32::GRAY));
+                ui.label(egui::RichText::new("Right Click/E - Fire Secondary").size(14.0).color(egui::Color32::GRAY));
+                ui.label(egui::RichText::new("Q - Cycle Secondary").size(14.0).color(egui::Color32::GRAY));
+                ui.label(egui::RichText::new("T - Cycle Target").size(14.0).color(egui::Color32::GRAY));
+                ui.label(egui::RichText::new("R - Radar Zoom").size(14.0).color(egui::Color32::GRAY));
                 ui.label(egui::RichText::new("ESC - Pause").size(14.0).color(egui::Color32::GRAY));
+                ui.label(egui::RichText::new("F3 - Debug Overlay").size(14.0).color(egui::Color32::GRAY));
             });
         });
     
@@ -247,7 +391,6 @@ pub fn game_hud(
                             crate::powerups::PowerUpType::Shield => ("🛡️", "Shield", egui::Color32::from_rgb(0, 128, 255)),
                             crate::powerups::PowerUpType::SpeedBoost => ("⚡", "Speed Boost", egui::Color32::from_rgb(255, 255, 0)),
                             crate::powerups::PowerUpType::TripleShot => ("🎯", "Triple Shot", egui::Color32::from_rgb(255, 0, 255)),
-                            crate::powerups::PowerUpType::HomingMissiles => ("🚀", "Homing", egui::Color32::from_rgb(255, 0, 0)),
                             _ => continue,
                         };
                         
@@ -260,9 +403,67 @@ pub fn game_hud(
             });
         });
     
+    // Target box around the locked enemy, and the gunsight: a cross where the guns point and a
+    // pipper where they need to point to hit the target
+    if let Ok((camera, camera_transform)) = camera_query.single() {
+        let to_screen = |position: Vec3| {
+            camera
+                .world_to_viewport(camera_transform, position)
+                .ok()
+                .map(|position| egui::pos2(position.x, position.y))
+        };
+        let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Background, egui::Id::new("gunsight")));
+        let sight_color = egui::Color32::from_rgb(120, 255, 120);
+        let stroke = egui::Stroke::new(1.5, sight_color);
+        
+        if let Ok((_, player_transform)) = player_query.single() {
+            let lead_distance = target_lock
+                .lead_point
+                .map_or(300.0, |lead_point| lead_point.distance(player_transform.translation));
+            if let Some(boresight) = to_screen(player_transform.translation + player_transform.forward() * lead_distance) {
+                painter.line_segment([boresight - egui::vec2(8.0, 0.0), boresight + egui::vec2(8.0, 0.0)], stroke);
+                painter.line_segment([boresight - egui::vec2(0.0, 8.0), boresight + egui::vec2(0.0, 8.0)], stroke);
+            }
+        }
+        if let Some(pipper) = target_lock.lead_point.and_then(to_screen) {
+            painter.circle_stroke(pipper, 10.0, stroke);
+            painter.circle_filled(pipper, 2.0, sight_color);
+        }
+        
+        let locked = target_lock.target.and_then(|target| enemy_query.get(target).ok());
+        if let Some((enemy_transform, health, _)) = locked {
+            if let Some(center) = to_screen(enemy_transform.translation()) {
+                let half = 24.0;
+                painter.rect_stroke(
+                    egui::Rect::from_center_size(center, egui::vec2(half * 2.0, half * 2.0)),
+                    0.0,
+                    stroke,
+                    egui::StrokeKind::Outside,
+                );
+                let distance = camera_transform.translation().distance(enemy_transform.translation());
+                painter.text(
+                    center + egui::vec2(half + 4.0, -half),
+                    egui::Align2::LEFT_TOP,
+                    format!("{distance:.0}m"),
+                    egui::FontId::proportional(13.0),
+                    sight_color,
+                );
+                // Health bar along the bottom of the box
+                let bar = egui::Rect::from_min_size(center + egui::vec2(-half, half + 4.0), egui::vec2(half * 2.0, 4.0));
+                painter.rect_filled(bar, 0.0, egui::Color32::from_rgba_unmultiplied(0, 0, 0, 150));
+                let fraction = (health.current / health.max).clamp(0.0, 1.0);
+                painter.rect_filled(
+                    egui::Rect::from_min_size(bar.min, egui::vec2(bar.width() * fraction, bar.height())),
+                    0.0,
+                    sight_color,
+                );
+            }
+        }
+    }
+    
     // Process new radio chatter events
     for event in radio_chatter_events.read() {
-        chatter_display.push((event.message.clone(), 5.0, event.sender_type));
+        chatter_display.push((event.message.clone(), 5.0, event.sender.hud_color()));
         
         // Keep only the last 5 messages
         if chatter_display.len() > 5 {
@@ -284,13 +485,10 @@ pub fn game_hud(
                     ui.label(egui::RichText::new("RADIO CHATTER").size(14.0).color(egui::Color32::LIGHT_GRAY));
                     ui.add_space(5.0);
                     
-                    for (message, timer, enemy_type) in chatter_display.iter() {
+                    for (message, timer, sender_color) in chatter_display.iter() {
                         let alpha = (*timer / 5.0 * 255.0) as u8;
-                        let color = match enemy_type {
-                            crate::enemies::EnemyType::Fighter => egui::Color32::from_rgba_unmultiplied(255, 100, 100, alpha),
-                            crate::enemies::EnemyType::Bomber => egui::Color32::from_rgba_unmultiplied(150, 150, 150, alpha),
-                            crate::enemies::EnemyType::Ace => egui::Color32::from_rgba_unmultiplied(100, 100, 255, alpha),
-                        };
+                        let [red, green, blue, _] = sender_color.to_srgba().to_u8_array();
+                        let color = egui::Color32::from_rgba_unmultiplied(red, green, blue, alpha);
                         
                         ui.label(egui::RichText::new(format!("📻 {}", message)).size(12.0).color(color));
                     }
@@ -301,6 +499,239 @@ pub fn game_hud(
     // Pause handling moved to handle_escape_key in main.rs
 }
 
+// Cannon heat, and the secondaries with their ammo, the selected one highlighted
+pub fn weapon_hud(
+    mut contexts: EguiContexts,
+    loadout: Res<Loadout>,
+) {
+    let ctx = contexts.ctx_mut();
+    let ammo_text = |slot: &WeaponSlot| match slot.ammo {
+        Some(ammo) => format!("{} x{}", slot.weapon.name, ammo),
+        None => slot.weapon.name.to_string(),
+    };
+    
+    egui::Area::new(egui::Id::new("weapon_hud"))
+        .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -75.0])
+        .show(ctx, |ui| {
+            ui.vertical(|ui| {
+                let primary = &loadout.primary;
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new(ammo_text(primary)).size(16.0).color(egui::Color32::WHITE));
+                    let (fill, text) = if primary.overheated {
+                        (egui::Color32::RED, "OVERHEAT")
+                    } else {
+                        (egui::Color32::from_rgb(255, 160, 0), "HEAT")
+                    };
+                    ui.add(egui::ProgressBar::new(primary.heat).desired_width(100.0).fill(fill).text(text));
+                });
+                
+                for (index, slot) in loadout.secondaries.iter().enumerate() {
+                    let color = if index != loadout.selected {
+                        egui::Color32::GRAY
+                    } else if slot.has_ammo() {
+                        egui::Color32::from_rgb(100, 255, 100)
+                    } else {
+                        egui::Color32::RED
+                    };
+                    let marker = if index == loadout.selected { "▶ " } else { "   " };
+                    ui.label(egui::RichText::new(format!("{marker}{}", ammo_text(slot))).size(16.0).color(color));
+                }
+            });
+        });
+}
+
+// Brackets the enemy the next missile will be launched at, and marks enemies already being chased
+pub fn missile_lock_hud(
+    mut contexts: EguiContexts,
+    lock: Res<MissileLock>,
+    missile_query: Query<&Missile>,
+    enemy_query: Query<&GlobalTransform, Or<(With<crate::enemies::Enemy>, With<Boss>)>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<crate::FlightCamera>>,
+) {
+    let Ok((camera, camera_transform)) = camera_query.single() else {
+        return;
+    };
+    let ctx = contexts.ctx_mut();
+    let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Background, egui::Id::new("missile_lock")));
+    let lock_color = egui::Color32::from_rgb(255, 60, 60);
+    let screen_position = |entity: Entity| {
+        let world_position = enemy_query.get(entity).ok()?.translation();
+        let position = camera.world_to_viewport(camera_transform, world_position).ok()?;
+        Some((egui::pos2(position.x, position.y), camera_transform.translation().distance(world_position)))
+    };
+    
+    for target in missile_query.iter().filter_map(|missile| missile.target) {
+        if let Some((center, _)) = screen_position(target) {
+            painter.circle_stroke(center, 6.0, egui::Stroke::new(2.0, egui::Color32::from_rgb(255, 160, 60)));
+        }
+    }
+    
+    if let Some((center, distance)) = lock.target.and_then(screen_position) {
+        let half = 18.0;
+        let corner = 7.0;
+        let stroke = egui::Stroke::new(2.0, lock_color);
+        for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+            let tip = center + egui::vec2(x * half, y * half);
+            painter.line_segment([tip, tip - egui::vec2(x * corner, 0.0)], stroke);
+            painter.line_segment([tip, tip - egui::vec2(0.0, y * corner)], stroke);
+        }
+        painter.text(
+            center + egui::vec2(0.0, half + 4.0),
+            egui::Align2::CENTER_TOP,
+            format!("LOCK {distance:.0}m"),
+            egui::FontId::proportional(13.0),
+            lock_color,
+        );
+    }
+}
+
+const RADAR_SIZE: f32 = 180.0;
+
+// Heading-up scope of everything around the player: enemies by type, balloons by kind and
+// power-ups, each drawn as a triangle pointing up or down when it's above or below
+pub fn radar_hud(
+    mut contexts: EguiContexts,
+    radar: Res<Radar>,
+    player_query: Query<&Transform, With<crate::Aircraft>>,
+    enemy_query: Query<(&Transform, &crate::enemies::Enemy)>,
+    target_query: Query<(&Transform, &crate::targets::Target)>,
+    powerup_query: Query<&Transform, With<crate::powerups::PowerUp>>,
+    ground_query: Query<(&Transform, &GroundUnit)>,
+    sam_query: Query<&Transform, With<SamMissile>>,
+    boss_query: Query<&Transform, With<Boss>>,
+) {
+    let Ok(player_transform) = player_query.single() else {
+        return;
+    };
+    let ctx = contexts.ctx_mut();
+    let range = radar.range();
+    let to_egui = |color: Color| {
+        let [r, g, b, _] = color.to_srgba().to_u8_array();
+        egui::Color32::from_rgb(r, g, b)
+    };
+    
+    egui::Area::new(egui::Id::new("radar"))
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+        .show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                let (response, painter) = ui.allocate_painter(egui::vec2(RADAR_SIZE, RADAR_SIZE), egui::Sense::hover());
+                let center = response.rect.center();
+                let radius = RADAR_SIZE * 0.5 - 2.0;
+                let grid = egui::Stroke::new(1.0, egui::Color32::from_rgba_unmultiplied(100, 255, 100, 90));
+                
+                painter.circle_filled(center, radius, egui::Color32::from_rgba_unmultiplied(0, 20, 0, 170));
+                painter.circle_stroke(center, radius * 0.5, grid);
+                painter.circle_stroke(center, radius, egui::Stroke::new(2.0, egui::Color32::from_rgb(100, 255, 100)));
+                painter.line_segment([center - egui::vec2(radius, 0.0), center + egui::vec2(radius, 0.0)], grid);
+                painter.line_segment([center - egui::vec2(0.0, radius), center + egui::vec2(0.0, radius)], grid);
+                // The player, always at the centre facing up
+                painter.add(egui::Shape::convex_polygon(
+                    vec![center + egui::vec2(0.0, -6.0), center + egui::vec2(4.0, 4.0), center + egui::vec2(-4.0, 4.0)],
+                    egui::Color32::WHITE,
+                    egui::Stroke::NONE,
+                ));
+                
+                let plot = |position: Vec3, color: egui::Color32| {
+                    let Some(blip) = radar_blip(player_transform, position, range) else {
+                        return;
+                    };
+                    let point = center + egui::vec2(blip.x, -blip.y) * radius;
+                    let size = 4.0;
+                    match RadarAltitude::of(position.y - player_transform.translation.y) {
+                        RadarAltitude::Level => {
+                            painter.circle_filled(point, size * 0.8, color);
+                        }
+                        altitude => {
+                            let tip = if altitude == RadarAltitude::Above { -size } else { size };
+                            painter.add(egui::Shape::convex_polygon(
+                                vec![point + egui::vec2(0.0, tip), point + egui::vec2(size, -tip), point + egui::vec2(-size, -tip)],
+                                color,
+                                egui::Stroke::NONE,
+                            ));
+                        }
+                    }
+                };
+                
+                for transform in powerup_query.iter() {
+                    plot(transform.translation, egui::Color32::from_rgb(120, 200, 255));
+                }
+                for (transform, target) in target_query.iter() {
+                    plot(transform.translation, to_egui(target.target_type.color()));
+                }
+                for (transform, enemy) in enemy_query.iter() {
+                    plot(transform.translation, to_egui(enemy.archetype.hud_color()));
+                }
+                // Ground units are squares, whatever their height
+                for (transform, unit) in ground_query.iter() {
+                    if let Some(blip) = radar_blip(player_transform, transform.translation, range) {
+                        let point = center + egui::vec2(blip.x, -blip.y) * radius;
+                        painter.rect_filled(egui::Rect::from_center_size(point, egui::vec2(6.0, 6.0)), 0.0, to_egui(unit.kind.hud_color()));
+                    }
+                }
+                for transform in sam_query.iter() {
+                    plot(transform.translation, egui::Color32::WHITE);
+                }
+                // Bosses are a big ring, kept on the rim when they're out of range so they can be found
+                for transform in boss_query.iter() {
+                    let distance = transform.translation.distance(player_transform.translation);
+                    let blip = radar_blip(player_transform, transform.translation, range)
+                        .or_else(|| radar_blip(player_transform, transform.translation, distance).map(|blip| blip.normalize_or_zero()));
+                    if let Some(blip) = blip {
+                        let point = center + egui::vec2(blip.x, -blip.y) * radius;
+                        painter.circle_stroke(point, 7.0, egui::Stroke::new(2.5, egui::Color32::from_rgb(255, 60, 60)));
+                    }
+                }
+                
+                ui.label(egui::RichText::new(format!("RADAR {range:.0}m")).size(13.0).color(egui::Color32::from_rgb(100, 255, 100)));
+                if !sam_query.is_empty() {
+                    ui.label(egui::RichText::new("⚠ MISSILE").size(16.0).strong().color(egui::Color32::RED));
+                } else if ground_query.iter().any(|(_, unit)| unit.tracking) {
+                    ui.label(egui::RichText::new("RADAR CONTACT").size(14.0).color(egui::Color32::YELLOW));
+                }
+            });
+        });
+}
+
+// Scene cost against the draw budgets, toggled with F3
+pub fn debug_overlay_ui(
+    mut contexts: EguiContexts,
+    overlay: Res<DebugOverlay>,
+) {
+    if !overlay.visible {
+        return;
+    }
+    let ctx = contexts.ctx_mut();
+    let stats = &overlay.stats;
+    
+    let budget_line = |ui: &mut egui::Ui, label: &str, value: usize, budget: usize| {
+        let color = if value > budget { egui::Color32::RED } else { egui::Color32::LIGHT_GREEN };
+        ui.label(egui::RichText::new(format!("{label}: {value} / {budget}")).size(14.0).color(color));
+    };
+    
+    egui::Area::new(egui::Id::new("debug_overlay"))
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-RADAR_SIZE - 20.0, -10.0])
+        .show(ctx, |ui| {
+            egui::Frame::new()
+                .fill(egui::Color32::from_rgba_unmultiplied(0, 0, 0, 180))
+                .inner_margin(8.0)
+                .show(ui, |ui| {
+                    ui.label(egui::RichText::new("DEBUG").size(16.0).color(egui::Color32::WHITE));
+                    budget_line(ui, "Entities", stats.entities, ENTITY_BUDGET);
+                    budget_line(ui, "Triangles", stats.triangles, TRIANGLE_BUDGET);
+                    ui.label(egui::RichText::new(format!("Meshes: {}", stats.meshes)).size(14.0).color(egui::Color32::GRAY));
+                    let chunks = stats.terrain_chunks.iter()
+                        .zip(TERRAIN_LOD_RESOLUTIONS)
+                        .map(|(count, resolution)| format!("{count}×{resolution}"))
+                        .collect::<Vec<_>>()
+                        .join("  ");
+                    ui.label(egui::RichText::new(format!("Terrain chunks: {chunks}")).size(14.0).color(egui::Color32::GRAY));
+                    ui.label(egui::RichText::new(format!("Models near/far: {} / {}", stats.near_models, stats.far_models)).size(14.0).color(egui::Color32::GRAY));
+                    ui.label(egui::RichText::new(format!("Pooled out/free: {} / {}", stats.pooled_active, stats.pooled_free)).size(14.0).color(egui::Color32::GRAY));
+                    ui.label(egui::RichText::new(format!("Particles: {}", stats.particles)).size(14.0).color(egui::Color32::GRAY));
+                });
+        });
+}
+
 pub fn pause_menu(
     mut contexts: EguiContexts,
     mut game_state: ResMut<NextState<GameState>>,
@@ -308,6 +739,9 @@ pub fn pause_menu(
     mut game_stats: ResMut<GameStats>,
     mut challenge_timer: ResMut<ChallengeTimer>,
     game_mode: Res<CurrentGameMode>,
+    mut commands: Commands,
+    game_entities: Query<Entity, With<crate::GameEntity>>,
+    playback: Option<Res<ReplayPlayback>>,
 ) {
     let ctx = contexts.ctx_mut();
     
@@ -343,31 +777,30 @@ pub fn pause_menu(
             
             ui.add_space(20.0);
             
-            if ui.add_sized([250.0, 60.0], egui::Button::new(egui::RichText::new("🔄 Restart").size(24.0))).clicked() {
-                // Reset game stats for restart
-                game_stats.score = 0;
-                game_stats.combo = 0;
-                game_stats.targets_hit = 0;
-                game_stats.time_played = 0.0;
-                
-                // Reset timer based on game mode
-                match game_mode.mode {
-                    GameMode::TimeAttack => {
-                        challenge_timer.time_remaining = 60.0;
-                        challenge_timer.total_time = 60.0;
-                    }
-                    GameMode::Survival => {
-                        challenge_timer.time_remaining = 10.0;
-                        challenge_timer.total_time = 10.0;
-                    }
-                    GameMode::RaceTheClock => {
-                        challenge_timer.time_remaining = 120.0;
-                        challenge_timer.total_time = 120.0;
-                    }
-                    _ => {}
+            let restart_label = if playback.is_some() { "🔄 Restart Replay" } else { "🔄 Restart" };
+            if ui.add_sized([250.0, 60.0], egui::Button::new(egui::RichText::new(restart_label).size(24.0))).clicked() {
+                // Clear the current world so entering Playing builds a fresh one
+                for entity in game_entities.iter() {
+                    commands.entity(entity).despawn();
                 }
                 
-                game_state.set(GameState::Playing);
+                // A replay starts over from its first frame; a live restart would get a new seed
+                // while the rest of the replay's input kept feeding it
+                if playback.is_some() {
+                    commands.queue(restart_playback);
+                } else {
+                    // Reset game stats for restart
+                    game_stats.score = 0;
+                    game_stats.combo = 0;
+                    game_stats.targets_hit = 0;
+                    game_stats.time_played = 0.0;
+                    game_stats.bonus_coins = 0;
+                    
+                    // Reset timer based on game mode
+                    *challenge_timer = ChallengeTimer::for_mode(game_mode.mode);
+                    
+                    game_state.set(GameState::Playing);
+                }
             }
             
             ui.add_space(20.0);
@@ -399,6 +832,8 @@ pub fn game_over_screen(
     mut game_stats: ResMut<GameStats>,
     mut challenge_timer: ResMut<ChallengeTimer>,
     game_mode: Res<CurrentGameMode>,
+    rng: Res<GameRng>,
+    mut seed_settings: ResMut<SeedSettings>,
 ) {
     let ctx = contexts.ctx_mut();
     
@@ -415,34 +850,32 @@ pub fn game_over_screen(
             
             ui.add_space(20.0);
             
-            let coins_earned = game_stats.score / 100;
+            let coins_earned = game_stats.coins_earned();
             ui.label(egui::RichText::new(format!("💰 Coins Earned: {coins_earned}")).size(20.0).color(egui::Color32::YELLOW));
             
+            ui.add_space(10.0);
+            ui.label(egui::RichText::new(format!("🌱 Seed: {}", rng.seed)).size(18.0).color(egui::Color32::LIGHT_GRAY));
+            
             ui.add_space(40.0);
             
-            if ui.add_sized([200.0, 50.0], egui::Button::new(egui::RichText::new("Play Again").size(20.0))).clicked() {
+            let play_again = ui.add_sized([200.0, 50.0], egui::Button::new(egui::RichText::new("Play Again").size(20.0))).clicked();
+            ui.add_space(10.0);
+            let replay_seed = ui.add_sized([200.0, 50.0], egui::Button::new(egui::RichText::new("Replay Seed").size(20.0))).clicked();
+            
+            if play_again || replay_seed {
+                if replay_seed {
+                    seed_settings.replay = Some(rng.seed);
+                }
+                
                 // Reset game stats for play again
                 game_stats.score = 0;
                 game_stats.combo = 0;
                 game_stats.targets_hit = 0;
                 game_stats.time_played = 0.0;
+                game_stats.bonus_coins = 0;
                 
                 // Reset timer based on game mode
-                match game_mode.mode {
-                    GameMode::TimeAttack => {
-                        challenge_timer.time_remaining = 60.0;
-                        challenge_timer.total_time = 60.0;
-                    }
-                    GameMode::Survival => {
-                        challenge_timer.time_remaining = 10.0;
-                        challenge_timer.total_time = 10.0;
-                    }
-                    GameMode::RaceTheClock => {
-                        challenge_timer.time_remaining = 120.0;
-                        challenge_timer.total_time = 120.0;
-                    }
-                    _ => {}
-                }
+                *challenge_timer = ChallengeTimer::for_mode(game_mode.mode);
                 
                 game_state.set(GameState::Playing);
             }
@@ -461,8 +894,10 @@ pub fn upgrade_shop_ui(
     mut game_state: ResMut<NextState<GameState>>,
     mut game_stats: ResMut<GameStats>,
     mut upgrades: ResMut<UpgradeData>,
+    save_file: Res<crate::save::SaveFile>,
 ) {
     let ctx = contexts.ctx_mut();
+    let mut purchased = false;
     
     egui::CentralPanel::default().show(ctx, |ui| {
         ui.vertical_centered(|ui| {
@@ -486,6 +921,7 @@ pub fn upgrade_shop_ui(
                 if ui.add_enabled(can_afford, egui::Button::new(format!("Upgrade (💰 {cost})"))).clicked() {
                     game_stats.coins -= cost;
                     upgrades.speed_level += 1;
+                    purchased = true;
                 }
             });
             
@@ -505,6 +941,7 @@ pub fn upgrade_shop_ui(
                 if ui.add_enabled(can_afford, egui::Button::new(format!("Upgrade (💰 {cost})"))).clicked() {
                     game_stats.coins -= cost;
                     upgrades.maneuverability_level += 1;
+                    purchased = true;
                 }
             });
             
@@ -524,6 +961,7 @@ pub fn upgrade_shop_ui(
                 if ui.add_enabled(can_afford, egui::Button::new(format!("Upgrade (💰 {cost})"))).clicked() {
                     game_stats.coins -= cost;
                     upgrades.magnet_level += 1;
+                    purchased = true;
                 }
             });
             
@@ -543,6 +981,7 @@ pub fn upgrade_shop_ui(
                 if ui.add_enabled(can_afford, egui::Button::new(format!("Upgrade (💰 {cost})"))).clicked() {
                     game_stats.coins -= cost;
                     upgrades.multiplier_level += 1;
+                    purchased = true;
                 }
             });
             
@@ -553,4 +992,9 @@ pub fn upgrade_shop_ui(
             }
         });
     });
+    
+    // Persist immediately so spent coins can't be refunded by quitting
+    if purchased {
+        crate::save::save_progress(&save_file, &game_stats, &upgrades);
+    }
 }
\ No newline at end of file
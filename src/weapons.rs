@@ -0,0 +1,290 @@
+use bevy::prelude::*;
+use crate::{Aircraft, replay::PlayerInput};
+use crate::assets::{SharedAssets, Tint};
+use crate::enemies::PlayerBullet;
+use crate::missiles::{Missile, MissileLock, launch_missile};
+use crate::particles::{ParticleBurst, ParticlePreset};
+use crate::physics::InterpolatedTransform;
+use crate::pool::EntityPool;
+use crate::powerups::{PowerUpEffect, PowerUpType};
+
+// Heat lost per second, out of a full bar of 1
+const HEAT_DISSIPATION: f32 = 0.35;
+// An overheated weapon fires again once it has cooled back down to this
+const OVERHEAT_RECOVERED: f32 = 0.25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Projectile {
+    Bullet,
+    Missile,
+}
+
+// Everything about how a weapon fires. Power-ups change these stats for as long as they last.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Weapon {
+    pub name: &'static str,
+    pub projectile: Projectile,
+    // Muzzle speed for bullets, top speed for missiles
+    pub projectile_speed: f32,
+    pub damage: f32,
+    // Distance between neighbouring projectiles of one shot, spaced out across the wings
+    pub spread: f32,
+    pub cooldown: f32,
+    pub projectile_count: u32,
+    // Rounds carried at most; None never runs out
+    pub ammo: Option<u32>,
+    // Heat each shot adds; a weapon that reaches 1 overheats. Zero never overheats.
+    pub heat_per_shot: f32,
+    pub tint: Tint,
+}
+
+impl Weapon {
+    pub const CANNON: Weapon = Weapon {
+        name: "Cannon",
+        projectile: Projectile::Bullet,
+        projectile_speed: 250.0,
+        damage: 25.0,
+        spread: 4.0,
+        cooldown: 0.25,
+        projectile_count: 2,
+        ammo: None,
+        heat_per_shot: 0.12,
+        tint: Tint::Player,
+    };
+
+    pub const HOMING_MISSILES: Weapon = Weapon {
+        name: "Homing Missiles",
+        projectile: Projectile::Missile,
+        projectile_speed: 220.0,
+        damage: 60.0,
+        spread: 0.0,
+        cooldown: 1.0,
+        projectile_count: 1,
+        ammo: Some(6),
+        heat_per_shot: 0.0,
+        tint: Tint::Missile,
+    };
+
+    pub const ROCKETS: Weapon = Weapon {
+        name: "Rockets",
+        projectile: Projectile::Bullet,
+        projectile_speed: 180.0,
+        damage: 40.0,
+        spread: 6.0,
+        cooldown: 0.6,
+        projectile_count: 2,
+        ammo: Some(24),
+        heat_per_shot: 0.0,
+        tint: Tint::Rocket,
+    };
+
+    // What an active power-up does to the primary weapon
+    pub fn boosted(mut self, power_type: PowerUpType) -> Self {
+        match power_type {
+            PowerUpType::RapidFire => {
+                self.cooldown *= 0.4;
+                self.heat_per_shot *= 0.5;
+            }
+            PowerUpType::TripleShot => {
+                self.projectile_count += 1;
+                self.tint = Tint::Triple;
+            }
+            _ => {}
+        }
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct WeaponSlot {
+    // Stats as the weapon is fired right now, i.e. `base` with any power-ups applied
+    pub weapon: Weapon,
+    pub base: Weapon,
+    pub ammo: Option<u32>,
+    pub heat: f32,
+    pub overheated: bool,
+    pub cooldown: f32,
+}
+
+impl WeaponSlot {
+    pub fn new(weapon: Weapon) -> Self {
+        Self {
+            weapon,
+            base: weapon,
+            ammo: weapon.ammo,
+            heat: 0.0,
+            overheated: false,
+            cooldown: 0.0,
+        }
+    }
+
+    pub fn has_ammo(&self) -> bool {
+        self.ammo != Some(0)
+    }
+
+    pub fn ready(&self) -> bool {
+        self.cooldown <= 0.0 && !self.overheated && self.has_ammo()
+    }
+
+    // Takes the ammo and heat for one shot and starts the cooldown
+    pub fn fire(&mut self) {
+        self.cooldown = self.weapon.cooldown;
+        if let Some(ammo) = &mut self.ammo {
+            *ammo -= 1;
+        }
+        self.heat += self.weapon.heat_per_shot;
+        if self.heat >= 1.0 {
+            self.heat = 1.0;
+            self.overheated = true;
+        }
+    }
+
+    pub fn cool(&mut self, delta: f32) {
+        self.cooldown -= delta;
+        self.heat = (self.heat - HEAT_DISSIPATION * delta).max(0.0);
+        if self.overheated && self.heat <= OVERHEAT_RECOVERED {
+            self.overheated = false;
+        }
+    }
+
+    // Adds up to `rounds`, never past the weapon's capacity
+    pub fn refill(&mut self, rounds: u32) {
+        if let (Some(ammo), Some(capacity)) = (&mut self.ammo, self.base.ammo) {
+            *ammo = (*ammo + rounds).min(capacity);
+        }
+    }
+}
+
+// The player's guns: a primary on the trigger, and secondaries fired with their own button,
+// one at a time, cycled through with another
+#[derive(Resource, Debug, Clone)]
+pub struct Loadout {
+    pub primary: WeaponSlot,
+    pub secondaries: Vec<WeaponSlot>,
+    pub selected: usize,
+    // Whether the cycle button was down last step, so holding it only cycles once
+    cycle_held: bool,
+}
+
+impl Default for Loadout {
+    fn default() -> Self {
+        Self {
+            primary: WeaponSlot::new(Weapon::CANNON),
+            secondaries: vec![WeaponSlot::new(Weapon::HOMING_MISSILES), WeaponSlot::new(Weapon::ROCKETS)],
+            selected: 0,
+            cycle_held: false,
+        }
+    }
+}
+
+impl Loadout {
+    pub fn secondary(&self) -> Option<&WeaponSlot> {
+        self.secondaries.get(self.selected)
+    }
+
+    // Refills the first secondary firing `projectile`
+    pub fn refill(&mut self, projectile: Projectile, rounds: u32) {
+        if let Some(slot) = self.secondaries.iter_mut().find(|slot| slot.weapon.projectile == projectile) {
+            slot.refill(rounds);
+        }
+    }
+
+    pub fn refill_all(&mut self) {
+        for slot in self.secondaries.iter_mut() {
+            slot.ammo = slot.base.ammo;
+        }
+    }
+}
+
+pub fn reset_loadout(mut loadout: ResMut<Loadout>) {
+    *loadout = Loadout::default();
+}
+
+// Recomputes the primary weapon's stats from the power-ups active this step
+pub fn apply_weapon_powerups(mut loadout: ResMut<Loadout>, effects: Query<&PowerUpEffect>) {
+    let primary = &mut loadout.primary;
+    primary.weapon = effects
+        .iter()
+        .fold(primary.base, |weapon, effect| weapon.boosted(effect.effect_type));
+}
+
+pub fn cycle_secondary_weapon(input: Res<PlayerInput>, mut loadout: ResMut<Loadout>) {
+    if input.cycle_secondary && !loadout.cycle_held && !loadout.secondaries.is_empty() {
+        loadout.selected = (loadout.selected + 1) % loadout.secondaries.len();
+    }
+    loadout.cycle_held = input.cycle_secondary;
+}
+
+pub fn fire_player_weapons(
+    mut commands: Commands,
+    shared_assets: Res<SharedAssets>,
+    mut bullet_pool: ResMut<EntityPool<PlayerBullet>>,
+    mut missile_pool: ResMut<EntityPool<Missile>>,
+    mut bursts: EventWriter<ParticleBurst>,
+    player_query: Query<(&Transform, &Aircraft)>,
+    input: Res<PlayerInput>,
+    time: Res<Time>,
+    mut loadout: ResMut<Loadout>,
+    lock: Res<MissileLock>,
+) {
+    let delta = time.delta_secs();
+    let Loadout { primary, secondaries, selected, .. } = &mut *loadout;
+    primary.cool(delta);
+    for slot in secondaries.iter_mut() {
+        slot.cool(delta);
+    }
+
+    let Ok((player_transform, aircraft)) = player_query.single() else {
+        return;
+    };
+    let secondary = secondaries.get_mut(*selected).filter(|_| input.fire_secondary);
+    for slot in std::iter::once(primary).filter(|_| input.fire).chain(secondary) {
+        if !slot.ready() {
+            continue;
+        }
+        slot.fire();
+        let weapon = slot.weapon;
+
+        match weapon.projectile {
+            Projectile::Missile => launch_missile(
+                &mut commands,
+                &mut missile_pool,
+                &shared_assets,
+                player_transform,
+                aircraft.current_speed,
+                lock.target,
+                &weapon,
+            ),
+            Projectile::Bullet => {
+                // Spaced evenly either side of the centreline
+                let first_offset = -(weapon.projectile_count as f32 - 1.0) * weapon.spread * 0.5;
+                for index in 0..weapon.projectile_count {
+                    let offset = first_offset + index as f32 * weapon.spread;
+                    let bullet_spawn = player_transform.translation
+                        + player_transform.forward() * 5.0
+                        + player_transform.right() * offset;
+
+                    let bullet_transform = Transform::from_translation(bullet_spawn).with_scale(Vec3::splat(0.3));
+                    bullet_pool.spawn(&mut commands, (
+                        Mesh3d(shared_assets.sphere.clone()),
+                        MeshMaterial3d(shared_assets.glow(weapon.tint)),
+                        bullet_transform,
+                        InterpolatedTransform::new(bullet_transform),
+                        PlayerBullet {
+                            velocity: player_transform.forward() * weapon.projectile_speed,
+                            damage: weapon.damage,
+                            lifetime: 3.0,
+                        },
+                    ));
+                    bursts.write(ParticleBurst {
+                        preset: ParticlePreset::MuzzleFlash,
+                        position: bullet_spawn,
+                        velocity: player_transform.forward() * 20.0,
+                        count: 4,
+                        color: None,
+                    });
+                }
+            }
+        }
+    }
+}